@@ -0,0 +1,114 @@
+use abstract_game_engine::core::agent::{Agent, DefaultAgents};
+use abstract_game_engine::core::alphabeta::AlphaBetaAgent;
+use abstract_game_engine::core::alphazero::{AlphaZero, PuctConfig};
+use abstract_game_engine::core::encoder::Encoder;
+use abstract_game_engine::core::ismcts::IsmctsAgent;
+use abstract_game_engine::core::mlp::Mlp;
+use abstract_game_engine::core::simulator::Simulator;
+use abstract_game_engine::domains::connect4::connect4_action::Connect4Action;
+use abstract_game_engine::domains::connect4::connect4_encoder::Connect4Encoder;
+use abstract_game_engine::domains::connect4::connect4_evaluator::ThreatEvaluator;
+use abstract_game_engine::domains::connect4::connect4_simulator::Connect4Simulator;
+use abstract_game_engine::domains::yahtzee::yahtzee_action::YahtzeeAction;
+use abstract_game_engine::domains::yahtzee::yahtzee_simulator::YahtzeeSimulator;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+const SEED: u64 = 0;
+
+/// Search iterations per selected action, reported as elements per second.
+const SEARCH_ITERATIONS: usize = 100;
+
+/// Plies searched by the alpha-beta agent.
+const SEARCH_DEPTH: u32 = 6;
+
+/// Entries in the alpha-beta agent's transposition table.
+const TABLE_CAPACITY: usize = 1 << 16;
+
+/// Hidden layer sizes of the AlphaZero network.
+const HIDDEN_SIZES: [usize; 2] = [64, 64];
+
+fn random_agent_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("random_agent");
+
+    let mut simulator = Connect4Simulator::new();
+    let state = simulator.generate_initial_state();
+    let mut agent = DefaultAgents::Random(ChaCha8Rng::seed_from_u64(SEED));
+    group.bench_function("connect4_select_action", |b| {
+        b.iter(|| -> Connect4Action { agent.select_action(0, black_box(&state), &mut simulator) })
+    });
+
+    let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(SEED));
+    let state = simulator.generate_initial_state();
+    let mut agent = DefaultAgents::Random(ChaCha8Rng::seed_from_u64(SEED));
+    group.bench_function("yahtzee_select_action", |b| {
+        b.iter(|| -> YahtzeeAction { agent.select_action(0, black_box(&state), &mut simulator) })
+    });
+
+    group.finish();
+}
+
+fn ismcts_agent_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ismcts_agent");
+    group.throughput(Throughput::Elements(SEARCH_ITERATIONS as u64));
+
+    let mut simulator = Connect4Simulator::new();
+    let state = simulator.generate_initial_state();
+    let mut agent = IsmctsAgent::new(SEARCH_ITERATIONS, 1.0, ChaCha8Rng::seed_from_u64(SEED));
+    group.bench_function("connect4_select_action", |b| {
+        b.iter(|| -> Connect4Action { Agent::select_action(&mut agent, 0, black_box(&state), &mut simulator) })
+    });
+
+    let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(SEED));
+    let state = simulator.generate_initial_state();
+    let mut agent = IsmctsAgent::new(SEARCH_ITERATIONS, 100.0, ChaCha8Rng::seed_from_u64(SEED));
+    group.bench_function("yahtzee_select_action", |b| {
+        b.iter(|| -> YahtzeeAction { Agent::select_action(&mut agent, 0, black_box(&state), &mut simulator) })
+    });
+
+    group.finish();
+}
+
+/// Every search starts from a new agent, so that it cannot reuse the
+/// transposition table of the last one and visits the same number of
+/// nodes, which is reported as elements per second.
+fn alpha_beta_agent_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("alpha_beta_agent");
+
+    let mut simulator = Connect4Simulator::new();
+    let state = simulator.generate_initial_state();
+    let mut agent = AlphaBetaAgent::new(SEARCH_DEPTH, ThreatEvaluator, TABLE_CAPACITY);
+    let _: Connect4Action = agent.select_action(0, &state, &mut simulator);
+    group.throughput(Throughput::Elements(agent.nodes() as u64));
+    group.bench_function("connect4_select_action", |b| {
+        b.iter_batched(
+            || AlphaBetaAgent::new(SEARCH_DEPTH, ThreatEvaluator, TABLE_CAPACITY),
+            |mut agent| -> Connect4Action { agent.select_action(0, black_box(&state), &mut simulator) },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn alphazero_agent_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("alphazero_agent");
+    group.throughput(Throughput::Elements(SEARCH_ITERATIONS as u64));
+
+    let mut simulator = Connect4Simulator::new();
+    let state = simulator.generate_initial_state();
+    let mut rng = ChaCha8Rng::seed_from_u64(SEED);
+    let encoder = Connect4Encoder;
+    let network = Mlp::new(encoder.input_size(), &HIDDEN_SIZES, encoder.policy_size(), 2, &mut rng);
+    let config = PuctConfig { simulations: SEARCH_ITERATIONS, ..PuctConfig::default() };
+    let mut agent = AlphaZero::new(network, encoder, config, rng);
+    group.bench_function("connect4_select_action", |b| {
+        b.iter(|| -> Connect4Action { agent.select_action(0, black_box(&state), &mut simulator) })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, random_agent_benchmarks, ismcts_agent_benchmarks, alpha_beta_agent_benchmarks, alphazero_agent_benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+use abstract_game_engine::core::simulator::{Action, Simulator, State};
+use abstract_game_engine::core::testing::{random_playout, DEFAULT_MAX_STEPS};
+use abstract_game_engine::domains::connect4::connect4_pop_simulator::{Connect4PopSimulator, PopVariant};
+use abstract_game_engine::domains::connect4::connect4_simulator::Connect4Simulator;
+use abstract_game_engine::domains::connectn::connectn_simulator::ConnectNSimulator;
+use abstract_game_engine::domains::gomoku::gomoku_simulator::GomokuSimulator;
+use abstract_game_engine::domains::mnk::mnk_simulator::MnkSimulator;
+use abstract_game_engine::domains::othello::othello_simulator::OthelloSimulator;
+use abstract_game_engine::domains::yahtzee::yahtzee_simulator::YahtzeeSimulator;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+const SEED: u64 = 0;
+
+/// Pairs each non-terminal state with one legal joint action from it.
+fn transitions<S, A, I>(simulator: &mut I, states: &[S]) -> Vec<(S, Vec<Option<A>>)> where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    states.iter()
+        .filter_map(|state| {
+            if simulator.is_terminal_state(state) {
+                return None;
+            }
+            let actions = simulator.calculate_legal_actions(state).iter()
+                .map(|legal_actions| legal_actions.iter().next().cloned())
+                .collect();
+            Some((state.clone(), actions))
+        })
+        .collect()
+}
+
+/// Benchmarks random playouts, legal action calculation and state
+/// transitions of a domain, starting each batch of playouts from a new
+/// simulator so that simulators with caches start from empty ones.
+fn bench_simulator<S, A, I, F>(c: &mut Criterion, name: &str, make_simulator: F) where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+    F: Fn() -> I,
+{
+    let mut group = c.benchmark_group(name);
+    let mut simulator = make_simulator();
+    let mut rng = ChaCha8Rng::seed_from_u64(SEED);
+    let states = random_playout(&mut simulator, &mut rng, DEFAULT_MAX_STEPS).unwrap();
+    let transitions = transitions(&mut simulator, &states);
+
+    group.bench_function("random_playout", |b| {
+        b.iter_batched(
+            &make_simulator,
+            |mut simulator| random_playout(&mut simulator, &mut rng, DEFAULT_MAX_STEPS).unwrap().len(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("calculate_legal_actions", |b| {
+        b.iter(|| {
+            for state in &states {
+                black_box(simulator.calculate_legal_actions(black_box(state)));
+            }
+        })
+    });
+    group.bench_function("state_transition", |b| {
+        b.iter(|| {
+            for (state, actions) in &transitions {
+                black_box(simulator.state_transition(black_box(state), black_box(actions)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn simulator_benchmarks(c: &mut Criterion) {
+    bench_simulator(c, "connect4", Connect4Simulator::new);
+    bench_simulator(c, "connectn", || ConnectNSimulator::new(11, 9, 5).unwrap());
+    bench_simulator(c, "gomoku", GomokuSimulator::freestyle);
+    bench_simulator(c, "mnk", MnkSimulator::tic_tac_toe);
+    bench_simulator(c, "othello", OthelloSimulator::new);
+    bench_simulator(c, "pop_out", || Connect4PopSimulator::new(PopVariant::PopOut));
+    bench_simulator(c, "pop_10", || Connect4PopSimulator::new(PopVariant::Pop10));
+    bench_simulator(c, "yahtzee", || YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(SEED)));
+}
+
+criterion_group!(benches, simulator_benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+//! Trains an AlphaZero network for Connect 4 on the CPU.
+//!
+//! Usage: `cargo run --release --example connect4_alphazero [iterations] [checkpoint directory]`
+
+use std::path::PathBuf;
+
+use abstract_game_engine::core::alphazero::{AlphaZero, PuctConfig, TrainingConfig};
+use abstract_game_engine::core::encoder::Encoder;
+use abstract_game_engine::core::mlp::Mlp;
+use abstract_game_engine::domains::connect4::connect4_encoder::Connect4Encoder;
+use abstract_game_engine::domains::connect4::connect4_simulator::Connect4Simulator;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+const SEED: u64 = 0;
+const HIDDEN_SIZES: [usize; 2] = [128, 128];
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let iterations = args.next().and_then(|arg| arg.parse::<usize>().ok()).unwrap_or(10);
+    let directory = PathBuf::from(args.next().unwrap_or("checkpoints".to_string()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let mut rng = ChaCha8Rng::seed_from_u64(SEED);
+    let encoder = Connect4Encoder;
+    let network = Mlp::new(encoder.input_size(), &HIDDEN_SIZES, encoder.policy_size(), 2, &mut rng);
+    let mut alphazero = AlphaZero::new(network, encoder, PuctConfig::default(), rng);
+    let mut simulator = Connect4Simulator::new();
+    let training = TrainingConfig::default();
+
+    for iteration in 1..=iterations {
+        let losses = alphazero.train_iterations(&mut simulator, 1, &training, Some(&directory)).unwrap();
+        println!("iteration {iteration}: loss {:.4}", losses[0]);
+    }
+}
@@ -0,0 +1,41 @@
+//! Records Connect 4 games played by ISMCTS agents as a training dataset.
+//!
+//! Usage: `cargo run --release --example connect4_dataset [games] [directory] [bin|csv|npy]`
+
+use std::path::PathBuf;
+
+use abstract_game_engine::core::dataset::{record_game, DatasetFormat, DatasetWriter};
+use abstract_game_engine::core::ismcts::IsmctsAgent;
+use abstract_game_engine::domains::connect4::connect4_encoder::Connect4Encoder;
+use abstract_game_engine::domains::connect4::connect4_simulator::Connect4Simulator;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+const SEED: u64 = 0;
+const ITERATIONS: usize = 200;
+const SHARD_SIZE: usize = 10_000;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let games = args.next().and_then(|arg| arg.parse::<usize>().ok()).unwrap_or(10);
+    let directory = PathBuf::from(args.next().unwrap_or("dataset".to_string()));
+    let format = match args.next().as_deref() {
+        Some("csv") => DatasetFormat::Csv,
+        Some("npy") => DatasetFormat::Npy,
+        _ => DatasetFormat::Binary,
+    };
+
+    let mut agents = [
+        IsmctsAgent::new(ITERATIONS, 1.0, ChaCha8Rng::seed_from_u64(SEED)),
+        IsmctsAgent::new(ITERATIONS, 1.0, ChaCha8Rng::seed_from_u64(SEED + 1)),
+    ];
+    let mut simulator = Connect4Simulator::new();
+    let mut writer = DatasetWriter::new(&directory, format, SHARD_SIZE, Some(ChaCha8Rng::seed_from_u64(SEED)));
+    for _ in 0..games {
+        writer.add(record_game(&mut simulator, &mut agents, &Connect4Encoder));
+    }
+    println!("{} records from {} games", writer.len(), games);
+    for path in writer.finish().unwrap() {
+        println!("{}", path.display());
+    }
+}
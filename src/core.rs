@@ -1,4 +1,23 @@
 pub mod agent;
+pub mod alphabeta;
+pub mod alphazero;
+pub mod cfr;
+pub mod dataset;
+pub mod encoder;
+pub mod evaluator;
+pub mod game;
 pub mod history;
+pub mod imperfect_info;
+pub mod ismcts;
+pub mod mlp;
+pub mod observer;
+pub mod perft;
+pub mod returns;
+pub mod regret;
 pub mod reward;
-pub mod simulator;
\ No newline at end of file
+pub mod simulator;
+pub mod stage_game;
+pub mod symmetry;
+pub mod tabular;
+pub mod testing;
+pub mod tt;
\ No newline at end of file
@@ -3,14 +3,20 @@ use rand_chacha::ChaCha8Rng;
 use std::fmt;
 use std::io;
 
+use super::imperfect_info::ImperfectInfoSimulator;
+use super::imperfect_info::Observation;
 use super::simulator::Action;
+use super::simulator::LegalActions;
 use super::simulator::Simulator;
 use super::simulator::State;
 
 /// An agent interacts in a domain by selecting
 /// an action from a list of legal actions for
 /// a player from the current state.
-pub trait Agent {
+///
+/// Agents are implemented for the state and action types they can
+/// play, either those of one domain or any types.
+pub trait Agent<S : State, A : Action> {
     /// Selects an action for a given player.
     /// 
     /// Will panic if the player has no legal actions.
@@ -20,17 +26,65 @@ pub trait Agent {
     /// * `player_id` - The ID that indicates which player tha agent is using to select an action.
     /// * `state` - The current domain state.
     /// * `simulator` - The simulator that determines action outcomes in the domain.
+    ///   The game loop passes a `fork`, so the agent cannot change the game.
     /// 
     /// ### Return Value
     /// 
     /// The selected action from the current state.
-    fn select_action<S, A, I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
-        S: State,
-        A: Action,
+    fn select_action<I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
         I: Simulator<S, A>;
+
+    /// Diagnostic information about the most recently selected action,
+    /// such as search statistics, for logging and visualization.
+    fn action_info(&self) -> Option<String> {
+        None
+    }
+
+    /// The probability the agent gave each legal action, such as a search's
+    /// visit distribution, when it selected its most recent action.
+    fn action_policy(&self) -> Option<Vec<(A, f64)>> {
+        None
+    }
+}
+
+/// An agent for domains with hidden information. Unlike `Agent`, it
+/// never receives the domain state, only what its player can observe.
+pub trait ImperfectInfoAgent<S : State, A : Action, O : Observation> {
+    /// Selects an action for a given player.
+    ///
+    /// Will panic if the player has no legal actions.
+    ///
+    /// ### Arguments
+    ///
+    /// * `player_id` - The ID that indicates which player the agent is using to select an action.
+    /// * `observation` - The player's observation of the current state.
+    /// * `action_history` - The actions visible to the player for each previous state transition.
+    /// * `legal_actions` - The player's legal actions from the current state.
+    /// * `simulator` - The simulator that determines action outcomes in the domain.
+    ///   The game loop passes a `fork`, so the agent cannot change the game.
+    ///
+    /// ### Return Value
+    ///
+    /// The selected action from the current state.
+    fn select_action<I>(
+        &mut self,
+        player_id: usize,
+        observation: &O,
+        action_history: &[Vec<Option<A>>],
+        legal_actions: &LegalActions<A>,
+        simulator: &mut I,
+    ) -> A where
+        I: ImperfectInfoSimulator<S, A, O>;
+
+    /// Diagnostic information about the most recently selected action,
+    /// such as search statistics, for logging and visualization.
+    fn action_info(&self) -> Option<String> {
+        None
+    }
 }
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum DefaultAgents {
     /// Selects an action by prompting the user.
     Io,
@@ -38,14 +92,35 @@ pub enum DefaultAgents {
     Random(ChaCha8Rng),
 }
 
-impl Agent for DefaultAgents {
-    fn select_action<S, A, I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
-        S: State,
-        A: Action,
+impl <S : State, A : Action> Agent<S, A> for DefaultAgents {
+    fn select_action<I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
         I: Simulator<S, A>,
     {
-        let player_legal_actions = &simulator.calculate_legal_actions(&state)[player_id];
-        
+        let player_legal_actions = &simulator.calculate_legal_actions(state)[player_id];
+        return self.select_legal_action(player_legal_actions);
+    }
+}
+
+impl <S : State, A : Action, O : Observation> ImperfectInfoAgent<S, A, O> for DefaultAgents {
+    fn select_action<I>(
+        &mut self,
+        _player_id: usize,
+        observation: &O,
+        _action_history: &[Vec<Option<A>>],
+        legal_actions: &LegalActions<A>,
+        _simulator: &mut I,
+    ) -> A where
+        I: ImperfectInfoSimulator<S, A, O>,
+    {
+        if let DefaultAgents::Io = self {
+            println!("{}", observation);
+        }
+        return self.select_legal_action(legal_actions);
+    }
+}
+
+impl DefaultAgents {
+    fn select_legal_action<A : Action>(&mut self, player_legal_actions: &LegalActions<A>) -> A {
         match self {
             DefaultAgents::Io => {
                 let mut input = String::new();
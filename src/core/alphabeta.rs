@@ -0,0 +1,297 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use super::agent::Agent;
+use super::evaluator::Evaluator;
+use super::simulator::{Action, Simulator};
+use super::tt::{ReplacementPolicy, TranspositionTable, ZobristHash};
+
+/// How much a terminal reward is increased for every remaining ply of
+/// search depth, so that quicker wins and slower losses are preferred.
+const DEPTH_BONUS: f64 = 1e-3;
+
+/// Seeds the simulator that each search runs on.
+const SEARCH_SEED: u64 = 0;
+
+/// Whether a stored value is exact or only a bound
+/// because the search was cut off by the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// A search result stored in the transposition table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchValue<A : Action> {
+    /// The value for the player to move.
+    pub value: f64,
+    pub bound: Bound,
+    pub best_action: Option<A>,
+}
+
+/// Negamax search with alpha-beta pruning to a fixed depth, estimating
+/// the value of states where the search stops with an evaluator.
+///
+/// Made for two-player zero-sum games in which at most one player acts
+/// in any state. Searched states are stored in a transposition table by
+/// their Zobrist hash, which is updated incrementally from each state to
+/// the next, and the best action of a stored state is searched first.
+/// The search runs on a fork of the simulator that is seeded the same
+/// way for every search, which only matters for games with chance.
+#[derive(Debug)]
+pub struct AlphaBetaAgent<A : Action, E> {
+    depth: u32,
+    evaluator: E,
+    table: TranspositionTable<SearchValue<A>>,
+    nodes: usize,
+    info: Option<String>,
+}
+
+impl <A : Action, E> AlphaBetaAgent<A, E> {
+    /// Creates an alpha-beta agent.
+    ///
+    /// ### Arguments
+    ///
+    /// * `depth` - The number of plies searched from each state.
+    /// * `evaluator` - Estimates the value of states where the search stops.
+    /// * `table_capacity` - The number of entries in the transposition table.
+    pub fn new(depth: u32, evaluator: E, table_capacity: usize) -> Self {
+        AlphaBetaAgent {
+            depth,
+            evaluator,
+            table: TranspositionTable::new(table_capacity, ReplacementPolicy::DepthPreferred),
+            nodes: 0,
+            info: None,
+        }
+    }
+
+    pub fn table(&self) -> &TranspositionTable<SearchValue<A>> {
+        &self.table
+    }
+
+    /// The number of states visited by the last search.
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// Searches the state after each of a player's legal actions,
+    /// to one ply less than `select_action` searches.
+    ///
+    /// ### Arguments
+    ///
+    /// * `player_id` - The player acting in the state.
+    /// * `state` - The state to search from.
+    /// * `simulator` - The simulator that determines action outcomes in the domain.
+    ///
+    /// ### Return Value
+    ///
+    /// Every legal action of the player with its value for the player.
+    pub fn action_values<S, I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> Vec<(A, f64)> where
+        S: ZobristHash,
+        E: Evaluator<S>,
+        I: Simulator<S, A>,
+    {
+        self.table.new_search();
+        let simulator = &mut simulator.fork(&mut ChaCha8Rng::seed_from_u64(SEARCH_SEED));
+        let hash = state.zobrist_hash();
+        let mut actions = simulator.calculate_legal_actions(state).swap_remove(player_id).0.into_iter().collect::<Vec<A>>();
+        actions.sort_by_cached_key(|action| action.to_string());
+        return actions.into_iter()
+            .map(|action| {
+                let mut joint_action = vec![None; simulator.number_of_players()];
+                joint_action[player_id] = Some(action.clone());
+                let next_state = simulator.state_transition(state, &joint_action);
+                let next_hash = state.updated_zobrist_hash(hash, &next_state);
+                let depth = self.depth.max(1) - 1;
+                let value = self.child_value(simulator, &next_state, next_hash, player_id, depth, f64::NEG_INFINITY, f64::INFINITY);
+                (action, value)
+            })
+            .collect();
+    }
+
+    /// Searches a state where a player acts.
+    ///
+    /// ### Arguments
+    ///
+    /// * `hash` - The Zobrist hash of the state.
+    ///
+    /// ### Return Value
+    ///
+    /// The value of the state for the acting player and the best action.
+    #[allow(clippy::too_many_arguments)]
+    fn search<S, I>(&mut self, simulator: &mut I, state: &S, hash: u64, player_id: usize, depth: u32, mut alpha: f64, beta: f64) -> (f64, Option<A>) where
+        S: ZobristHash,
+        E: Evaluator<S>,
+        I: Simulator<S, A>,
+    {
+        self.nodes += 1;
+        if depth == 0 {
+            return (self.evaluator.evaluate(state)[player_id], None);
+        }
+
+        let mut actions = simulator.calculate_legal_actions(state).swap_remove(player_id).0.into_iter().collect::<Vec<A>>();
+        let mut best_action = None;
+        if let Some(entry) = self.table.get(hash) {
+            best_action = entry.value.best_action.clone().filter(|action| actions.contains(action));
+            let value = entry.value.value;
+            let cutoff = entry.depth >= depth && match entry.value.bound {
+                Bound::Exact => true,
+                Bound::Lower => value >= beta,
+                Bound::Upper => value <= alpha,
+            };
+            if cutoff && best_action.is_some() {
+                return (value, best_action);
+            }
+        }
+        // Search the stored best action first, then in a fixed order.
+        actions.sort_by_cached_key(|action| (Some(action) != best_action.as_ref(), action.to_string()));
+
+        let original_alpha = alpha;
+        let mut best: (f64, Option<A>) = (f64::NEG_INFINITY, None);
+        for action in actions {
+            let mut joint_action = vec![None; simulator.number_of_players()];
+            joint_action[player_id] = Some(action.clone());
+            let next_state = simulator.state_transition(state, &joint_action);
+            let next_hash = state.updated_zobrist_hash(hash, &next_state);
+            let value = self.child_value(simulator, &next_state, next_hash, player_id, depth - 1, alpha, beta);
+            if value > best.0 {
+                best = (value, Some(action));
+            }
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best.0 <= original_alpha {
+            Bound::Upper
+        } else if best.0 >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.insert(hash, depth, SearchValue { value: best.0, bound, best_action: best.1.clone() });
+        return best;
+    }
+
+    /// The value of a state for the player who acted before it.
+    #[allow(clippy::too_many_arguments)]
+    fn child_value<S, I>(&mut self, simulator: &mut I, state: &S, hash: u64, player_id: usize, depth: u32, alpha: f64, beta: f64) -> f64 where
+        S: ZobristHash,
+        E: Evaluator<S>,
+        I: Simulator<S, A>,
+    {
+        let legal_actions = simulator.calculate_legal_actions(state);
+        let acting_player = (0..legal_actions.len()).find(|&i| !legal_actions[i].0.is_empty());
+        return match acting_player {
+            None => {
+                self.nodes += 1;
+                simulator.calculate_rewards(state)[player_id].0 * (1.0 + DEPTH_BONUS * depth as f64)
+            },
+            Some(acting_player) if acting_player == player_id => self.search(simulator, state, hash, acting_player, depth, alpha, beta).0,
+            Some(acting_player) => -self.search(simulator, state, hash, acting_player, depth, -beta, -alpha).0,
+        };
+    }
+}
+
+impl <S : ZobristHash, A : Action, E : Evaluator<S>> Agent<S, A> for AlphaBetaAgent<A, E> {
+    fn select_action<I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
+        I: Simulator<S, A>,
+    {
+        self.nodes = 0;
+        self.table.new_search();
+        let depth = self.depth.max(1);
+        let simulator = &mut simulator.fork(&mut ChaCha8Rng::seed_from_u64(SEARCH_SEED));
+        let (value, action) = self.search(simulator, state, state.zobrist_hash(), player_id, depth, f64::NEG_INFINITY, f64::INFINITY);
+        self.info = Some(format!("depth: {}, nodes: {}, value: {:.3}", depth, self.nodes, value));
+        return action.expect("Player should have legal actions.");
+    }
+
+    fn action_info(&self) -> Option<String> {
+        self.info.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::game::play_game;
+    use crate::core::reward::ADVERSARIAL_P1_WIN;
+    use crate::core::testing::counters::{CountersSimulator, CountersState, TestAction};
+
+    use super::*;
+
+    const MAX_COUNTERS: u8 = 10;
+
+    fn agent(depth: u32) -> AlphaBetaAgent<TestAction, NoEvaluator> {
+        return AlphaBetaAgent::new(depth, NoEvaluator, 1024);
+    }
+
+    #[test]
+    fn select_action_takes_winning_counters() {
+        let mut agent = agent(20);
+        let action = agent.select_action(0, &CountersState { counters: 7, turn: 0 }, &mut CountersSimulator::new(MAX_COUNTERS));
+        assert_eq!(action, TestAction(1));
+        let action = agent.select_action(1, &CountersState { counters: 5, turn: 1 }, &mut CountersSimulator::new(MAX_COUNTERS));
+        assert_eq!(action, TestAction(2));
+        assert!(agent.action_info().unwrap().starts_with("depth: 20"));
+    }
+
+    #[test]
+    fn search_stores_values_in_table() {
+        let mut agent = agent(20);
+        agent.select_action(0, &CountersState { counters: MAX_COUNTERS, turn: 0 }, &mut CountersSimulator::new(MAX_COUNTERS));
+        let root = CountersState { counters: MAX_COUNTERS, turn: 0 }.zobrist_hash();
+        let entry = agent.table().get(root).unwrap();
+        assert_eq!(entry.value.best_action, Some(TestAction(1)));
+        assert!(entry.value.value > 1.0);
+        assert!(agent.table().len() > 1);
+        assert!(agent.nodes() >= agent.table().len());
+    }
+
+    #[test]
+    fn shallow_search_uses_evaluator() {
+        let mut agent = AlphaBetaAgent::new(1, CountersEvaluator, 16);
+        let action = agent.select_action(0, &CountersState { counters: MAX_COUNTERS, turn: 0 }, &mut CountersSimulator::new(MAX_COUNTERS));
+        assert_eq!(action, TestAction(1));
+        assert!(agent.action_info().unwrap().ends_with("value: 0.500"));
+    }
+
+    #[test]
+    fn action_values_score_every_action() {
+        let mut agent = agent(20);
+        let values = agent.action_values(0, &CountersState { counters: 4, turn: 0 }, &mut CountersSimulator::new(MAX_COUNTERS));
+        assert_eq!(values.iter().map(|(action, _)| action.clone()).collect::<Vec<TestAction>>(), vec![TestAction(1), TestAction(2)]);
+        assert!(values[0].1 > 1.0);
+        assert!(values[1].1 < -1.0);
+    }
+
+    #[test]
+    fn agent_wins_as_first_player() {
+        let mut agents = [agent(20), agent(2)];
+        let history = play_game(&mut CountersSimulator::new(MAX_COUNTERS), &mut agents, &mut []);
+        assert_eq!(history.outcome(), Some(&ADVERSARIAL_P1_WIN[..]));
+    }
+
+    /// Estimates every state as a draw.
+    struct NoEvaluator;
+
+    impl Evaluator<CountersState> for NoEvaluator {
+        fn evaluate(&self, _state: &CountersState) -> Vec<f64> {
+            vec![0.0, 0.0]
+        }
+    }
+
+    /// The player to move loses when the counters are a multiple of three.
+    struct CountersEvaluator;
+
+    impl Evaluator<CountersState> for CountersEvaluator {
+        fn evaluate(&self, state: &CountersState) -> Vec<f64> {
+            let value = if state.counters.is_multiple_of(3) { -0.5 } else { 0.5 };
+            let mut values = vec![-value, -value];
+            values[state.turn] = value;
+            return values;
+        }
+    }
+}
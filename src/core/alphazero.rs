@@ -0,0 +1,516 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::marker::PhantomData;
+use std::path::Path;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+use super::agent::Agent;
+use super::encoder::Encoder;
+use super::mlp::{Mlp, Sample};
+use super::simulator::{Action, Simulator, State};
+
+/// Parameters of the PUCT search.
+#[derive(Clone, Copy, Debug)]
+pub struct PuctConfig {
+    /// The number of simulations per search.
+    pub simulations: usize,
+    /// Weights the network's prior against the observed values.
+    pub exploration: f64,
+    /// The concentration of the Dirichlet noise added to the root priors in self-play.
+    pub dirichlet_alpha: f64,
+    /// The weight of the Dirichlet noise in the root priors in self-play.
+    pub noise_fraction: f64,
+    /// The number of self-play moves sampled in proportion to visit counts
+    /// before always playing the most visited action.
+    pub temperature_moves: usize,
+}
+
+impl Default for PuctConfig {
+    fn default() -> Self {
+        PuctConfig { simulations: 100, exploration: 1.5, dirichlet_alpha: 1.0, noise_fraction: 0.25, temperature_moves: 8 }
+    }
+}
+
+/// Parameters of each training iteration.
+#[derive(Clone, Copy, Debug)]
+pub struct TrainingConfig {
+    /// The number of self-play games per iteration.
+    pub games: usize,
+    /// The number of passes over the replay buffer per iteration.
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub learning_rate: f64,
+    pub weight_decay: f64,
+    /// The number of most recent samples kept for training.
+    pub replay_capacity: usize,
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        TrainingConfig { games: 20, epochs: 2, batch_size: 32, learning_rate: 0.01, weight_decay: 1e-4, replay_capacity: 20_000 }
+    }
+}
+
+#[derive(Debug)]
+struct Node<S, A> {
+    state: S,
+    /// The player choosing an action, `None` in a terminal state.
+    player_id: Option<usize>,
+    actions: Vec<A>,
+    priors: Vec<f64>,
+    children: Vec<Option<usize>>,
+    visits: u32,
+    value_sum: Vec<f64>,
+}
+
+/// The result of a search from a state.
+#[derive(Debug)]
+struct SearchResult<A> {
+    actions: Vec<A>,
+    visits: Vec<u32>,
+    values: Vec<f64>,
+}
+
+/// AlphaZero: a network predicting action priors and values guides
+/// a PUCT tree search, and is trained on games the search plays
+/// against itself.
+///
+/// The network predicts a value for every player, so rewards must be
+/// between -1 and 1. At most one player may act in any state.
+#[derive(Debug)]
+pub struct AlphaZero<S : State, A : Action, E : Encoder<S, A>> {
+    network: Mlp,
+    encoder: E,
+    config: PuctConfig,
+    rng: ChaCha8Rng,
+    replay: VecDeque<Sample>,
+    iterations: usize,
+    info: Option<String>,
+    policy: Option<Vec<(A, f64)>>,
+    phantom: PhantomData<(S, A)>,
+}
+
+impl <S : State, A : Action, E : Encoder<S, A>> AlphaZero<S, A, E> {
+    /// Creates an AlphaZero agent.
+    ///
+    /// ### Arguments
+    ///
+    /// * `network` - The network evaluating states, sized to match the encoder.
+    /// * `encoder` - Converts states and actions for the network.
+    /// * `config` - The parameters of the search.
+    /// * `rng` - The random number generator used for noise and sampling moves.
+    pub fn new(network: Mlp, encoder: E, config: PuctConfig, rng: ChaCha8Rng) -> Self {
+        assert_eq!(network.input_size(), encoder.input_size(), "network input does not match encoder");
+        assert_eq!(network.policy_size(), encoder.policy_size(), "network policy does not match encoder");
+        AlphaZero { network, encoder, config, rng, replay: VecDeque::new(), iterations: 0, info: None, policy: None, phantom: PhantomData }
+    }
+
+    pub fn network(&self) -> &Mlp {
+        &self.network
+    }
+
+    /// The number of completed training iterations.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    fn evaluate(&self, state: &S, actions: &[A]) -> (Vec<f64>, Vec<f64>) {
+        let (policy, values) = self.network.forward(&self.encoder.encode(state));
+        let priors = actions.iter().map(|action| policy[self.encoder.action_index(action)]).collect();
+        return (priors, values);
+    }
+
+    /// Searches from a state and returns each legal action with its visit count.
+    ///
+    /// ### Arguments
+    ///
+    /// * `simulator` - The simulator that determines action outcomes in the domain.
+    /// * `state` - The state to search from.
+    /// * `add_noise` - Adds Dirichlet noise to the root priors to explore in self-play.
+    pub fn search<I : Simulator<S, A>>(&mut self, simulator: &mut I, state: &S, add_noise: bool) -> Vec<(A, u32)> {
+        let result = self.run_search(simulator, state, add_noise);
+        return result.actions.into_iter().zip(result.visits).collect();
+    }
+
+    fn run_search<I : Simulator<S, A>>(&mut self, simulator: &mut I, state: &S, add_noise: bool) -> SearchResult<A> {
+        let simulator = &mut simulator.fork(&mut self.rng);
+        let mut evaluate = |state: &S, actions: &[A]| self.evaluate(state, actions);
+        let mut tree = Tree::new(simulator, state.clone(), &mut evaluate);
+        if add_noise {
+            let noise = dirichlet(self.config.dirichlet_alpha, tree.nodes[0].priors.len(), &mut self.rng);
+            let fraction = self.config.noise_fraction;
+            tree.nodes[0].priors.iter_mut().zip(noise).for_each(|(p, n)| *p = (1.0 - fraction) * *p + fraction * n);
+        }
+        let mut evaluate = |state: &S, actions: &[A]| self.evaluate(state, actions);
+        for _ in 0..self.config.simulations {
+            tree.simulate(simulator, self.config.exploration, &mut evaluate);
+        }
+        return tree.result();
+    }
+
+    fn sample_action(&mut self, result: &SearchResult<A>, proportional: bool) -> usize {
+        if proportional {
+            let total = result.visits.iter().sum::<u32>();
+            let mut remaining = self.rng.gen_range(0..total.max(1));
+            for (i, &visits) in result.visits.iter().enumerate() {
+                if remaining < visits {
+                    return i;
+                }
+                remaining -= visits;
+            }
+        }
+        return (0..result.visits.len()).max_by_key(|&i| result.visits[i]).unwrap_or(0);
+    }
+
+    /// Plays a game against itself, recording the search's visit
+    /// distribution at each state as the policy target and the
+    /// final rewards as the value target.
+    ///
+    /// ### Return Value
+    ///
+    /// A training sample for every state where an action was chosen.
+    pub fn self_play<I : Simulator<S, A>>(&mut self, simulator: &mut I) -> Vec<Sample> {
+        let mut state = simulator.generate_initial_state();
+        let mut positions: Vec<(Vec<f64>, Vec<f64>)> = Vec::new();
+        while !simulator.is_terminal_state(&state) {
+            let result = self.run_search(simulator, &state, true);
+            let total = result.visits.iter().sum::<u32>().max(1) as f64;
+            let mut policy = vec![0.0; self.encoder.policy_size()];
+            for (action, &visits) in result.actions.iter().zip(&result.visits) {
+                policy[self.encoder.action_index(action)] += visits as f64 / total;
+            }
+            positions.push((self.encoder.encode(&state), policy));
+
+            let index = self.sample_action(&result, positions.len() <= self.config.temperature_moves);
+            let player_id = acting_player(simulator, &state).unwrap();
+            let mut actions = vec![None; simulator.number_of_players()];
+            actions[player_id] = Some(result.actions[index].clone());
+            state = simulator.state_transition(&state, &actions);
+        }
+        let value = simulator.calculate_rewards(&state).iter().map(|reward| reward.0.clamp(-1.0, 1.0)).collect::<Vec<f64>>();
+        return positions.into_iter()
+            .map(|(input, policy)| Sample { input, policy, value: value.clone() })
+            .collect();
+    }
+
+    /// Trains the network on shuffled batches of samples.
+    ///
+    /// ### Return Value
+    ///
+    /// The loss over the samples after training.
+    pub fn train(&mut self, samples: &[Sample], training: &TrainingConfig) -> f64 {
+        let mut samples = samples.to_vec();
+        for _ in 0..training.epochs {
+            samples.shuffle(&mut self.rng);
+            for batch in samples.chunks(training.batch_size.max(1)) {
+                self.network.train_batch(batch, training.learning_rate, training.weight_decay);
+            }
+        }
+        return self.network.loss(&samples);
+    }
+
+    /// Runs training iterations, each playing self-play games, adding
+    /// their samples to the replay buffer and training on the buffer.
+    ///
+    /// ### Arguments
+    ///
+    /// * `simulator` - The simulator that determines action outcomes in the domain.
+    /// * `iterations` - The number of iterations to run.
+    /// * `training` - The parameters of each iteration.
+    /// * `checkpoint_directory` - If given, the network is saved there
+    ///   after every iteration as `checkpoint_<iteration>.txt`.
+    ///
+    /// ### Return Value
+    ///
+    /// The loss after each iteration, or an error if a checkpoint cannot be saved.
+    pub fn train_iterations<I : Simulator<S, A>>(
+        &mut self,
+        simulator: &mut I,
+        iterations: usize,
+        training: &TrainingConfig,
+        checkpoint_directory: Option<&Path>,
+    ) -> Result<Vec<f64>, String> {
+        let mut losses = Vec::new();
+        for _ in 0..iterations {
+            for _ in 0..training.games {
+                let samples = self.self_play(simulator);
+                self.replay.extend(samples);
+            }
+            while self.replay.len() > training.replay_capacity {
+                self.replay.pop_front();
+            }
+            let replay = self.replay.iter().cloned().collect::<Vec<Sample>>();
+            losses.push(self.train(&replay, training));
+            self.iterations += 1;
+            if let Some(directory) = checkpoint_directory {
+                self.save_checkpoint(&directory.join(format!("checkpoint_{:04}.txt", self.iterations)))?;
+            }
+        }
+        return Ok(losses);
+    }
+
+    /// Saves the network to a file.
+    pub fn save_checkpoint(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        return self.network.save(&mut BufWriter::new(file)).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+
+    /// Replaces the network with one saved by `save_checkpoint`.
+    pub fn load_checkpoint(&mut self, path: &Path) -> Result<(), String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let network = Mlp::load(&mut BufReader::new(file))?;
+        if network.input_size() != self.encoder.input_size() || network.policy_size() != self.encoder.policy_size() {
+            return Err(format!("{}: network does not match encoder", path.display()));
+        }
+        self.network = network;
+        return Ok(());
+    }
+}
+
+/// Plays the most visited action of each search.
+impl <S : State, A : Action, E : Encoder<S, A>> Agent<S, A> for AlphaZero<S, A, E> {
+    fn select_action<I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
+        I: Simulator<S, A>,
+    {
+        assert_eq!(acting_player(simulator, state), Some(player_id), "Player should be the only one with legal actions.");
+        let result = self.run_search(simulator, state, false);
+        let index = self.sample_action(&result, false);
+        let total = result.visits.iter().sum::<u32>().max(1) as f64;
+        self.policy = Some(result.actions.iter().zip(&result.visits)
+            .map(|(action, &visits)| (action.clone(), visits as f64 / total))
+            .collect());
+        self.info = Some(format!(
+            "simulations: {}, visits: {}, value: {:.3}",
+            self.config.simulations,
+            result.visits[index],
+            result.values[player_id],
+        ));
+        return result.actions[index].clone();
+    }
+
+    fn action_info(&self) -> Option<String> {
+        self.info.clone()
+    }
+
+    fn action_policy(&self) -> Option<Vec<(A, f64)>> {
+        self.policy.clone()
+    }
+}
+
+/// Finds the only player with legal actions, or `None` in a terminal state.
+fn acting_player<S, A, I>(simulator: &mut I, state: &S) -> Option<usize> where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    let legal_actions = simulator.calculate_legal_actions(state);
+    let mut acting = (0..legal_actions.len()).filter(|&i| !legal_actions[i].0.is_empty());
+    let player_id = acting.next();
+    assert!(acting.next().is_none(), "PUCT requires at most one acting player per state");
+    return player_id;
+}
+
+/// A PUCT search tree stored as an arena of nodes.
+struct Tree<S, A> {
+    nodes: Vec<Node<S, A>>,
+}
+
+impl <S : State, A : Action> Tree<S, A> {
+    fn new<I, F>(simulator: &mut I, state: S, evaluate: &mut F) -> Self where
+        I: Simulator<S, A>,
+        F: FnMut(&S, &[A]) -> (Vec<f64>, Vec<f64>),
+    {
+        let mut tree = Tree { nodes: vec![] };
+        tree.expand(simulator, state, evaluate);
+        return tree;
+    }
+
+    /// Adds a node for a state and returns its index and value for each player.
+    fn expand<I, F>(&mut self, simulator: &mut I, state: S, evaluate: &mut F) -> (usize, Vec<f64>) where
+        I: Simulator<S, A>,
+        F: FnMut(&S, &[A]) -> (Vec<f64>, Vec<f64>),
+    {
+        let player_id = acting_player(simulator, &state);
+        let (actions, priors, values) = match player_id {
+            Some(player_id) => {
+                let mut actions = simulator.calculate_legal_actions(&state).swap_remove(player_id).0.into_iter().collect::<Vec<A>>();
+                actions.sort_by_cached_key(|action| action.to_string());
+                let (priors, values) = evaluate(&state, &actions);
+                let total = priors.iter().sum::<f64>();
+                let priors = match total > 0.0 {
+                    true => priors.iter().map(|p| p / total).collect(),
+                    false => vec![1.0 / actions.len() as f64; actions.len()],
+                };
+                (actions, priors, values)
+            },
+            None => {
+                let rewards = simulator.calculate_rewards(&state).iter().map(|reward| reward.0).collect();
+                (vec![], vec![], rewards)
+            },
+        };
+        let children = vec![None; actions.len()];
+        let value_sum = vec![0.0; values.len()];
+        self.nodes.push(Node { state, player_id, actions, priors, children, visits: 0, value_sum });
+        return (self.nodes.len() - 1, values);
+    }
+
+    /// Descends the tree choosing actions by PUCT, expands a leaf
+    /// and backs its values up along the path.
+    fn simulate<I, F>(&mut self, simulator: &mut I, exploration: f64, evaluate: &mut F) where
+        I: Simulator<S, A>,
+        F: FnMut(&S, &[A]) -> (Vec<f64>, Vec<f64>),
+    {
+        let mut path = vec![0];
+        let values = loop {
+            let index = *path.last().unwrap();
+            let node = &self.nodes[index];
+            let player_id = match node.player_id {
+                Some(player_id) => player_id,
+                None => break node.value_sum.iter().map(|v| v / node.visits.max(1) as f64).collect::<Vec<f64>>(),
+            };
+            let sqrt_visits = (node.visits.max(1) as f64).sqrt();
+            let action = (0..node.actions.len())
+                .map(|i| {
+                    let (q, n) = match node.children[i] {
+                        Some(child) => {
+                            let child = &self.nodes[child];
+                            (child.value_sum[player_id] / child.visits.max(1) as f64, child.visits)
+                        },
+                        None => (0.0, 0),
+                    };
+                    (i, q + exploration * node.priors[i] * sqrt_visits / (1.0 + n as f64))
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap().0;
+            match node.children[action] {
+                Some(child) => path.push(child),
+                None => {
+                    let mut actions = vec![None; simulator.number_of_players()];
+                    actions[player_id] = Some(node.actions[action].clone());
+                    let next_state = simulator.state_transition(&node.state, &actions);
+                    let (child, values) = self.expand(simulator, next_state, evaluate);
+                    self.nodes[index].children[action] = Some(child);
+                    path.push(child);
+                    break values;
+                },
+            }
+        };
+        for index in path {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.value_sum.iter_mut().zip(&values).for_each(|(sum, v)| *sum += v);
+        }
+    }
+
+    fn result(self) -> SearchResult<A> {
+        let root = &self.nodes[0];
+        let visits = root.children.iter().map(|child| child.map_or(0, |c| self.nodes[c].visits)).collect();
+        let values = root.value_sum.iter().map(|v| v / root.visits.max(1) as f64).collect();
+        let root = self.nodes.into_iter().next().unwrap();
+        return SearchResult { actions: root.actions, visits, values };
+    }
+}
+
+/// Samples from a symmetric Dirichlet distribution.
+fn dirichlet(alpha: f64, n: usize, rng: &mut ChaCha8Rng) -> Vec<f64> {
+    let samples = (0..n).map(|_| gamma(alpha, rng)).collect::<Vec<f64>>();
+    let total = samples.iter().sum::<f64>();
+    if total <= 0.0 {
+        return vec![1.0 / n as f64; n];
+    }
+    return samples.iter().map(|x| x / total).collect();
+}
+
+/// Samples from a gamma distribution with unit scale using
+/// the Marsaglia and Tsang method.
+fn gamma(alpha: f64, rng: &mut ChaCha8Rng) -> f64 {
+    if alpha < 1.0 {
+        return gamma(alpha + 1.0, rng) * rng.gen::<f64>().powf(1.0 / alpha);
+    }
+    let d = alpha - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        // Box-Muller transform for a standard normal sample.
+        let normal = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt() * (2.0 * std::f64::consts::PI * rng.gen::<f64>()).cos();
+        let v = (1.0 + c * normal).powi(3);
+        if v > 0.0 && (1.0 - rng.gen::<f64>()).ln() < 0.5 * normal * normal + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::core::game::play_game;
+    use crate::core::reward::ADVERSARIAL_P1_WIN;
+    use crate::core::testing::counters::{CountersEncoder, CountersSimulator, CountersState, TestAction};
+
+    use super::*;
+
+    const MAX_COUNTERS: u8 = 7;
+
+    fn alphazero(simulations: usize) -> AlphaZero<CountersState, TestAction, CountersEncoder> {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let encoder = CountersEncoder::new(MAX_COUNTERS);
+        let network = Mlp::new(encoder.input_size(), &[16], encoder.policy_size(), 2, &mut rng);
+        let config = PuctConfig { simulations, ..PuctConfig::default() };
+        return AlphaZero::new(network, encoder, config, rng);
+    }
+
+    #[test]
+    fn search_prefers_winning_move() {
+        let mut alphazero = alphazero(200);
+        let state = CountersState { counters: 4, turn: 0 };
+        let visits = alphazero.search(&mut CountersSimulator::new(MAX_COUNTERS), &state, false);
+        let (best, _) = visits.iter().max_by_key(|(_, visits)| *visits).unwrap();
+        assert_eq!(*best, TestAction(1));
+        assert_eq!(visits.iter().map(|(_, visits)| visits).sum::<u32>(), 200);
+    }
+
+    #[test]
+    fn self_play_targets_visits_and_outcome() {
+        let mut alphazero = alphazero(20);
+        let samples = alphazero.self_play(&mut CountersSimulator::new(MAX_COUNTERS));
+        assert!(!samples.is_empty());
+        for sample in &samples {
+            assert_eq!(sample.input.len(), CountersEncoder::new(MAX_COUNTERS).input_size());
+            assert!((sample.policy.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            assert_eq!(sample.value, samples[0].value);
+        }
+        assert_eq!(samples[0].value.iter().sum::<f64>(), 0.0);
+        assert_eq!(samples[0].value[0].abs(), 1.0);
+    }
+
+    #[test]
+    fn train_iterations_saves_checkpoints() {
+        let directory = std::env::temp_dir().join(format!("alphazero_checkpoints_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut trained = alphazero(10);
+        let training = TrainingConfig { games: 4, epochs: 1, batch_size: 8, ..TrainingConfig::default() };
+        let losses = trained.train_iterations(&mut CountersSimulator::new(MAX_COUNTERS), 2, &training, Some(&directory)).unwrap();
+        assert_eq!(losses.len(), 2);
+        assert!(losses.iter().all(|loss| loss.is_finite()));
+
+        let mut restored = alphazero(10);
+        restored.load_checkpoint(&directory.join("checkpoint_0002.txt")).unwrap();
+        assert_eq!(restored.network(), trained.network());
+        assert!(restored.load_checkpoint(&directory.join("checkpoint_0003.txt")).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn agent_wins_as_first_player() {
+        let mut agents = [alphazero(100), alphazero(100)];
+        let history = play_game(&mut CountersSimulator::new(MAX_COUNTERS), &mut agents, &mut []);
+        assert_eq!(history.outcome(), Some(&ADVERSARIAL_P1_WIN[..]));
+        assert!(agents[0].action_info().unwrap().starts_with("simulations: 100"));
+        let policy = agents[0].action_policy().unwrap();
+        assert!((policy.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
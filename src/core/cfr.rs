@@ -0,0 +1,622 @@
+use std::collections::HashMap;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand_chacha::ChaCha8Rng;
+
+use super::agent::ImperfectInfoAgent;
+use super::imperfect_info::{ImperfectInfoSimulator, Observation};
+use super::regret::{normalize, regret_matching_strategy};
+use super::simulator::{Action, LegalActions, State};
+
+/// A simulator that can enumerate its chance outcomes, so that
+/// counterfactual values can be computed exactly.
+///
+/// Chance is limited to the initial state: every state transition
+/// must be deterministic. Observations are used as information set keys
+/// and must have perfect recall, meaning a player's observation includes
+/// everything the player has previously observed, including their own actions.
+/// At most one player may have legal actions in any state.
+pub trait CfrSimulator<S : State, A : Action, O : Observation> : ImperfectInfoSimulator<S, A, O> {
+    /// Every possible initial state with the probability that it is generated.
+    fn initial_state_distribution(&mut self) -> Vec<(S, f64)>;
+}
+
+/// The algorithm used to update regrets and strategies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CfrVariant {
+    /// Counterfactual regret minimization over the full game tree.
+    Vanilla,
+    /// CFR with regrets floored at zero and linearly weighted averaging.
+    Plus,
+    /// Monte Carlo CFR sampling chance and opponent actions,
+    /// exploring every action of the updating player.
+    ExternalSampling,
+}
+
+/// Regrets and cumulative strategy for one information set.
+#[derive(Debug)]
+struct InfoSetNode<A : Action> {
+    actions: Vec<A>,
+    regrets: Vec<f64>,
+    strategy_sum: Vec<f64>,
+}
+
+impl <A : Action> InfoSetNode<A> {
+    fn new(legal_actions: &LegalActions<A>) -> Self {
+        let actions = legal_actions.iter().cloned().collect::<Vec<A>>();
+        let n = actions.len();
+        InfoSetNode { actions, regrets: vec![0.0; n], strategy_sum: vec![0.0; n] }
+    }
+
+    fn current_strategy(&self) -> Vec<f64> {
+        return regret_matching_strategy(&self.regrets);
+    }
+
+    fn average_strategy(&self) -> Vec<f64> {
+        return normalize(&self.strategy_sum);
+    }
+}
+
+/// Finds the only player with legal actions, or `None` in a terminal state.
+fn acting_player<S, A, O, I>(simulator: &mut I, state: &S) -> Option<(usize, LegalActions<A>)> where
+    S: State,
+    A: Action,
+    O: Observation,
+    I: CfrSimulator<S, A, O>,
+{
+    let mut acting = simulator.calculate_legal_actions(state).into_iter()
+        .enumerate()
+        .filter(|(_, legal_actions)| !legal_actions.0.is_empty());
+    let player = acting.next();
+    assert!(acting.next().is_none(), "CFR requires at most one acting player per state");
+    return player;
+}
+
+fn player_reward<S, A, O, I>(simulator: &mut I, state: &S, player_id: usize) -> f64 where
+    S: State,
+    A: Action,
+    O: Observation,
+    I: CfrSimulator<S, A, O>,
+{
+    return simulator.calculate_rewards(state)[player_id].0;
+}
+
+fn apply<S, A, O, I>(simulator: &mut I, state: &S, player_id: usize, action: &A) -> S where
+    S: State,
+    A: Action,
+    O: Observation,
+    I: CfrSimulator<S, A, O>,
+{
+    let mut actions = vec![None; simulator.number_of_players()];
+    actions[player_id] = Some(action.clone());
+    return simulator.state_transition(state, &actions);
+}
+
+/// Computes strategies for imperfect-information games by
+/// counterfactual regret minimization. Information sets are keyed
+/// by the acting player and their observation.
+#[derive(Debug)]
+pub struct CfrTrainer<A : Action, O : Observation> {
+    variant: CfrVariant,
+    nodes: HashMap<(usize, O), InfoSetNode<A>>,
+    iterations: usize,
+    rng: ChaCha8Rng,
+}
+
+impl <A : Action, O : Observation> CfrTrainer<A, O> {
+    /// Creates a trainer with no regrets.
+    ///
+    /// ### Arguments
+    ///
+    /// * `variant` - The CFR algorithm to train with.
+    /// * `rng` - The random number generator used by sampling variants.
+    pub fn new(variant: CfrVariant, rng: ChaCha8Rng) -> Self {
+        CfrTrainer { variant, nodes: HashMap::new(), iterations: 0, rng }
+    }
+
+    /// The number of training iterations run so far.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Runs training iterations, updating each player in turn.
+    pub fn train<S, I>(&mut self, simulator: &mut I, iterations: usize) where
+        S: State,
+        I: CfrSimulator<S, A, O>,
+    {
+        let number_of_players = simulator.number_of_players();
+        for _ in 0..iterations {
+            self.iterations += 1;
+            for player_id in 0..number_of_players {
+                match self.variant {
+                    CfrVariant::Vanilla | CfrVariant::Plus => {
+                        for (state, probability) in simulator.initial_state_distribution() {
+                            self.traverse(simulator, &state, player_id, 1.0, probability);
+                        }
+                    },
+                    CfrVariant::ExternalSampling => {
+                        let state = simulator.generate_initial_state();
+                        self.traverse_external_sampling(simulator, &state, player_id);
+                    },
+                }
+            }
+        }
+    }
+
+    /// Updates regrets for `player_id` and returns the expected utility of the state
+    /// for that player given the current strategies.
+    fn traverse<S, I>(&mut self, simulator: &mut I, state: &S, player_id: usize, player_reach: f64, others_reach: f64) -> f64 where
+        S: State,
+        I: CfrSimulator<S, A, O>,
+    {
+        let (acting_player, legal_actions) = match acting_player(simulator, state) {
+            Some(acting) => acting,
+            None => return player_reward(simulator, state, player_id),
+        };
+        let key = (acting_player, simulator.observe(state, acting_player));
+        let node = self.nodes.entry(key.clone()).or_insert_with(|| InfoSetNode::new(&legal_actions));
+        let actions = node.actions.clone();
+        let strategy = node.current_strategy();
+
+        let mut utilities = vec![0.0; actions.len()];
+        for (i, action) in actions.iter().enumerate() {
+            let next_state = apply(simulator, state, acting_player, action);
+            utilities[i] = if acting_player == player_id {
+                self.traverse(simulator, &next_state, player_id, player_reach * strategy[i], others_reach)
+            } else {
+                self.traverse(simulator, &next_state, player_id, player_reach, others_reach * strategy[i])
+            };
+        }
+        let utility = strategy.iter().zip(&utilities).map(|(p, u)| p * u).sum::<f64>();
+
+        if acting_player == player_id {
+            let weight = match self.variant {
+                CfrVariant::Plus => self.iterations as f64,
+                _ => 1.0,
+            };
+            let node = self.nodes.get_mut(&key).unwrap();
+            for i in 0..actions.len() {
+                node.regrets[i] += others_reach * (utilities[i] - utility);
+                if self.variant == CfrVariant::Plus {
+                    node.regrets[i] = node.regrets[i].max(0.0);
+                }
+                node.strategy_sum[i] += weight * player_reach * strategy[i];
+            }
+        }
+        return utility;
+    }
+
+    /// Updates regrets for `player_id` along sampled opponent actions and
+    /// returns the sampled utility of the state for that player.
+    fn traverse_external_sampling<S, I>(&mut self, simulator: &mut I, state: &S, player_id: usize) -> f64 where
+        S: State,
+        I: CfrSimulator<S, A, O>,
+    {
+        let (acting_player, legal_actions) = match acting_player(simulator, state) {
+            Some(acting) => acting,
+            None => return player_reward(simulator, state, player_id),
+        };
+        let key = (acting_player, simulator.observe(state, acting_player));
+        let node = self.nodes.entry(key.clone()).or_insert_with(|| InfoSetNode::new(&legal_actions));
+        let actions = node.actions.clone();
+        let strategy = node.current_strategy();
+
+        if acting_player != player_id {
+            node.strategy_sum.iter_mut().zip(&strategy).for_each(|(sum, p)| *sum += p);
+            let i = WeightedIndex::new(&strategy).unwrap().sample(&mut self.rng);
+            let next_state = apply(simulator, state, acting_player, &actions[i]);
+            return self.traverse_external_sampling(simulator, &next_state, player_id);
+        }
+
+        let utilities = actions.iter()
+            .map(|action| {
+                let next_state = apply(simulator, state, acting_player, action);
+                self.traverse_external_sampling(simulator, &next_state, player_id)
+            })
+            .collect::<Vec<f64>>();
+        let utility = strategy.iter().zip(&utilities).map(|(p, u)| p * u).sum::<f64>();
+        let node = self.nodes.get_mut(&key).unwrap();
+        node.regrets.iter_mut().zip(&utilities).for_each(|(regret, u)| *regret += u - utility);
+        return utility;
+    }
+
+    /// The average strategy of an information set, which converges to an
+    /// equilibrium strategy in two-player zero-sum games.
+    ///
+    /// ### Return Value
+    ///
+    /// Each legal action with its probability, or `None` if the
+    /// information set has not been visited during training.
+    pub fn average_strategy(&self, player_id: usize, observation: &O) -> Option<Vec<(A, f64)>> {
+        let node = self.nodes.get(&(player_id, observation.clone()))?;
+        return Some(node.actions.iter().cloned().zip(node.average_strategy()).collect());
+    }
+
+    fn average_strategy_or_uniform(&self, player_id: usize, observation: O, legal_actions: &LegalActions<A>) -> Vec<(A, f64)> {
+        return self.average_strategy(player_id, &observation).unwrap_or_else(|| {
+            let probability = 1.0 / legal_actions.0.len() as f64;
+            legal_actions.iter().map(|action| (action.clone(), probability)).collect()
+        });
+    }
+
+    /// The expected reward of each player when every player
+    /// follows the average strategy.
+    pub fn expected_values<S, I>(&self, simulator: &mut I) -> Vec<f64> where
+        S: State,
+        I: CfrSimulator<S, A, O>,
+    {
+        let mut values = vec![0.0; simulator.number_of_players()];
+        for (state, probability) in simulator.initial_state_distribution() {
+            let state_values = self.state_values(simulator, &state);
+            values.iter_mut().zip(state_values).for_each(|(value, v)| *value += probability * v);
+        }
+        return values;
+    }
+
+    fn state_values<S, I>(&self, simulator: &mut I, state: &S) -> Vec<f64> where
+        S: State,
+        I: CfrSimulator<S, A, O>,
+    {
+        let (acting_player, legal_actions) = match acting_player(simulator, state) {
+            Some(acting) => acting,
+            None => return simulator.calculate_rewards(state).iter().map(|reward| reward.0).collect(),
+        };
+        let observation = simulator.observe(state, acting_player);
+        let mut values = vec![0.0; simulator.number_of_players()];
+        for (action, probability) in self.average_strategy_or_uniform(acting_player, observation, &legal_actions) {
+            let next_state = apply(simulator, state, acting_player, &action);
+            let next_values = self.state_values(simulator, &next_state);
+            values.iter_mut().zip(next_values).for_each(|(value, v)| *value += probability * v);
+        }
+        return values;
+    }
+
+    /// How much a best responding opponent could gain against the average
+    /// strategy in a two-player zero-sum game, averaged over both players.
+    /// Zero for a Nash equilibrium.
+    pub fn exploitability<S, I>(&self, simulator: &mut I) -> f64 where
+        S: State,
+        I: CfrSimulator<S, A, O>,
+    {
+        let number_of_players = simulator.number_of_players();
+        assert_eq!(number_of_players, 2, "exploitability requires a two-player game");
+        let best_response_values = (0..number_of_players)
+            .map(|player_id| BestResponse::new(self, player_id).value(simulator))
+            .sum::<f64>();
+        return best_response_values / number_of_players as f64;
+    }
+
+    /// Creates an agent that plays the current average strategy.
+    pub fn agent(&self, rng: ChaCha8Rng) -> CfrAgent<A, O> {
+        let strategy = self.nodes.iter()
+            .map(|(key, node)| (key.clone(), node.actions.iter().cloned().zip(node.average_strategy()).collect()))
+            .collect();
+        return CfrAgent { strategy, rng };
+    }
+}
+
+/// Computes the value of a best response for one player against
+/// the average strategy of the other players.
+struct BestResponse<'a, S : State, A : Action, O : Observation> {
+    trainer: &'a CfrTrainer<A, O>,
+    player_id: usize,
+    /// The states of each of the player's information sets with the
+    /// probability of reaching them due to chance and other players.
+    info_sets: HashMap<O, Vec<(S, f64)>>,
+    best_actions: HashMap<O, A>,
+}
+
+impl <'a, S : State, A : Action, O : Observation> BestResponse<'a, S, A, O> {
+    fn new(trainer: &'a CfrTrainer<A, O>, player_id: usize) -> Self {
+        BestResponse { trainer, player_id, info_sets: HashMap::new(), best_actions: HashMap::new() }
+    }
+
+    fn value<I : CfrSimulator<S, A, O>>(&mut self, simulator: &mut I) -> f64 {
+        let initial_states = simulator.initial_state_distribution();
+        for (state, probability) in &initial_states {
+            self.collect_info_sets(simulator, state, *probability);
+        }
+        return initial_states.iter()
+            .map(|(state, probability)| probability * self.state_value(simulator, state))
+            .sum();
+    }
+
+    fn collect_info_sets<I : CfrSimulator<S, A, O>>(&mut self, simulator: &mut I, state: &S, others_reach: f64) {
+        let (acting_player, legal_actions) = match acting_player(simulator, state) {
+            Some(acting) => acting,
+            None => return,
+        };
+        let observation = simulator.observe(state, acting_player);
+        if acting_player == self.player_id {
+            self.info_sets.entry(observation).or_default().push((state.clone(), others_reach));
+            for action in legal_actions.iter() {
+                let next_state = apply(simulator, state, acting_player, action);
+                self.collect_info_sets(simulator, &next_state, others_reach);
+            }
+        } else {
+            for (action, probability) in self.trainer.average_strategy_or_uniform(acting_player, observation, &legal_actions) {
+                let next_state = apply(simulator, state, acting_player, &action);
+                self.collect_info_sets(simulator, &next_state, others_reach * probability);
+            }
+        }
+    }
+
+    fn state_value<I : CfrSimulator<S, A, O>>(&mut self, simulator: &mut I, state: &S) -> f64 {
+        let (acting_player, legal_actions) = match acting_player(simulator, state) {
+            Some(acting) => acting,
+            None => return player_reward(simulator, state, self.player_id),
+        };
+        let observation = simulator.observe(state, acting_player);
+        if acting_player == self.player_id {
+            let action = self.best_action(simulator, observation, &legal_actions);
+            let next_state = apply(simulator, state, acting_player, &action);
+            return self.state_value(simulator, &next_state);
+        }
+        let mut value = 0.0;
+        for (action, probability) in self.trainer.average_strategy_or_uniform(acting_player, observation, &legal_actions) {
+            let next_state = apply(simulator, state, acting_player, &action);
+            value += probability * self.state_value(simulator, &next_state);
+        }
+        return value;
+    }
+
+    /// The action maximizing the reach weighted value over
+    /// every state in the player's information set.
+    fn best_action<I : CfrSimulator<S, A, O>>(&mut self, simulator: &mut I, observation: O, legal_actions: &LegalActions<A>) -> A {
+        if let Some(action) = self.best_actions.get(&observation) {
+            return action.clone();
+        }
+        let states = self.info_sets[&observation].clone();
+        let mut best: Option<(A, f64)> = None;
+        for action in legal_actions.iter() {
+            let mut value = 0.0;
+            for (state, others_reach) in &states {
+                let next_state = apply(simulator, state, self.player_id, action);
+                value += others_reach * self.state_value(simulator, &next_state);
+            }
+            let is_better = match &best {
+                Some((_, best_value)) => value > *best_value,
+                None => true,
+            };
+            if is_better {
+                best = Some((action.clone(), value));
+            }
+        }
+        let action = best.expect("Player should have legal actions.").0;
+        self.best_actions.insert(observation, action.clone());
+        return action;
+    }
+}
+
+/// Plays a strategy computed by `CfrTrainer`.
+///
+/// Information sets are keyed by the acting player and their observation.
+/// Unknown information sets are played uniformly at random.
+#[derive(Debug)]
+pub struct CfrAgent<A : Action, O : Observation> {
+    strategy: HashMap<(usize, O), Vec<(A, f64)>>,
+    rng: ChaCha8Rng,
+}
+
+impl <S : State, A : Action, O : Observation> ImperfectInfoAgent<S, A, O> for CfrAgent<A, O> {
+    fn select_action<I>(
+        &mut self,
+        player_id: usize,
+        observation: &O,
+        _action_history: &[Vec<Option<A>>],
+        legal_actions: &LegalActions<A>,
+        _simulator: &mut I,
+    ) -> A where
+        I: ImperfectInfoSimulator<S, A, O>,
+    {
+        let actions = legal_actions.iter().collect::<Vec<&A>>();
+        let probabilities = match self.strategy.get(&(player_id, observation.clone())) {
+            Some(strategy) => normalize(&actions.iter()
+                .map(|&action| strategy.iter().find(|(a, _)| a == action).map_or(0.0, |(_, p)| *p))
+                .collect::<Vec<f64>>()),
+            None => vec![1.0 / actions.len() as f64; actions.len()],
+        };
+        return actions[WeightedIndex::new(&probabilities).unwrap().sample(&mut self.rng)].clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use rand::{Rng, SeedableRng};
+
+    use crate::core::reward::{Reward, ADVERSARIAL_DRAW};
+    use crate::core::simulator::Simulator;
+
+    use super::*;
+
+    /// Expected reward of the first player in Kuhn poker at equilibrium.
+    const KUHN_GAME_VALUE: f64 = -1.0 / 18.0;
+
+    fn train(variant: CfrVariant, iterations: usize) -> CfrTrainer<KuhnAction, KuhnObservation> {
+        let mut simulator = KuhnSimulator { rng: ChaCha8Rng::seed_from_u64(0) };
+        let mut trainer = CfrTrainer::new(variant, ChaCha8Rng::seed_from_u64(0));
+        trainer.train(&mut simulator, iterations);
+        return trainer;
+    }
+
+    #[test]
+    fn exploitability_of_uniform_strategy() {
+        let mut simulator = KuhnSimulator { rng: ChaCha8Rng::seed_from_u64(0) };
+        let trainer = CfrTrainer::<KuhnAction, KuhnObservation>::new(CfrVariant::Vanilla, ChaCha8Rng::seed_from_u64(0));
+        assert!(trainer.exploitability(&mut simulator) > 0.1);
+    }
+
+    #[test]
+    fn vanilla_cfr_converges_on_kuhn_poker() {
+        let mut simulator = KuhnSimulator { rng: ChaCha8Rng::seed_from_u64(0) };
+        let trainer = train(CfrVariant::Vanilla, 1000);
+        assert!(trainer.exploitability(&mut simulator) < 0.01);
+        assert!((trainer.expected_values(&mut simulator)[0] - KUHN_GAME_VALUE).abs() < 0.01);
+    }
+
+    #[test]
+    fn cfr_plus_converges_on_kuhn_poker() {
+        let mut simulator = KuhnSimulator { rng: ChaCha8Rng::seed_from_u64(0) };
+        let trainer = train(CfrVariant::Plus, 300);
+        assert!(trainer.exploitability(&mut simulator) < 0.01);
+        assert!((trainer.expected_values(&mut simulator)[0] - KUHN_GAME_VALUE).abs() < 0.01);
+    }
+
+    #[test]
+    fn external_sampling_converges_on_kuhn_poker() {
+        let mut simulator = KuhnSimulator { rng: ChaCha8Rng::seed_from_u64(0) };
+        let trainer = train(CfrVariant::ExternalSampling, 5000);
+        assert!(trainer.exploitability(&mut simulator) < 0.05);
+    }
+
+    #[test]
+    fn average_strategy_always_calls_bet_with_king() {
+        let trainer = train(CfrVariant::Plus, 300);
+        let observation = KuhnObservation { card: 2, history: vec![KuhnAction::Bet] };
+        let strategy = trainer.average_strategy(1, &observation).unwrap();
+        let (_, call) = strategy.iter().find(|(action, _)| *action == KuhnAction::Bet).unwrap();
+        assert!(*call > 0.99);
+    }
+
+    #[test]
+    fn agent_plays_average_strategy() {
+        let trainer = train(CfrVariant::Plus, 300);
+        let mut agent = trainer.agent(ChaCha8Rng::seed_from_u64(0));
+        let mut simulator = KuhnSimulator { rng: ChaCha8Rng::seed_from_u64(0) };
+        let state = KuhnState { cards: [0, 2], history: vec![KuhnAction::Bet] };
+        let observation = simulator.observe(&state, 1);
+        let legal_actions = simulator.calculate_legal_actions(&state).swap_remove(1);
+        for _ in 0..10 {
+            let action = agent.select_action(1, &observation, &[], &legal_actions, &mut simulator);
+            assert_eq!(action, KuhnAction::Bet);
+        }
+    }
+
+    #[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+    enum KuhnAction {
+        Pass,
+        Bet,
+    }
+
+    impl Action for KuhnAction {}
+
+    impl fmt::Display for KuhnAction {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                KuhnAction::Pass => write!(f, "p"),
+                KuhnAction::Bet => write!(f, "b"),
+            }
+        }
+    }
+
+    fn history_to_string(history: &[KuhnAction]) -> String {
+        history.iter().map(|action| action.to_string()).collect()
+    }
+
+    /// Kuhn poker: each player antes one chip and is dealt one of
+    /// three cards, then players may pass or bet one chip.
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct KuhnState {
+        cards: [u8; 2],
+        history: Vec<KuhnAction>,
+    }
+
+    impl State for KuhnState {}
+
+    impl fmt::Display for KuhnState {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} {} {}", self.cards[0], self.cards[1], history_to_string(&self.history))
+        }
+    }
+
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct KuhnObservation {
+        card: u8,
+        history: Vec<KuhnAction>,
+    }
+
+    impl Observation for KuhnObservation {}
+
+    impl fmt::Display for KuhnObservation {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} {}", self.card, history_to_string(&self.history))
+        }
+    }
+
+    #[derive(Clone)]
+    struct KuhnSimulator {
+        rng: ChaCha8Rng,
+    }
+
+    impl KuhnSimulator {
+        fn deals() -> Vec<[u8; 2]> {
+            return (0..3).flat_map(|a| (0..3).filter(move |&b| b != a).map(move |b| [a, b])).collect();
+        }
+    }
+
+    impl Simulator<KuhnState, KuhnAction> for KuhnSimulator {
+        fn generate_initial_state(&mut self) -> KuhnState {
+            let deals = KuhnSimulator::deals();
+            KuhnState { cards: deals[self.rng.gen_range(0..deals.len())], history: vec![] }
+        }
+
+        fn calculate_rewards(&mut self, state: &KuhnState) -> Vec<Reward> {
+            use KuhnAction::*;
+            let showdown = if state.cards[0] > state.cards[1] { 1.0 } else { -1.0 };
+            let player_1_reward = match state.history.as_slice() {
+                [Pass, Pass] => showdown,
+                [Bet, Pass] => 1.0,
+                [Pass, Bet, Pass] => -1.0,
+                [Bet, Bet] | [Pass, Bet, Bet] => 2.0 * showdown,
+                _ => return ADVERSARIAL_DRAW.to_vec(),
+            };
+            vec![Reward(player_1_reward), Reward(-player_1_reward)]
+        }
+
+        fn calculate_legal_actions(&mut self, state: &KuhnState) -> Vec<LegalActions<KuhnAction>> {
+            use KuhnAction::*;
+            let mut legal_actions = vec![LegalActions::new(), LegalActions::new()];
+            let is_terminal = matches!(state.history.as_slice(), [Pass, Pass] | [Bet, _] | [Pass, Bet, _]);
+            if !is_terminal {
+                let player_id = state.history.len() % 2;
+                legal_actions[player_id].insert(Pass);
+                legal_actions[player_id].insert(Bet);
+            }
+            return legal_actions;
+        }
+
+        fn state_transition(&mut self, state: &KuhnState, actions: &Vec<Option<KuhnAction>>) -> KuhnState {
+            let action = actions.iter().flatten().next().unwrap();
+            let mut history = state.history.clone();
+            history.push(*action);
+            return KuhnState { cards: state.cards, history };
+        }
+
+        fn number_of_players(&mut self) -> usize {
+            2
+        }
+    }
+
+    impl ImperfectInfoSimulator<KuhnState, KuhnAction, KuhnObservation> for KuhnSimulator {
+        fn observe(&mut self, state: &KuhnState, player_id: usize) -> KuhnObservation {
+            KuhnObservation { card: state.cards[player_id], history: state.history.clone() }
+        }
+
+        fn sample_determinization(&mut self, observation: &KuhnObservation, player_id: usize, rng: &mut ChaCha8Rng) -> KuhnState {
+            let deals = KuhnSimulator::deals().into_iter()
+                .filter(|deal| deal[player_id] == observation.card)
+                .collect::<Vec<[u8; 2]>>();
+            KuhnState { cards: deals[rng.gen_range(0..deals.len())], history: observation.history.clone() }
+        }
+    }
+
+    impl CfrSimulator<KuhnState, KuhnAction, KuhnObservation> for KuhnSimulator {
+        fn initial_state_distribution(&mut self) -> Vec<(KuhnState, f64)> {
+            let deals = KuhnSimulator::deals();
+            let probability = 1.0 / deals.len() as f64;
+            return deals.into_iter().map(|cards| (KuhnState { cards, history: vec![] }, probability)).collect();
+        }
+    }
+}
@@ -0,0 +1,541 @@
+//! Exports games as training data for models trained outside Rust.
+//!
+//! Every decision in a game becomes a `Record` of the acting player's
+//! encoded observation, a mask of their legal actions, the policy their
+//! agent followed and the final outcome of the game. Games of states with
+//! symmetries can be recorded with `record_symmetric_game` to add a record
+//! for every symmetric variant of each decision. A `DatasetWriter` collects
+//! records, shuffles them and writes them in shards of a fixed size in one
+//! of three formats:
+//!
+//! * `Binary` - A compact little-endian format, read back with `read_binary`.
+//!   An 8 byte magic `AGEDATA1` is followed by four `u32` values: the number
+//!   of records, the observation size, the policy size and the number of
+//!   players. Each record is a `u32` player ID, the observation as `f32`
+//!   values, the legal mask packed one bit per action in little-endian bit
+//!   order, the policy as `f32` values and the outcome as `f32` values.
+//! * `Csv` - One row per record with a header naming every column.
+//! * `Npy` - A single two-dimensional `float32` NumPy array with one row per
+//!   record and the same columns as the CSV format.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+
+use super::agent::Agent;
+use super::encoder::Encoder;
+use super::game::play_game;
+use super::imperfect_info::{ImperfectInfoSimulator, Observation};
+use super::observer::GameObserver;
+use super::simulator::{Action, State};
+use super::symmetry::Symmetric;
+
+const BINARY_MAGIC: &[u8; 8] = b"AGEDATA1";
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// A player's decision from a recorded game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// The player who selected an action.
+    pub player_id: usize,
+    /// The encoded observation the player selected an action from.
+    pub observation: Vec<f32>,
+    /// Whether each action index was legal for the player.
+    pub legal_mask: Vec<bool>,
+    /// The probability the player's agent gave each action index.
+    pub policy: Vec<f32>,
+    /// The final reward of every player.
+    pub outcome: Vec<f32>,
+}
+
+/// The file format of a dataset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DatasetFormat {
+    Binary,
+    Csv,
+    Npy,
+}
+
+impl DatasetFormat {
+    /// The file extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DatasetFormat::Binary => "bin",
+            DatasetFormat::Csv => "csv",
+            DatasetFormat::Npy => "npy",
+        }
+    }
+}
+
+/// Plays a game from the initial state and records every decision.
+///
+/// The policy of a decision is the agent's `action_policy` if it has one,
+/// such as a search's visit distribution, and otherwise puts all of its
+/// probability on the selected action, so games played by any agent can
+/// be recorded. The game is played by `play_game`, so agents are given a
+/// fork of the simulator.
+///
+/// ### Arguments
+///
+/// * `simulator` - The simulator that determines action outcomes in the domain.
+/// * `agents` - The agent for each player, indexed by player ID.
+/// * `encoder` - Converts observations and actions into network inputs and action indices.
+///
+/// ### Return Value
+///
+/// A record for every action selected in the game, in the order they were selected.
+pub fn record_game<S, A, O, I, G, E>(simulator: &mut I, agents: &mut [G], encoder: &E) -> Vec<Record> where
+    S: State,
+    A: Action,
+    O: Observation,
+    I: ImperfectInfoSimulator<S, A, O>,
+    G: Agent<S, A>,
+    E: Encoder<O, A>,
+{
+    return record_variants(simulator, agents, encoder, |state, legal_actions, policy| {
+        vec![(state.clone(), legal_actions.to_vec(), policy.to_vec())]
+    });
+}
+
+/// Plays a game like `record_game`, augmenting the dataset with
+/// a record of every symmetric variant of each decision.
+///
+/// ### Return Value
+///
+/// For every action selected in the game, a record of each symmetric
+/// variant of the decision, starting with the decision itself.
+pub fn record_symmetric_game<S, A, O, I, G, E>(simulator: &mut I, agents: &mut [G], encoder: &E) -> Vec<Record> where
+    S: Symmetric<A>,
+    A: Action,
+    O: Observation,
+    I: ImperfectInfoSimulator<S, A, O>,
+    G: Agent<S, A>,
+    E: Encoder<O, A>,
+{
+    return record_variants(simulator, agents, encoder, |state, legal_actions, policy| {
+        state.symmetries().into_iter()
+            .map(|(variant, symmetry)| (
+                variant,
+                legal_actions.iter().map(|action| state.transform_action(action, symmetry)).collect(),
+                policy.iter().map(|(action, p)| (state.transform_action(action, symmetry), *p)).collect(),
+            ))
+            .collect()
+    });
+}
+
+/// Plays a game and records the variants of every decision.
+///
+/// ### Arguments
+///
+/// * `variants` - Converts a state, its legal actions for the acting player and
+///   the policy followed into the variants of the decision to record.
+fn record_variants<S, A, O, I, G, E, F>(simulator: &mut I, agents: &mut [G], encoder: &E, mut variants: F) -> Vec<Record> where
+    S: State,
+    A: Action,
+    O: Observation,
+    I: ImperfectInfoSimulator<S, A, O>,
+    G: Agent<S, A>,
+    E: Encoder<O, A>,
+    F: FnMut(&S, &[A], &[(A, f64)]) -> Vec<(S, Vec<A>, Vec<(A, f64)>)>,
+{
+    let mut decisions = DecisionObserver { decisions: Vec::new() };
+    let history = play_game(simulator, agents, &mut [&mut decisions]);
+    let outcome = history.outcome().expect("Game should be finished.").iter()
+        .map(|reward| reward.0 as f32)
+        .collect::<Vec<f32>>();
+    let mut records = Vec::new();
+    for Decision { player_id, state, action, policy } in decisions.decisions {
+        let legal_actions = simulator.calculate_legal_actions(&state).swap_remove(player_id).0.into_iter().collect::<Vec<A>>();
+        let policy = policy.unwrap_or_else(|| vec![(action, 1.0)]);
+        for (variant, legal_actions, policy) in variants(&state, &legal_actions, &policy) {
+            let observation = simulator.observe(&variant, player_id);
+            let mut record = decision_record(encoder, player_id, &observation, &legal_actions, &policy);
+            record.outcome = outcome.clone();
+            records.push(record);
+        }
+    }
+    return records;
+}
+
+/// An action selected by a player with the policy their agent followed, if any.
+struct Decision<S : State, A : Action> {
+    player_id: usize,
+    state: S,
+    action: A,
+    policy: Option<Vec<(A, f64)>>,
+}
+
+/// Collects every decision of a game.
+struct DecisionObserver<S : State, A : Action> {
+    decisions: Vec<Decision<S, A>>,
+}
+
+impl <S : State, A : Action> GameObserver<S, A> for DecisionObserver<S, A> {
+    fn on_action_selected(&mut self, player_id: usize, state: &S, action: &A, _info: Option<&str>, policy: Option<&[(A, f64)]>) {
+        self.decisions.push(Decision {
+            player_id,
+            state: state.clone(),
+            action: action.clone(),
+            policy: policy.map(|policy| policy.to_vec()),
+        });
+    }
+}
+
+/// A record of a decision without its outcome.
+fn decision_record<O, A, E>(encoder: &E, player_id: usize, observation: &O, legal_actions: &[A], policy: &[(A, f64)]) -> Record where
+    O: Observation,
+    A: Action,
+    E: Encoder<O, A>,
+{
+    let mut legal_mask = vec![false; encoder.policy_size()];
+    legal_actions.iter().for_each(|action| legal_mask[encoder.action_index(action)] = true);
+    let mut action_probabilities = vec![0.0; encoder.policy_size()];
+    policy.iter().for_each(|(action, p)| action_probabilities[encoder.action_index(action)] += *p as f32);
+    return Record {
+        player_id,
+        observation: encoder.encode(observation).into_iter().map(|x| x as f32).collect(),
+        legal_mask,
+        policy: action_probabilities,
+        outcome: Vec::new(),
+    };
+}
+
+/// Collects records and writes them to a directory as shuffled shards.
+#[derive(Debug)]
+pub struct DatasetWriter {
+    directory: PathBuf,
+    format: DatasetFormat,
+    shard_size: usize,
+    rng: Option<ChaCha8Rng>,
+    records: Vec<Record>,
+}
+
+impl DatasetWriter {
+    /// Creates a writer with no records.
+    ///
+    /// ### Arguments
+    ///
+    /// * `directory` - The directory shards are written to, created if it does not exist.
+    /// * `format` - The file format of the shards.
+    /// * `shard_size` - The maximum number of records in each shard.
+    /// * `rng` - Shuffles the records before they are written, or `None`
+    ///   to write them in the order they were added.
+    pub fn new(directory: &Path, format: DatasetFormat, shard_size: usize, rng: Option<ChaCha8Rng>) -> Self {
+        assert!(shard_size > 0, "shard size must be positive");
+        DatasetWriter { directory: directory.to_path_buf(), format, shard_size, rng, records: Vec::new() }
+    }
+
+    /// Adds records to be written.
+    pub fn add(&mut self, records: impl IntoIterator<Item = Record>) {
+        self.records.extend(records);
+    }
+
+    /// The number of records added.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Shuffles the records and writes them as `shard_<index>.<extension>`.
+    ///
+    /// ### Return Value
+    ///
+    /// The path of every shard written, or an error if the records do
+    /// not all have the same sizes or a shard cannot be written.
+    pub fn finish(mut self) -> Result<Vec<PathBuf>, String> {
+        dimensions(&self.records)?;
+        if let Some(rng) = &mut self.rng {
+            self.records.shuffle(rng);
+        }
+        fs::create_dir_all(&self.directory).map_err(|e| format!("{}: {}", self.directory.display(), e))?;
+        let mut paths = Vec::new();
+        for (index, shard) in self.records.chunks(self.shard_size).enumerate() {
+            let path = self.directory.join(format!("shard_{:05}.{}", index, self.format.extension()));
+            let file = File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let mut writer = BufWriter::new(file);
+            match self.format {
+                DatasetFormat::Binary => write_binary(&mut writer, shard),
+                DatasetFormat::Csv => write_csv(&mut writer, shard),
+                DatasetFormat::Npy => write_npy(&mut writer, shard),
+            }.and_then(|_| writer.flush().map_err(|e| e.to_string()))
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            paths.push(path);
+        }
+        return Ok(paths);
+    }
+}
+
+/// Writes records in the binary format.
+///
+/// ### Return Value
+///
+/// An error if the records do not all have the same sizes or cannot be written.
+pub fn write_binary<W : Write>(writer: &mut W, records: &[Record]) -> Result<(), String> {
+    let (observation_size, policy_size, players) = dimensions(records)?;
+    let mut bytes = BINARY_MAGIC.to_vec();
+    for size in [records.len(), observation_size, policy_size, players] {
+        bytes.extend((size as u32).to_le_bytes());
+    }
+    for record in records {
+        bytes.extend((record.player_id as u32).to_le_bytes());
+        record.observation.iter().for_each(|x| bytes.extend(x.to_le_bytes()));
+        for chunk in record.legal_mask.chunks(8) {
+            bytes.push(chunk.iter().enumerate().fold(0, |byte, (bit, &legal)| byte | ((legal as u8) << bit)));
+        }
+        record.policy.iter().chain(&record.outcome).for_each(|x| bytes.extend(x.to_le_bytes()));
+    }
+    return writer.write_all(&bytes).map_err(|e| e.to_string());
+}
+
+/// Reads records written by `write_binary`.
+pub fn read_binary<R : Read>(reader: &mut R) -> Result<Vec<Record>, String> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    let mut reader = ByteReader { bytes: &bytes, offset: 0 };
+    if reader.take(BINARY_MAGIC.len())? != BINARY_MAGIC {
+        return Err("not a binary dataset".to_string());
+    }
+    let length = reader.u32()? as usize;
+    let observation_size = reader.u32()? as usize;
+    let policy_size = reader.u32()? as usize;
+    let players = reader.u32()? as usize;
+    let mut records = Vec::with_capacity(length);
+    for _ in 0..length {
+        let player_id = reader.u32()? as usize;
+        let observation = reader.f32s(observation_size)?;
+        let mask_bytes = reader.take(policy_size.div_ceil(8))?;
+        let legal_mask = (0..policy_size).map(|i| mask_bytes[i / 8] & (1 << (i % 8)) != 0).collect();
+        let policy = reader.f32s(policy_size)?;
+        let outcome = reader.f32s(players)?;
+        records.push(Record { player_id, observation, legal_mask, policy, outcome });
+    }
+    return Ok(records);
+}
+
+/// Reads little-endian values from the start of a byte slice.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl <'a> ByteReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let slice = self.bytes.get(self.offset..self.offset + length).ok_or("unexpected end of dataset")?;
+        self.offset += length;
+        return Ok(slice);
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn f32s(&mut self, count: usize) -> Result<Vec<f32>, String> {
+        return Ok(self.take(4 * count)?.chunks(4).map(|x| f32::from_le_bytes(x.try_into().unwrap())).collect());
+    }
+}
+
+/// Writes records as CSV with a header row.
+///
+/// ### Return Value
+///
+/// An error if the records do not all have the same sizes or cannot be written.
+pub fn write_csv<W : Write>(writer: &mut W, records: &[Record]) -> Result<(), String> {
+    let (observation_size, policy_size, players) = dimensions(records)?;
+    let columns = column_names(observation_size, policy_size, players);
+    writeln!(writer, "{}", columns.join(",")).map_err(|e| e.to_string())?;
+    for record in records {
+        let row = row(record).iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        writeln!(writer, "{}", row.join(",")).map_err(|e| e.to_string())?;
+    }
+    return Ok(());
+}
+
+/// Writes records as a version 1.0 NumPy array of `float32` values
+/// with one row per record.
+///
+/// ### Return Value
+///
+/// An error if the records do not all have the same sizes or cannot be written.
+pub fn write_npy<W : Write>(writer: &mut W, records: &[Record]) -> Result<(), String> {
+    let (observation_size, policy_size, players) = dimensions(records)?;
+    let columns = 1 + observation_size + 2 * policy_size + players;
+    let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}", records.len(), columns);
+    // The data must start at a multiple of 64 bytes after the magic,
+    // version and header length, and the header must end in a newline.
+    let prefix_length = NPY_MAGIC.len() + 4;
+    let padding = 63 - (prefix_length + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut bytes = NPY_MAGIC.to_vec();
+    bytes.extend([1, 0]);
+    bytes.extend((header.len() as u16).to_le_bytes());
+    bytes.extend(header.as_bytes());
+    for record in records {
+        row(record).iter().for_each(|x| bytes.extend(x.to_le_bytes()));
+    }
+    return writer.write_all(&bytes).map_err(|e| e.to_string());
+}
+
+/// The columns of the CSV and NumPy formats.
+fn column_names(observation_size: usize, policy_size: usize, players: usize) -> Vec<String> {
+    let mut columns = vec!["player".to_string()];
+    columns.extend((0..observation_size).map(|i| format!("observation_{}", i)));
+    columns.extend((0..policy_size).map(|i| format!("mask_{}", i)));
+    columns.extend((0..policy_size).map(|i| format!("policy_{}", i)));
+    columns.extend((0..players).map(|i| format!("outcome_{}", i)));
+    return columns;
+}
+
+fn row(record: &Record) -> Vec<f32> {
+    let mut row = vec![record.player_id as f32];
+    row.extend(&record.observation);
+    row.extend(record.legal_mask.iter().map(|&legal| if legal { 1.0 } else { 0.0 }));
+    row.extend(&record.policy);
+    row.extend(&record.outcome);
+    return row;
+}
+
+/// Checks that every record has the same sizes.
+///
+/// ### Return Value
+///
+/// The observation size, policy size and number of players.
+fn dimensions(records: &[Record]) -> Result<(usize, usize, usize), String> {
+    let Some(first) = records.first() else {
+        return Ok((0, 0, 0));
+    };
+    let dimensions = (first.observation.len(), first.policy.len(), first.outcome.len());
+    for (i, record) in records.iter().enumerate() {
+        if (record.observation.len(), record.policy.len(), record.outcome.len()) != dimensions
+            || record.legal_mask.len() != dimensions.1 {
+            return Err(format!("record {} does not match the sizes of the first record", i));
+        }
+    }
+    return Ok(dimensions);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::core::agent::DefaultAgents;
+    use crate::core::ismcts::IsmctsAgent;
+    use crate::core::testing::counters::{CountersEncoder, CountersSimulator};
+
+    use super::*;
+
+    const MAX_COUNTERS: u8 = 5;
+
+    fn random_agents() -> [DefaultAgents; 2] {
+        return [DefaultAgents::Random(ChaCha8Rng::seed_from_u64(0)), DefaultAgents::Random(ChaCha8Rng::seed_from_u64(1))];
+    }
+
+    fn records(games: usize) -> Vec<Record> {
+        let mut agents = random_agents();
+        return (0..games).flat_map(|_| record_game(&mut CountersSimulator::new(MAX_COUNTERS), &mut agents, &CountersEncoder::new(MAX_COUNTERS))).collect();
+    }
+
+    #[test]
+    fn record_game_without_agent_policy() {
+        let records = record_game(&mut CountersSimulator::new(MAX_COUNTERS), &mut random_agents(), &CountersEncoder::new(MAX_COUNTERS));
+        let taken = records.iter()
+            .map(|record| record.policy.iter().position(|&p| p == 1.0).unwrap() + 1)
+            .sum::<usize>();
+        assert_eq!(taken, MAX_COUNTERS as usize);
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(record.player_id, i % 2);
+            assert_eq!(record.observation.len(), CountersEncoder::new(MAX_COUNTERS).input_size());
+            assert_eq!(record.policy.iter().sum::<f32>(), 1.0);
+            assert!(record.policy.iter().zip(&record.legal_mask).all(|(&p, &legal)| legal || p == 0.0));
+            assert_eq!(record.outcome, records[0].outcome);
+        }
+        assert_eq!(records.last().unwrap().outcome[records.last().unwrap().player_id], 1.0);
+        assert_eq!(records[0].legal_mask, vec![true, true]);
+    }
+
+    #[test]
+    fn record_game_with_search_policy() {
+        let mut agents = [IsmctsAgent::new(100, 1.0, ChaCha8Rng::seed_from_u64(0)), IsmctsAgent::new(100, 1.0, ChaCha8Rng::seed_from_u64(1))];
+        let records = record_game(&mut CountersSimulator::new(MAX_COUNTERS), &mut agents, &CountersEncoder::new(MAX_COUNTERS));
+        assert!(records.iter().all(|record| (record.policy.iter().sum::<f32>() - 1.0).abs() < 1e-6));
+        assert!(records.iter().any(|record| record.policy.iter().all(|&p| p > 0.0)));
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let records = records(3);
+        let mut bytes = Vec::new();
+        write_binary(&mut bytes, &records).unwrap();
+        let header_size = BINARY_MAGIC.len() + 16;
+        let record_size = 4 + 4 * CountersEncoder::new(MAX_COUNTERS).input_size() + 1 + 4 * 2 + 4 * 2;
+        assert_eq!(bytes.len(), header_size + records.len() * record_size);
+        assert_eq!(read_binary(&mut bytes.as_slice()).unwrap(), records);
+        assert!(read_binary(&mut &bytes[..bytes.len() - 1]).is_err());
+        assert!(read_binary(&mut "not a dataset".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn csv_has_header_and_rows() {
+        let records = records(1);
+        let mut bytes = Vec::new();
+        write_csv(&mut bytes, &records).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        let lines = text.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), records.len() + 1);
+        assert!(lines[0].starts_with("player,observation_0,"));
+        assert!(lines[0].ends_with(",mask_0,mask_1,policy_0,policy_1,outcome_0,outcome_1"));
+        assert_eq!(lines[1].split(',').count(), lines[0].split(',').count());
+        assert!(lines[1].starts_with("0,"));
+    }
+
+    #[test]
+    fn npy_header_is_aligned() {
+        let records = records(1);
+        let mut bytes = Vec::new();
+        write_npy(&mut bytes, &records).unwrap();
+        assert_eq!(&bytes[..6], NPY_MAGIC);
+        let header_length = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_length) % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..10 + header_length]).unwrap();
+        let columns = 1 + CountersEncoder::new(MAX_COUNTERS).input_size() + 2 * 2 + 2;
+        assert!(header.contains(&format!("'shape': ({}, {})", records.len(), columns)));
+        assert!(header.ends_with('\n'));
+        assert_eq!(bytes.len(), 10 + header_length + 4 * records.len() * columns);
+    }
+
+    #[test]
+    fn mismatched_records_are_rejected() {
+        let mut records = records(1);
+        records[1].observation.pop();
+        assert!(write_binary(&mut Vec::new(), &records).is_err());
+        assert!(write_npy(&mut Vec::new(), &records).is_err());
+    }
+
+    #[test]
+    fn writer_shuffles_into_shards() {
+        let directory = std::env::temp_dir().join(format!("dataset_shards_{}", std::process::id()));
+        let records = records(4);
+        let mut writer = DatasetWriter::new(&directory, DatasetFormat::Binary, 5, Some(ChaCha8Rng::seed_from_u64(0)));
+        writer.add(records.clone());
+        assert_eq!(writer.len(), records.len());
+        let paths = writer.finish().unwrap();
+        assert_eq!(paths.len(), records.len().div_ceil(5));
+        assert!(paths[0].ends_with("shard_00000.bin"));
+
+        let mut written = Vec::new();
+        for path in &paths {
+            written.extend(read_binary(&mut File::open(path).unwrap()).unwrap());
+        }
+        assert_eq!(written.len(), records.len());
+        assert_ne!(written, records);
+        assert!(records.iter().all(|record| written.contains(record)));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,19 @@
+use super::simulator::Action;
+
+/// Converts what a player sees of a domain, a state or an observation,
+/// into the fixed-size numeric input of a neural network, and actions
+/// into indices of the network's policy output.
+pub trait Encoder<T, A : Action> {
+    /// The length of every encoded input.
+    fn input_size(&self) -> usize;
+
+    /// Encodes a state or observation as `input_size` numbers.
+    fn encode(&self, input: &T) -> Vec<f64>;
+
+    /// The number of distinct actions in the domain.
+    fn policy_size(&self) -> usize;
+
+    /// The index of an action in the policy output,
+    /// less than `policy_size`.
+    fn action_index(&self, action: &A) -> usize;
+}
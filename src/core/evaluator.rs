@@ -0,0 +1,19 @@
+use super::simulator::State;
+
+/// Estimates how good a non-terminal state is for each player, so that
+/// search agents can stop searching before the end of the game and
+/// trade search depth for knowledge of the domain.
+pub trait Evaluator<S : State> {
+    /// Estimates the rewards of every player from a state.
+    ///
+    /// ### Arguments
+    ///
+    /// * `state` - The state to evaluate.
+    ///
+    /// ### Return Value
+    ///
+    /// An estimate for each player, indexed by player ID, strictly between
+    /// the lowest and highest rewards of the domain so that estimates are
+    /// never preferred to actual wins or losses.
+    fn evaluate(&self, state: &S) -> Vec<f64>;
+}
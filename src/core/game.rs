@@ -0,0 +1,445 @@
+use std::collections::HashMap;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use super::agent::{Agent, ImperfectInfoAgent};
+use super::history::History;
+use super::imperfect_info::{ImperfectInfoSimulator, Observation};
+use super::observer::GameObserver;
+use super::simulator::{Action, Simulator, State};
+
+/// Seeds the forks of the simulator that agents are given.
+const FORK_SEED: u64 = 0;
+
+/// Plays a game from the initial state until a terminal state is reached.
+///
+/// Every player with legal actions selects an action from the same state
+/// without seeing the other players' choices, so simultaneous moves are
+/// blind. Observers are notified of the selected actions once all players
+/// have chosen, and of each event in the order given. Agents are given
+/// a fork of the simulator, so they cannot change the game.
+///
+/// ### Arguments
+///
+/// * `simulator` - The simulator that determines action outcomes in the domain.
+/// * `agents` - The agent for each player, indexed by player ID.
+/// * `observers` - Observers notified of game events.
+///
+/// ### Return Value
+///
+/// The history of the game with the step rewards of each transition,
+/// and the rewards for each player in the terminal state as its outcome.
+pub fn play_game<S, A, I, G>(simulator: &mut I, agents: &mut [G], observers: &mut [&mut dyn GameObserver<S, A>]) -> History<S, A> where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+    G: Agent<S, A>,
+{
+    let mut state = simulator.generate_initial_state();
+    let mut history = History::new(state.clone());
+    let mut fork_rng = ChaCha8Rng::seed_from_u64(FORK_SEED);
+    observers.iter_mut().for_each(|observer| observer.on_game_start(&state));
+
+    while !simulator.is_terminal_state(&state) {
+        let player_legal_actions = simulator.calculate_legal_actions(&state);
+        let mut selected_actions: Vec<Option<A>> = Vec::new();
+        let mut action_infos: Vec<Option<String>> = Vec::new();
+        let mut action_policies: Vec<Option<Vec<(A, f64)>>> = Vec::new();
+        for player_id in 0..simulator.number_of_players() {
+            if player_legal_actions[player_id].0.is_empty() {
+                selected_actions.push(None);
+                action_infos.push(None);
+                action_policies.push(None);
+            } else {
+                selected_actions.push(Some(agents[player_id].select_action(player_id, &state, &mut simulator.fork(&mut fork_rng))));
+                action_infos.push(agents[player_id].action_info());
+                action_policies.push(agents[player_id].action_policy());
+            }
+        }
+        notify_actions_selected(observers, &state, &selected_actions, &action_infos, &action_policies);
+        let next_state = simulator.state_transition(&state, &selected_actions);
+        record_transition(simulator, &mut history, &state, &selected_actions, &next_state);
+        observers.iter_mut().for_each(|observer| observer.on_state_transition(&state, &selected_actions, &next_state));
+        state = next_state;
+    }
+
+    let rewards = simulator.calculate_rewards(&state);
+    observers.iter_mut().for_each(|observer| observer.on_game_end(&state, &rewards));
+    history.set_outcome(rewards);
+    return history;
+}
+
+/// Plays a game with hidden information from the initial state
+/// until a terminal state is reached.
+///
+/// Agents only receive their player's observation of the current state
+/// and the actions visible to their player, while observers see the
+/// full state and all actions. As in `play_game`, simultaneous moves
+/// are selected blind before any of them are applied, and agents are
+/// given a fork of the simulator.
+///
+/// ### Arguments
+///
+/// * `simulator` - The simulator that determines action outcomes in the domain.
+/// * `agents` - The agent for each player, indexed by player ID.
+/// * `observers` - Observers notified of game events.
+///
+/// ### Return Value
+///
+/// The history of the game, recorded as in `play_game`.
+pub fn play_imperfect_info_game<S, A, O, I, G>(simulator: &mut I, agents: &mut [G], observers: &mut [&mut dyn GameObserver<S, A>]) -> History<S, A> where
+    S: State,
+    A: Action,
+    O: Observation,
+    I: ImperfectInfoSimulator<S, A, O>,
+    G: ImperfectInfoAgent<S, A, O>,
+{
+    let mut state = simulator.generate_initial_state();
+    let mut history = History::new(state.clone());
+    let mut fork_rng = ChaCha8Rng::seed_from_u64(FORK_SEED);
+    let number_of_players = simulator.number_of_players();
+    let mut action_histories: Vec<Vec<Vec<Option<A>>>> = vec![Vec::new(); number_of_players];
+    observers.iter_mut().for_each(|observer| observer.on_game_start(&state));
+
+    while !simulator.is_terminal_state(&state) {
+        let player_legal_actions = simulator.calculate_legal_actions(&state);
+        let mut selected_actions: Vec<Option<A>> = Vec::new();
+        let mut action_infos: Vec<Option<String>> = Vec::new();
+        for player_id in 0..number_of_players {
+            if player_legal_actions[player_id].0.is_empty() {
+                selected_actions.push(None);
+                action_infos.push(None);
+            } else {
+                let observation = simulator.observe(&state, player_id);
+                selected_actions.push(Some(agents[player_id].select_action(
+                    player_id,
+                    &observation,
+                    &action_histories[player_id],
+                    &player_legal_actions[player_id],
+                    &mut simulator.fork(&mut fork_rng),
+                )));
+                action_infos.push(agents[player_id].action_info());
+            }
+        }
+        let action_policies = vec![None; number_of_players];
+        notify_actions_selected(observers, &state, &selected_actions, &action_infos, &action_policies);
+        let next_state = simulator.state_transition(&state, &selected_actions);
+        record_transition(simulator, &mut history, &state, &selected_actions, &next_state);
+        for player_id in 0..number_of_players {
+            let visible_actions = simulator.observe_actions(&state, &selected_actions, player_id);
+            action_histories[player_id].push(visible_actions);
+        }
+        observers.iter_mut().for_each(|observer| observer.on_state_transition(&state, &selected_actions, &next_state));
+        state = next_state;
+    }
+
+    let rewards = simulator.calculate_rewards(&state);
+    observers.iter_mut().for_each(|observer| observer.on_game_end(&state, &rewards));
+    history.set_outcome(rewards);
+    return history;
+}
+
+/// Adds a state transition to the history with the step rewards of each player.
+fn record_transition<S, A, I>(simulator: &mut I, history: &mut History<S, A>, state: &S, selected_actions: &[Option<A>], next_state: &S) where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    let rewards = simulator.step_rewards(state, selected_actions, next_state);
+    let actions = selected_actions.iter().enumerate()
+        .filter_map(|(player_id, action)| action.clone().map(|action| (player_id, action)))
+        .collect::<HashMap<usize, A>>();
+    history.push(next_state.clone(), actions, rewards);
+}
+
+/// Notifies observers of every selected action in player order.
+fn notify_actions_selected<S, A>(
+    observers: &mut [&mut dyn GameObserver<S, A>],
+    state: &S,
+    selected_actions: &[Option<A>],
+    action_infos: &[Option<String>],
+    action_policies: &[Option<Vec<(A, f64)>>],
+) where
+    S: State,
+    A: Action,
+{
+    for (player_id, action) in selected_actions.iter().enumerate() {
+        if let Some(action) = action {
+            let info = action_infos[player_id].as_deref();
+            let policy = action_policies[player_id].as_deref();
+            observers.iter_mut().for_each(|observer| observer.on_action_selected(player_id, state, action, info, policy));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use rand::Rng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::core::reward::{Reward, ADVERSARIAL_DRAW, ADVERSARIAL_P1_LOSS, ADVERSARIAL_P1_WIN};
+    use crate::core::simulator::LegalActions;
+    use crate::core::testing::counters::{CountersSimulator, CountersState, TestAction};
+
+    use super::*;
+
+    #[test]
+    fn play_game_returns_terminal_rewards() {
+        let mut simulator = CountersSimulator::new(2);
+        let mut agents = [TestAgent, TestAgent];
+        let history = play_game(&mut simulator, &mut agents, &mut []);
+        assert_eq!(history.outcome(), Some(&ADVERSARIAL_P1_LOSS[..]));
+    }
+
+    #[test]
+    fn play_game_records_step_rewards_apart_from_outcome() {
+        let mut simulator = CountersSimulator::new(2);
+        let mut agents = [TestAgent, TestAgent];
+        let history = play_game(&mut simulator, &mut agents, &mut []);
+        assert_eq!(history.states().iter().map(|state| state.to_string()).collect::<Vec<String>>(), vec!["2 0", "1 1", "0 0"]);
+        assert_eq!(history.rewards(), &[ADVERSARIAL_DRAW.to_vec(), ADVERSARIAL_DRAW.to_vec()]);
+    }
+
+    #[test]
+    fn play_game_notifies_observers_in_order() {
+        let mut simulator = CountersSimulator::new(2);
+        let mut agents = [TestAgent, TestAgent];
+        let mut first = RecordingObserver::default();
+        let mut second = RecordingObserver::default();
+        play_game(&mut simulator, &mut agents, &mut [&mut first, &mut second]);
+        let expected = vec![
+            "start 2 0",
+            "player 0 selected 1 (took 1)",
+            "2 0 -> 1 1",
+            "player 1 selected 1 (took 1)",
+            "1 1 -> 0 0",
+            "end 0 0 [Reward(-1.0), Reward(1.0)]",
+        ];
+        assert_eq!(first.events, expected);
+        assert_eq!(second.events, expected);
+    }
+
+    #[test]
+    fn play_game_applies_simultaneous_actions_together() {
+        let mut simulator = SimultaneousSimulator;
+        let mut agents = [TestAgent, TestAgent];
+        let mut observer = RecordingObserver::default();
+        play_game(&mut simulator, &mut agents, &mut [&mut observer]);
+        let expected = vec![
+            "start 2 0",
+            "player 0 selected 1 (took 1)",
+            "player 1 selected 1 (took 1)",
+            "2 0 -> 0 0",
+            "end 0 0 [Reward(0.0), Reward(0.0)]",
+        ];
+        assert_eq!(observer.events, expected);
+    }
+
+    #[test]
+    fn play_imperfect_info_game_hides_secret() {
+        let mut simulator = SecretSimulator;
+        let mut agents = [RecordingAgent::default(), RecordingAgent::default()];
+        let history = play_imperfect_info_game(&mut simulator, &mut agents, &mut []);
+        assert_eq!(agents[0].observations, vec!["secret ? guess ?"]);
+        assert_eq!(agents[1].observations, vec!["secret hidden guess ?"]);
+        assert_eq!(agents[1].histories, vec!["[[None, None]]"]);
+        assert_eq!(history.outcome(), Some(&ADVERSARIAL_P1_LOSS[..]));
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: Vec<String>,
+    }
+
+    impl GameObserver<CountersState, TestAction> for RecordingObserver {
+        fn on_game_start(&mut self, state: &CountersState) {
+            self.events.push(format!("start {}", state));
+        }
+
+        fn on_action_selected(&mut self, player_id: usize, _state: &CountersState, action: &TestAction, info: Option<&str>, _policy: Option<&[(TestAction, f64)]>) {
+            self.events.push(format!("player {} selected {} ({})", player_id, action, info.unwrap_or("")));
+        }
+
+        fn on_state_transition(&mut self, state: &CountersState, _actions: &[Option<TestAction>], next_state: &CountersState) {
+            self.events.push(format!("{} -> {}", state, next_state));
+        }
+
+        fn on_game_end(&mut self, state: &CountersState, rewards: &[Reward]) {
+            self.events.push(format!("end {} {:?}", state, rewards));
+        }
+    }
+
+    /// Always takes a single counter.
+    struct TestAgent;
+
+    impl <S : State, A : Action> Agent<S, A> for TestAgent {
+        fn select_action<I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
+            I: Simulator<S, A>,
+        {
+            let legal_actions = &simulator.calculate_legal_actions(state)[player_id];
+            return legal_actions.iter().find(|action| action.to_string() == "1").unwrap().clone();
+        }
+
+        fn action_info(&self) -> Option<String> {
+            Some("took 1".to_string())
+        }
+    }
+
+    /// Both players remove one or two counters at the same time.
+    #[derive(Clone)]
+    struct SimultaneousSimulator;
+
+    impl Simulator<CountersState, TestAction> for SimultaneousSimulator {
+        fn generate_initial_state(&mut self) -> CountersState {
+            CountersState { counters: 2, turn: 0 }
+        }
+
+        fn calculate_rewards(&mut self, _state: &CountersState) -> Vec<Reward> {
+            ADVERSARIAL_DRAW.to_vec()
+        }
+
+        fn calculate_legal_actions(&mut self, state: &CountersState) -> Vec<LegalActions<TestAction>> {
+            let mut legal_actions = vec![LegalActions::new(), LegalActions::new()];
+            for player_legal_actions in legal_actions.iter_mut() {
+                (1..=(state.counters / 2).min(2)).for_each(|x| player_legal_actions.insert(TestAction(x)));
+            }
+            return legal_actions;
+        }
+
+        fn state_transition(&mut self, state: &CountersState, actions: &Vec<Option<TestAction>>) -> CountersState {
+            let taken = actions.iter().flatten().map(|action| action.0).sum::<u8>();
+            return CountersState { counters: state.counters - taken, turn: state.turn };
+        }
+
+        fn number_of_players(&mut self) -> usize {
+            2
+        }
+    }
+
+    /// Records what it is given and selects the largest legal action.
+    #[derive(Default)]
+    struct RecordingAgent {
+        observations: Vec<String>,
+        histories: Vec<String>,
+    }
+
+    impl <S : State, A : Action, O : Observation> ImperfectInfoAgent<S, A, O> for RecordingAgent {
+        fn select_action<I>(
+            &mut self,
+            _player_id: usize,
+            observation: &O,
+            action_history: &[Vec<Option<A>>],
+            legal_actions: &LegalActions<A>,
+            _simulator: &mut I,
+        ) -> A where
+            I: ImperfectInfoSimulator<S, A, O>,
+        {
+            self.observations.push(observation.to_string());
+            self.histories.push(format!("{:?}", action_history));
+            return legal_actions.iter().max_by_key(|action| action.to_string()).unwrap().clone();
+        }
+    }
+
+    /// Player 1 secretly picks a number and player 2 wins by guessing it.
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct SecretState {
+        secret: Option<u8>,
+        guess: Option<u8>,
+    }
+
+    impl State for SecretState {}
+
+    impl fmt::Display for SecretState {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:?} {:?}", self.secret, self.guess)
+        }
+    }
+
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct SecretObservation {
+        secret: Option<Option<u8>>,
+        guess: Option<u8>,
+    }
+
+    impl Observation for SecretObservation {}
+
+    impl fmt::Display for SecretObservation {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let secret = match self.secret {
+                None => "hidden".to_string(),
+                Some(None) => "?".to_string(),
+                Some(Some(secret)) => secret.to_string(),
+            };
+            let guess = self.guess.map_or("?".to_string(), |guess| guess.to_string());
+            write!(f, "secret {} guess {}", secret, guess)
+        }
+    }
+
+    #[derive(Clone)]
+    struct SecretSimulator;
+
+    impl Simulator<SecretState, TestAction> for SecretSimulator {
+        fn generate_initial_state(&mut self) -> SecretState {
+            SecretState { secret: None, guess: None }
+        }
+
+        fn calculate_rewards(&mut self, state: &SecretState) -> Vec<Reward> {
+            match (state.secret, state.guess) {
+                (Some(secret), Some(guess)) if secret == guess => ADVERSARIAL_P1_LOSS.to_vec(),
+                (_, Some(_)) => ADVERSARIAL_P1_WIN.to_vec(),
+                _ => ADVERSARIAL_DRAW.to_vec(),
+            }
+        }
+
+        fn calculate_legal_actions(&mut self, state: &SecretState) -> Vec<LegalActions<TestAction>> {
+            let mut legal_actions = vec![LegalActions::new(), LegalActions::new()];
+            let player_id = match (state.secret, state.guess) {
+                (None, _) => Some(0),
+                (Some(_), None) => Some(1),
+                _ => None,
+            };
+            if let Some(player_id) = player_id {
+                (1..=2).for_each(|x| legal_actions[player_id].insert(TestAction(x)));
+            }
+            return legal_actions;
+        }
+
+        fn state_transition(&mut self, state: &SecretState, actions: &Vec<Option<TestAction>>) -> SecretState {
+            return match (&actions[0], &actions[1]) {
+                (Some(secret), _) => SecretState { secret: Some(secret.0), guess: None },
+                (_, Some(guess)) => SecretState { secret: state.secret, guess: Some(guess.0) },
+                _ => state.clone(),
+            };
+        }
+
+        fn number_of_players(&mut self) -> usize {
+            2
+        }
+    }
+
+    impl ImperfectInfoSimulator<SecretState, TestAction, SecretObservation> for SecretSimulator {
+        fn observe(&mut self, state: &SecretState, player_id: usize) -> SecretObservation {
+            let secret = if player_id == 0 { Some(state.secret) } else { None };
+            SecretObservation { secret, guess: state.guess }
+        }
+
+        fn sample_determinization(&mut self, observation: &SecretObservation, _player_id: usize, rng: &mut ChaCha8Rng) -> SecretState {
+            let secret = match observation.secret {
+                Some(secret) => secret,
+                None => Some(rng.gen_range(1..=2)),
+            };
+            SecretState { secret, guess: observation.guess }
+        }
+
+        fn observe_actions(&mut self, _state: &SecretState, actions: &[Option<TestAction>], player_id: usize) -> Vec<Option<TestAction>> {
+            let mut actions = actions.to_vec();
+            if player_id != 0 {
+                actions[0] = None;
+            }
+            return actions;
+        }
+    }
+}
@@ -1,11 +1,15 @@
+use super::reward::Reward;
 use super::simulator::Action;
 use super::simulator::State;
 
 use std::collections::HashMap;
 
-/// Keeps track of state transition history.
+/// Keeps track of state transition history, the rewards
+/// received for each state transition and the outcome.
 pub struct History<S : State, A : Action> {
     nodes: Vec<HistoryNode<S, A>>,
+    rewards: Vec<Vec<Reward>>,
+    outcome: Option<Vec<Reward>>,
 }
 
 /// A history node can be a state or set of actions.
@@ -20,17 +24,22 @@ impl <S : State, A : Action> History<S, A> {
     pub fn new(initial_state: S) -> Self {
         History {
             nodes: vec![HistoryNode::State(initial_state)],
+            rewards: vec![],
+            outcome: None,
         }
     }
 
-    /// Add the next state and the actions taken by each agent
-    /// to arrive at that state.
-    pub fn push(&mut self, state: S, actions: HashMap<usize, A>) {
+    /// Add the next state, the actions taken by each agent
+    /// to arrive at that state and the step rewards of each
+    /// player for the state transition.
+    pub fn push(&mut self, state: S, actions: HashMap<usize, A>, rewards: Vec<Reward>) {
         self.nodes.push(HistoryNode::Actions(actions));
         self.nodes.push(HistoryNode::State(state));
+        self.rewards.push(rewards);
     }
 
-    /// Remove the last state and actions from the history.
+    /// Remove the last state and actions from the history,
+    /// along with the outcome if there is one.
     /// If the history is already at an initial state, then
     /// this function will panic.
     pub fn pop(&mut self) {
@@ -39,6 +48,44 @@ impl <S : State, A : Action> History<S, A> {
         }
         self.nodes.pop();
         self.nodes.pop();
+        self.rewards.pop();
+        self.outcome = None;
+    }
+
+    /// The number of state transitions in the history.
+    pub fn len(&self) -> usize {
+        self.rewards.len()
+    }
+
+    /// True if the history only contains the initial state.
+    pub fn is_empty(&self) -> bool {
+        self.rewards.is_empty()
+    }
+
+    /// Every state in the history from the initial state onwards.
+    pub fn states(&self) -> Vec<&S> {
+        return self.nodes.iter()
+            .filter_map(|node| match node {
+                HistoryNode::State(state) => Some(state),
+                HistoryNode::Actions(_) => None,
+            })
+            .collect();
+    }
+
+    /// The rewards of each player for every state transition.
+    pub fn rewards(&self) -> &[Vec<Reward>] {
+        &self.rewards
+    }
+
+    /// Records the final rewards of the game once its last state
+    /// is reached, apart from the step rewards of the last transition.
+    pub fn set_outcome(&mut self, outcome: Vec<Reward>) {
+        self.outcome = Some(outcome);
+    }
+
+    /// The final rewards of the game, if it has finished.
+    pub fn outcome(&self) -> Option<&[Reward]> {
+        self.outcome.as_deref()
     }
 
     pub fn peek(&self) -> (&S, Option<&HashMap<usize, A>>) {
@@ -46,7 +93,7 @@ impl <S : State, A : Action> History<S, A> {
             Some(HistoryNode::State(state)) => Some(state),
             _ => None,
         };
-        let actions = match self.nodes.get(self.nodes.len() - 2) {
+        let actions = match self.nodes.len().checked_sub(2).and_then(|i| self.nodes.get(i)) {
             Some(HistoryNode::Actions(actions)) => Some(actions),
             _ => None,
         };
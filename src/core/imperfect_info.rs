@@ -0,0 +1,60 @@
+use std::fmt;
+use std::hash::Hash;
+
+use rand_chacha::ChaCha8Rng;
+
+use super::simulator::{Action, Simulator, State};
+
+/// The part of a state that a single player can see.
+pub trait Observation : Clone + fmt::Debug + fmt::Display + Hash + Eq {}
+
+/// A simulator for domains where players cannot see the full state,
+/// such as hidden cards or hidden dice rolls.
+///
+/// Agents playing through `play_imperfect_info_game` only receive their
+/// own observation and the actions visible to them, never the state.
+pub trait ImperfectInfoSimulator<S : State, A : Action, O : Observation> : Simulator<S, A> {
+    /// Calculates what a player can see of a state.
+    ///
+    /// ### Arguments
+    ///
+    /// * `state` - The full domain state.
+    /// * `player_id` - The player observing the state.
+    ///
+    /// ### Return Value
+    ///
+    /// The player's observation of the state.
+    fn observe(&mut self, state: &S, player_id: usize) -> O;
+
+    /// Samples a full state that is consistent with what a player
+    /// has observed, filling in hidden information at random.
+    /// Used by search agents to plan without seeing the true state.
+    ///
+    /// ### Arguments
+    ///
+    /// * `observation` - The player's observation of the current state.
+    /// * `player_id` - The observing player.
+    /// * `rng` - The random number generator used to fill in hidden information.
+    ///
+    /// ### Return Value
+    ///
+    /// A state that the player would observe as `observation`.
+    fn sample_determinization(&mut self, observation: &O, player_id: usize, rng: &mut ChaCha8Rng) -> S;
+
+    /// Calculates which of the actions performed from a state a
+    /// player can see. Hidden actions are replaced with `None`.
+    /// All actions are public by default.
+    ///
+    /// ### Arguments
+    ///
+    /// * `state` - The state the actions were performed from.
+    /// * `actions` - The actions performed by each player.
+    /// * `player_id` - The player observing the actions.
+    ///
+    /// ### Return Value
+    ///
+    /// The actions of each player as seen by the observing player.
+    fn observe_actions(&mut self, _state: &S, actions: &[Option<A>], _player_id: usize) -> Vec<Option<A>> {
+        return actions.to_vec();
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+use super::agent::{Agent, ImperfectInfoAgent};
+use super::evaluator::Evaluator;
+use super::imperfect_info::{ImperfectInfoSimulator, Observation};
+use super::simulator::{Action, LegalActions, Simulator, State};
+
+/// Number of transitions after which a rollout is stopped
+/// and the rewards of the current state are used.
+const MAX_ROLLOUT_STEPS: usize = 10_000;
+
+/// Information Set Monte Carlo Tree Search (single observer).
+///
+/// Each iteration samples a determinization consistent with the agent's
+/// observation, descends the tree of actions with UCB applied to each
+/// player's own action statistics, expands one node and finishes with a
+/// random rollout, which may be cut off early and scored by an evaluator.
+/// Action statistics count how often each action was
+/// available so that actions legal in few determinizations are not
+/// over-explored.
+///
+/// In fully observable domains this is plain UCT.
+#[derive(Debug)]
+pub struct IsmctsAgent<S : State, A : Action> {
+    iterations: usize,
+    exploration: f64,
+    rng: ChaCha8Rng,
+    info: Option<String>,
+    policy: Option<Vec<(A, f64)>>,
+    cutoff: Option<RolloutCutoff<S>>,
+}
+
+/// Stops rollouts after a number of steps and evaluates the state reached.
+struct RolloutCutoff<S : State> {
+    steps: usize,
+    evaluator: Box<dyn Evaluator<S>>,
+}
+
+impl <S : State> fmt::Debug for RolloutCutoff<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RolloutCutoff").field("steps", &self.steps).finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+struct ActionStatistics {
+    visits: u32,
+    availability: u32,
+    total_reward: f64,
+}
+
+#[derive(Debug)]
+struct Node<A : Action> {
+    children: HashMap<Vec<Option<A>>, usize>,
+    statistics: Vec<HashMap<A, ActionStatistics>>,
+}
+
+impl <A : Action> Node<A> {
+    fn new(number_of_players: usize) -> Self {
+        Node {
+            children: HashMap::new(),
+            statistics: (0..number_of_players).map(|_| HashMap::new()).collect(),
+        }
+    }
+}
+
+impl <S : State, A : Action> IsmctsAgent<S, A> {
+    /// Creates an agent that runs a fixed number of iterations per action.
+    ///
+    /// ### Arguments
+    ///
+    /// * `iterations` - The number of determinized iterations per action.
+    /// * `exploration` - The UCB exploration constant, on the scale of the domain's rewards.
+    /// * `rng` - The random number generator used for determinizations and rollouts.
+    pub fn new(iterations: usize, exploration: f64, rng: ChaCha8Rng) -> Self {
+        IsmctsAgent { iterations, exploration, rng, info: None, policy: None, cutoff: None }
+    }
+
+    /// Stops rollouts after a number of random steps and uses an evaluator's
+    /// estimate of the state reached instead of playing to the end of the game.
+    ///
+    /// ### Arguments
+    ///
+    /// * `steps` - The number of random steps before the state is evaluated.
+    /// * `evaluator` - Estimates the rewards of states where rollouts stop,
+    ///   on the scale of the domain's rewards.
+    pub fn with_rollout_cutoff<E : Evaluator<S> + 'static>(mut self, steps: usize, evaluator: E) -> Self {
+        self.cutoff = Some(RolloutCutoff { steps, evaluator: Box::new(evaluator) });
+        return self;
+    }
+
+    fn search<I, F>(&mut self, player_id: usize, legal_actions: &LegalActions<A>, simulator: &mut I, mut determinize: F) -> A where
+        I: Simulator<S, A>,
+        F: FnMut(&mut I, &mut ChaCha8Rng) -> S,
+    {
+        let simulator = &mut simulator.fork(&mut self.rng);
+        let number_of_players = simulator.number_of_players();
+        let mut nodes: Vec<Node<A>> = vec![Node::new(number_of_players)];
+
+        for _ in 0..self.iterations {
+            let mut state = determinize(simulator, &mut self.rng);
+            let mut node = 0;
+            let mut path: Vec<(usize, Vec<Option<A>>)> = Vec::new();
+
+            loop {
+                let all_legal_actions = simulator.calculate_legal_actions(&state);
+                if all_legal_actions.iter().all(|x| x.0.is_empty()) {
+                    break;
+                }
+                let mut expanded = false;
+                let actions = all_legal_actions.iter().enumerate()
+                    .map(|(player, legal_actions)| {
+                        if legal_actions.0.is_empty() {
+                            return None;
+                        }
+                        let statistics = &mut nodes[node].statistics[player];
+                        let (action, is_new) = select(statistics, legal_actions, self.exploration, &mut self.rng);
+                        expanded |= is_new;
+                        Some(action)
+                    })
+                    .collect::<Vec<Option<A>>>();
+                state = simulator.state_transition(&state, &actions);
+                let next_node = nodes.len();
+                node = *nodes[node].children.entry(actions.clone()).or_insert(next_node);
+                if node == next_node {
+                    nodes.push(Node::new(number_of_players));
+                }
+                path.push((node, actions));
+                if expanded {
+                    break;
+                }
+            }
+
+            let rewards = rollout(simulator, state, self.cutoff.as_ref(), &mut self.rng);
+            let mut parent = 0;
+            for (child, actions) in path {
+                for (player, action) in actions.iter().enumerate() {
+                    if let Some(action) = action {
+                        let statistics = nodes[parent].statistics[player].get_mut(action).unwrap();
+                        statistics.visits += 1;
+                        statistics.total_reward += rewards[player];
+                    }
+                }
+                parent = child;
+            }
+        }
+
+        let root_statistics = &nodes[0].statistics[player_id];
+        let (action, statistics) = legal_actions.iter()
+            .filter_map(|action| root_statistics.get(action).map(|statistics| (action, statistics)))
+            .max_by_key(|(_, statistics)| statistics.visits)
+            .expect("Player should have legal actions.");
+        let total_visits = root_statistics.values().map(|statistics| statistics.visits).sum::<u32>().max(1) as f64;
+        self.policy = Some(legal_actions.iter()
+            .map(|action| {
+                let visits = root_statistics.get(action).map_or(0, |statistics| statistics.visits);
+                (action.clone(), visits as f64 / total_visits)
+            })
+            .collect());
+        self.info = Some(format!(
+            "iterations: {}, nodes: {}, visits: {}, mean reward: {:.3}",
+            self.iterations,
+            nodes.len(),
+            statistics.visits,
+            statistics.total_reward / statistics.visits as f64,
+        ));
+        return action.clone();
+    }
+}
+
+/// Selects an unvisited action at random if there is one, otherwise the
+/// action with the highest upper confidence bound. Every legal action
+/// has its availability incremented.
+///
+/// ### Return Value
+///
+/// The selected action and whether it had not been visited before.
+fn select<A : Action>(
+    statistics: &mut HashMap<A, ActionStatistics>,
+    legal_actions: &LegalActions<A>,
+    exploration: f64,
+    rng: &mut ChaCha8Rng,
+) -> (A, bool) {
+    legal_actions.iter().for_each(|action| statistics.entry(action.clone()).or_default().availability += 1);
+    let unvisited = legal_actions.iter()
+        .filter(|action| statistics[*action].visits == 0)
+        .collect::<Vec<&A>>();
+    if !unvisited.is_empty() {
+        let action = unvisited[rng.gen_range(0..unvisited.len())];
+        return (action.clone(), true);
+    }
+    let upper_confidence_bound = |statistics: &ActionStatistics| {
+        let visits = statistics.visits as f64;
+        statistics.total_reward / visits + exploration * ((statistics.availability as f64).ln() / visits).sqrt()
+    };
+    let action = legal_actions.iter()
+        .max_by(|a, b| upper_confidence_bound(&statistics[*a]).total_cmp(&upper_confidence_bound(&statistics[*b])))
+        .unwrap();
+    return (action.clone(), false);
+}
+
+/// Plays random actions from a state and returns the rewards for each
+/// player where the rollout stopped, or their estimates if the rollout
+/// was cut off.
+fn rollout<S, A, I>(simulator: &mut I, mut state: S, cutoff: Option<&RolloutCutoff<S>>, rng: &mut ChaCha8Rng) -> Vec<f64> where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    let max_steps = cutoff.map_or(MAX_ROLLOUT_STEPS, |cutoff| cutoff.steps);
+    for _ in 0..max_steps {
+        let all_legal_actions = simulator.calculate_legal_actions(&state);
+        if all_legal_actions.iter().all(|x| x.0.is_empty()) {
+            return simulator.calculate_rewards(&state).iter().map(|reward| reward.0).collect();
+        }
+        let actions = all_legal_actions.iter()
+            .map(|legal_actions| {
+                if legal_actions.0.is_empty() {
+                    None
+                } else {
+                    legal_actions.iter().nth(rng.gen_range(0..legal_actions.0.len())).cloned()
+                }
+            })
+            .collect::<Vec<Option<A>>>();
+        state = simulator.state_transition(&state, &actions);
+    }
+    if let Some(cutoff) = cutoff {
+        if !simulator.is_terminal_state(&state) {
+            return cutoff.evaluator.evaluate(&state);
+        }
+    }
+    return simulator.calculate_rewards(&state).iter().map(|reward| reward.0).collect();
+}
+
+impl <S : State, A : Action, O : Observation> ImperfectInfoAgent<S, A, O> for IsmctsAgent<S, A> {
+    fn select_action<I>(
+        &mut self,
+        player_id: usize,
+        observation: &O,
+        _action_history: &[Vec<Option<A>>],
+        legal_actions: &LegalActions<A>,
+        simulator: &mut I,
+    ) -> A where
+        I: ImperfectInfoSimulator<S, A, O>,
+    {
+        return self.search(player_id, legal_actions, simulator, |simulator, rng| {
+            simulator.sample_determinization(observation, player_id, rng)
+        });
+    }
+
+    fn action_info(&self) -> Option<String> {
+        self.info.clone()
+    }
+}
+
+impl <S : State, A : Action> Agent<S, A> for IsmctsAgent<S, A> {
+    fn select_action<I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
+        I: Simulator<S, A>,
+    {
+        let legal_actions = simulator.calculate_legal_actions(state).swap_remove(player_id);
+        return self.search(player_id, &legal_actions, simulator, |_, _| state.clone());
+    }
+
+    fn action_info(&self) -> Option<String> {
+        self.info.clone()
+    }
+
+    fn action_policy(&self) -> Option<Vec<(A, f64)>> {
+        self.policy.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use rand::SeedableRng;
+
+    use crate::core::reward::Reward;
+    use crate::core::testing::counters::{CountersSimulator, CountersState, TestAction};
+
+    use super::*;
+
+    #[test]
+    fn select_action_takes_winning_counters() {
+        let mut simulator = CountersSimulator::new(5);
+        let mut agent = IsmctsAgent::new(500, 1.0, ChaCha8Rng::seed_from_u64(0));
+        let action = Agent::select_action(&mut agent, 0, &CountersState { counters: 4, turn: 0 }, &mut simulator);
+        assert_eq!(action, TestAction(1));
+        let action = Agent::select_action(&mut agent, 1, &CountersState { counters: 5, turn: 1 }, &mut simulator);
+        assert_eq!(action, TestAction(2));
+        assert!(Agent::<CountersState, TestAction>::action_info(&agent).unwrap().starts_with("iterations: 500"));
+        let policy = Agent::<CountersState, TestAction>::action_policy(&agent).unwrap();
+        let (best, _) = policy.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+        assert_eq!(*best, TestAction(2));
+    }
+
+    #[test]
+    fn rollout_cutoff_uses_evaluator() {
+        let state = CountersState { counters: 20, turn: 0 };
+        let mut agent = IsmctsAgent::new(300, 1.0, ChaCha8Rng::seed_from_u64(0))
+            .with_rollout_cutoff(0, CountersEvaluator { sign: 1.0 });
+        assert_eq!(Agent::select_action(&mut agent, 0, &state, &mut CountersSimulator::new(5)), TestAction(2));
+        let mut agent = IsmctsAgent::new(300, 1.0, ChaCha8Rng::seed_from_u64(0))
+            .with_rollout_cutoff(0, CountersEvaluator { sign: -1.0 });
+        assert_eq!(Agent::select_action(&mut agent, 0, &state, &mut CountersSimulator::new(5)), TestAction(1));
+    }
+
+    #[test]
+    fn select_action_only_guesses_consistent_cards() {
+        let mut simulator = HiddenCardSimulator { rng: ChaCha8Rng::seed_from_u64(0) };
+        let mut agent = IsmctsAgent::new(300, 1.0, ChaCha8Rng::seed_from_u64(0));
+        for _ in 0..10 {
+            let state = simulator.generate_initial_state();
+            let observation = simulator.observe(&state, 0);
+            let legal_actions = simulator.calculate_legal_actions(&state).swap_remove(0);
+            let action = ImperfectInfoAgent::select_action(&mut agent, 0, &observation, &[], &legal_actions, &mut simulator);
+            assert_eq!(action.0 % 2, state.card % 2, "guessed {} for card {}", action, state.card);
+        }
+    }
+
+    /// The player to move loses when the counters are a multiple of three,
+    /// or wins when the sign is negative.
+    struct CountersEvaluator {
+        sign: f64,
+    }
+
+    impl Evaluator<CountersState> for CountersEvaluator {
+        fn evaluate(&self, state: &CountersState) -> Vec<f64> {
+            let value = if state.counters.is_multiple_of(3) { -0.5 } else { 0.5 } * self.sign;
+            let mut values = vec![-value, -value];
+            values[state.turn] = value;
+            return values;
+        }
+    }
+
+    /// A card from 1 to 4 is dealt face down and the player is told
+    /// whether it is odd or even before guessing it.
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct HiddenCardState {
+        card: u8,
+        guess: Option<u8>,
+    }
+
+    impl State for HiddenCardState {}
+
+    impl fmt::Display for HiddenCardState {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} {:?}", self.card, self.guess)
+        }
+    }
+
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct HiddenCardObservation {
+        odd: bool,
+        guess: Option<u8>,
+    }
+
+    impl Observation for HiddenCardObservation {}
+
+    impl fmt::Display for HiddenCardObservation {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} {:?}", if self.odd { "odd" } else { "even" }, self.guess)
+        }
+    }
+
+    #[derive(Clone)]
+    struct HiddenCardSimulator {
+        rng: ChaCha8Rng,
+    }
+
+    impl Simulator<HiddenCardState, TestAction> for HiddenCardSimulator {
+        fn generate_initial_state(&mut self) -> HiddenCardState {
+            HiddenCardState { card: self.rng.gen_range(1..=4), guess: None }
+        }
+
+        fn calculate_rewards(&mut self, state: &HiddenCardState) -> Vec<Reward> {
+            vec![Reward(if state.guess == Some(state.card) { 1.0 } else { 0.0 })]
+        }
+
+        fn calculate_legal_actions(&mut self, state: &HiddenCardState) -> Vec<LegalActions<TestAction>> {
+            let mut legal_actions = LegalActions::new();
+            if state.guess.is_none() {
+                (1..=4).for_each(|x| legal_actions.insert(TestAction(x)));
+            }
+            return vec![legal_actions];
+        }
+
+        fn state_transition(&mut self, state: &HiddenCardState, actions: &Vec<Option<TestAction>>) -> HiddenCardState {
+            let guess = actions[0].as_ref().unwrap().0;
+            return HiddenCardState { card: state.card, guess: Some(guess) };
+        }
+
+        fn number_of_players(&mut self) -> usize {
+            1
+        }
+    }
+
+    impl ImperfectInfoSimulator<HiddenCardState, TestAction, HiddenCardObservation> for HiddenCardSimulator {
+        fn observe(&mut self, state: &HiddenCardState, _player_id: usize) -> HiddenCardObservation {
+            HiddenCardObservation { odd: state.card % 2 == 1, guess: state.guess }
+        }
+
+        fn sample_determinization(&mut self, observation: &HiddenCardObservation, _player_id: usize, rng: &mut ChaCha8Rng) -> HiddenCardState {
+            let card = if observation.odd { [1, 3] } else { [2, 4] }[rng.gen_range(0..2)];
+            HiddenCardState { card, guess: observation.guess }
+        }
+    }
+}
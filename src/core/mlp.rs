@@ -0,0 +1,338 @@
+use std::io::{BufRead, Write};
+
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+/// A fully connected layer computing `weights * input + biases`.
+#[derive(Clone, Debug, PartialEq)]
+struct Layer {
+    inputs: usize,
+    outputs: usize,
+    /// Row-major with one row of `inputs` weights per output.
+    weights: Vec<f64>,
+    biases: Vec<f64>,
+}
+
+impl Layer {
+    /// He initialization, suited to ReLU activations.
+    fn new(inputs: usize, outputs: usize, rng: &mut ChaCha8Rng) -> Self {
+        let limit = (6.0 / inputs as f64).sqrt();
+        Layer {
+            inputs,
+            outputs,
+            weights: (0..inputs * outputs).map(|_| rng.gen_range(-limit..limit)).collect(),
+            biases: vec![0.0; outputs],
+        }
+    }
+
+    fn zeros_like(&self) -> Self {
+        Layer { inputs: self.inputs, outputs: self.outputs, weights: vec![0.0; self.weights.len()], biases: vec![0.0; self.outputs] }
+    }
+
+    fn forward(&self, input: &[f64]) -> Vec<f64> {
+        return (0..self.outputs)
+            .map(|o| {
+                let row = &self.weights[o * self.inputs..(o + 1) * self.inputs];
+                self.biases[o] + row.iter().zip(input).map(|(w, x)| w * x).sum::<f64>()
+            })
+            .collect();
+    }
+
+    /// Accumulates the gradients of the layer parameters into `gradients`
+    /// and returns the gradient with respect to the input.
+    fn backward(&self, input: &[f64], output_gradient: &[f64], gradients: &mut Layer) -> Vec<f64> {
+        let mut input_gradient = vec![0.0; self.inputs];
+        for o in 0..self.outputs {
+            let g = output_gradient[o];
+            if g == 0.0 {
+                continue;
+            }
+            gradients.biases[o] += g;
+            let row = o * self.inputs;
+            for i in 0..self.inputs {
+                gradients.weights[row + i] += g * input[i];
+                input_gradient[i] += g * self.weights[row + i];
+            }
+        }
+        return input_gradient;
+    }
+}
+
+/// A training target for the network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// The encoded input.
+    pub input: Vec<f64>,
+    /// The target probability of each policy output, summing to one.
+    pub policy: Vec<f64>,
+    /// The target of each value output, between -1 and 1.
+    pub value: Vec<f64>,
+}
+
+/// A multi-layer perceptron with ReLU hidden layers and two heads:
+/// policy logits and values squashed between -1 and 1.
+///
+/// Small enough to train on a CPU, it replaces the convolutional
+/// networks usually used by AlphaZero.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mlp {
+    hidden: Vec<Layer>,
+    policy: Layer,
+    value: Layer,
+}
+
+impl Mlp {
+    /// Creates a network with randomly initialized weights.
+    ///
+    /// ### Arguments
+    ///
+    /// * `input_size` - The length of each encoded input.
+    /// * `hidden_sizes` - The number of units in each hidden layer.
+    /// * `policy_size` - The number of policy outputs.
+    /// * `value_size` - The number of value outputs.
+    /// * `rng` - The random number generator used to initialize weights.
+    pub fn new(input_size: usize, hidden_sizes: &[usize], policy_size: usize, value_size: usize, rng: &mut ChaCha8Rng) -> Self {
+        let mut hidden = Vec::new();
+        let mut inputs = input_size;
+        for &outputs in hidden_sizes {
+            hidden.push(Layer::new(inputs, outputs, rng));
+            inputs = outputs;
+        }
+        Mlp { hidden, policy: Layer::new(inputs, policy_size, rng), value: Layer::new(inputs, value_size, rng) }
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.hidden.first().unwrap_or(&self.policy).inputs
+    }
+
+    pub fn policy_size(&self) -> usize {
+        self.policy.outputs
+    }
+
+    pub fn value_size(&self) -> usize {
+        self.value.outputs
+    }
+
+    /// The input of each layer, followed by the output of the last hidden layer.
+    fn hidden_activations(&self, input: &[f64]) -> Vec<Vec<f64>> {
+        let mut activations = vec![input.to_vec()];
+        for layer in &self.hidden {
+            let output = layer.forward(activations.last().unwrap()).into_iter().map(|x| x.max(0.0)).collect();
+            activations.push(output);
+        }
+        return activations;
+    }
+
+    /// Evaluates the network.
+    ///
+    /// ### Arguments
+    ///
+    /// * `input` - The encoded input.
+    ///
+    /// ### Return Value
+    ///
+    /// The policy probabilities and the values.
+    pub fn forward(&self, input: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let activations = self.hidden_activations(input);
+        let features = activations.last().unwrap();
+        let policy = softmax(&self.policy.forward(features));
+        let value = self.value.forward(features).into_iter().map(f64::tanh).collect();
+        return (policy, value);
+    }
+
+    /// The mean cross-entropy of the policy plus the mean squared error
+    /// of the values over a set of samples.
+    pub fn loss(&self, samples: &[Sample]) -> f64 {
+        let total = samples.iter()
+            .map(|sample| {
+                let (policy, value) = self.forward(&sample.input);
+                let cross_entropy = sample.policy.iter().zip(&policy).map(|(t, p)| -t * p.max(1e-12).ln()).sum::<f64>();
+                let squared_error = sample.value.iter().zip(&value).map(|(t, v)| (v - t).powi(2)).sum::<f64>() / value.len() as f64;
+                cross_entropy + squared_error
+            })
+            .sum::<f64>();
+        return total / samples.len() as f64;
+    }
+
+    /// Takes a gradient descent step on the mean loss of a batch of samples.
+    ///
+    /// ### Arguments
+    ///
+    /// * `samples` - The batch of training samples.
+    /// * `learning_rate` - The step size.
+    /// * `weight_decay` - The L2 regularization applied to the weights.
+    pub fn train_batch(&mut self, samples: &[Sample], learning_rate: f64, weight_decay: f64) {
+        let mut hidden_gradients = self.hidden.iter().map(Layer::zeros_like).collect::<Vec<Layer>>();
+        let mut policy_gradients = self.policy.zeros_like();
+        let mut value_gradients = self.value.zeros_like();
+
+        for sample in samples {
+            let activations = self.hidden_activations(&sample.input);
+            let features = activations.last().unwrap();
+            let policy = softmax(&self.policy.forward(features));
+            let value = self.value.forward(features).into_iter().map(f64::tanh).collect::<Vec<f64>>();
+
+            let policy_gradient = policy.iter().zip(&sample.policy).map(|(p, t)| p - t).collect::<Vec<f64>>();
+            let value_gradient = value.iter()
+                .zip(&sample.value)
+                .map(|(v, t)| 2.0 * (v - t) * (1.0 - v * v) / value.len() as f64)
+                .collect::<Vec<f64>>();
+            let mut gradient = self.policy.backward(features, &policy_gradient, &mut policy_gradients);
+            let value_input_gradient = self.value.backward(features, &value_gradient, &mut value_gradients);
+            gradient.iter_mut().zip(value_input_gradient).for_each(|(g, v)| *g += v);
+
+            for (l, layer) in self.hidden.iter().enumerate().rev() {
+                // The gradient only flows through units that were active.
+                let output = &activations[l + 1];
+                gradient.iter_mut().zip(output).for_each(|(g, &a)| if a <= 0.0 { *g = 0.0 });
+                gradient = layer.backward(&activations[l], &gradient, &mut hidden_gradients[l]);
+            }
+        }
+
+        let scale = learning_rate / samples.len() as f64;
+        let layers = self.hidden.iter_mut().chain([&mut self.policy, &mut self.value]);
+        let gradients = hidden_gradients.iter().chain([&policy_gradients, &value_gradients]);
+        for (layer, gradient) in layers.zip(gradients) {
+            for (w, g) in layer.weights.iter_mut().zip(&gradient.weights) {
+                *w -= scale * g + learning_rate * weight_decay * *w;
+            }
+            for (b, g) in layer.biases.iter_mut().zip(&gradient.biases) {
+                *b -= scale * g;
+            }
+        }
+    }
+
+    /// Writes the network as text: a header line with the layer sizes
+    /// followed by the weights and biases of each layer on their own lines.
+    pub fn save<W : Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let hidden_sizes = self.hidden.iter().map(|layer| layer.outputs.to_string()).collect::<Vec<String>>();
+        writeln!(writer, "mlp {} [{}] {} {}", self.input_size(), hidden_sizes.join(" "), self.policy_size(), self.value_size())?;
+        for layer in self.hidden.iter().chain([&self.policy, &self.value]) {
+            for parameters in [&layer.weights, &layer.biases] {
+                let line = parameters.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+                writeln!(writer, "{}", line.join(" "))?;
+            }
+        }
+        return Ok(());
+    }
+
+    /// Reads a network written by `save`.
+    ///
+    /// ### Return Value
+    ///
+    /// The network, or an error if it cannot be read or parsed.
+    pub fn load<R : BufRead>(reader: &mut R) -> Result<Self, String> {
+        let mut lines = reader.lines();
+        let mut next_line = || lines.next().unwrap_or(Err(std::io::ErrorKind::UnexpectedEof.into())).map_err(|e| e.to_string());
+
+        let header = next_line()?;
+        let sizes = header.strip_prefix("mlp ")
+            .map(|sizes| sizes.replace(['[', ']'], " "))
+            .ok_or(format!("invalid network header: {}", header))?
+            .split_whitespace()
+            .map(|size| size.parse::<usize>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<usize>, String>>()?;
+        if sizes.len() < 3 {
+            return Err(format!("invalid network header: {}", header));
+        }
+        let (input_size, hidden_sizes, policy_size, value_size) = (sizes[0], &sizes[1..sizes.len() - 2], sizes[sizes.len() - 2], sizes[sizes.len() - 1]);
+
+        let mut layer_sizes = Vec::new();
+        let mut inputs = input_size;
+        for &outputs in hidden_sizes {
+            layer_sizes.push((inputs, outputs));
+            inputs = outputs;
+        }
+        layer_sizes.push((inputs, policy_size));
+        layer_sizes.push((inputs, value_size));
+
+        let mut layers = Vec::new();
+        for (inputs, outputs) in layer_sizes {
+            let mut read_parameters = |length: usize| -> Result<Vec<f64>, String> {
+                let parameters = next_line()?
+                    .split_whitespace()
+                    .map(|x| x.parse::<f64>().map_err(|e| e.to_string()))
+                    .collect::<Result<Vec<f64>, String>>()?;
+                if parameters.len() != length {
+                    return Err(format!("expected {} parameters, found {}", length, parameters.len()));
+                }
+                return Ok(parameters);
+            };
+            let weights = read_parameters(inputs * outputs)?;
+            let biases = read_parameters(outputs)?;
+            layers.push(Layer { inputs, outputs, weights, biases });
+        }
+        let value = layers.pop().unwrap();
+        let policy = layers.pop().unwrap();
+        return Ok(Mlp { hidden: layers, policy, value });
+    }
+}
+
+fn softmax(logits: &[f64]) -> Vec<f64> {
+    let max = logits.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let exponentials = logits.iter().map(|x| (x - max).exp()).collect::<Vec<f64>>();
+    let total = exponentials.iter().sum::<f64>();
+    return exponentials.iter().map(|x| x / total).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn network() -> Mlp {
+        Mlp::new(3, &[8, 8], 2, 1, &mut ChaCha8Rng::seed_from_u64(0))
+    }
+
+    /// The policy prefers the first output when the first input is set,
+    /// and the value is the second input.
+    fn samples() -> Vec<Sample> {
+        return (0..4)
+            .map(|i| {
+                let (a, b) = ((i & 1) as f64, (i >> 1) as f64);
+                Sample { input: vec![a, b, 1.0], policy: vec![a, 1.0 - a], value: vec![2.0 * b - 1.0] }
+            })
+            .collect();
+    }
+
+    #[test]
+    fn forward_outputs_probabilities_and_bounded_values() {
+        let (policy, value) = network().forward(&[1.0, 0.0, 1.0]);
+        assert_eq!(policy.len(), 2);
+        assert!((policy.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert_eq!(value.len(), 1);
+        assert!(value[0].abs() < 1.0);
+    }
+
+    #[test]
+    fn train_batch_reduces_loss() {
+        let mut network = network();
+        let samples = samples();
+        let initial_loss = network.loss(&samples);
+        for _ in 0..500 {
+            network.train_batch(&samples, 0.1, 0.0);
+        }
+        assert!(network.loss(&samples) < initial_loss / 4.0);
+        let (policy, value) = network.forward(&samples[3].input);
+        assert!(policy[0] > 0.9);
+        assert!(value[0] > 0.8);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let network = network();
+        let mut saved = Vec::new();
+        network.save(&mut saved).unwrap();
+        assert_eq!(Mlp::load(&mut saved.as_slice()).unwrap(), network);
+    }
+
+    #[test]
+    fn load_rejects_missing_parameters() {
+        let mut saved = Vec::new();
+        network().save(&mut saved).unwrap();
+        saved.truncate(saved.len() / 2);
+        assert!(Mlp::load(&mut saved.as_slice()).is_err());
+        assert!(Mlp::load(&mut "mlp x".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+use super::reward::Reward;
+use super::simulator::{Action, State};
+
+/// Receives events from a game in progress, such as loggers,
+/// visualizers and metric collectors.
+/// Every callback does nothing by default.
+pub trait GameObserver<S : State, A : Action> {
+    /// Called once with the initial state before any action is selected.
+    fn on_game_start(&mut self, _state: &S) {}
+
+    /// Called after a player's agent selects an action.
+    ///
+    /// ### Arguments
+    ///
+    /// * `player_id` - The player the action was selected for.
+    /// * `state` - The state the action was selected from.
+    /// * `action` - The selected action.
+    /// * `info` - Diagnostic information provided by the agent, if any.
+    /// * `policy` - The probability the agent gave each legal action, if it provides one.
+    fn on_action_selected(&mut self, _player_id: usize, _state: &S, _action: &A, _info: Option<&str>, _policy: Option<&[(A, f64)]>) {}
+
+    /// Called after the selected actions of all players are applied.
+    fn on_state_transition(&mut self, _state: &S, _actions: &[Option<A>], _next_state: &S) {}
+
+    /// Called once when a terminal state is reached.
+    fn on_game_end(&mut self, _state: &S, _rewards: &[Reward]) {}
+}
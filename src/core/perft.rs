@@ -0,0 +1,134 @@
+use super::simulator::{Action, LegalActions, Simulator, State};
+
+/// Counts the leaf nodes of the game tree below a state by
+/// applying every combination of legal actions.
+/// Terminal states reached before `depth` are not counted.
+///
+/// Only meaningful for deterministic domains where a state and a set
+/// of actions always produce the same next state.
+///
+/// ### Arguments
+///
+/// * `simulator` - The simulator used to expand the game tree.
+/// * `state` - The root state of the tree.
+/// * `depth` - The number of transitions to expand.
+///
+/// ### Return Value
+///
+/// The number of states reachable in exactly `depth` transitions,
+/// counted once for each path to them.
+pub fn perft<S, A, I>(simulator: &mut I, state: &S, depth: usize) -> u64 where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    if depth == 0 {
+        return 1;
+    }
+    let legal_actions = simulator.calculate_legal_actions(state);
+    let mut nodes = 0;
+    for actions in joint_actions(&legal_actions) {
+        let next_state = simulator.state_transition(state, &actions);
+        nodes += perft(simulator, &next_state, depth - 1);
+    }
+    return nodes;
+}
+
+/// Runs `perft` below each joint action available from a state.
+///
+/// Useful to locate move generation bugs by comparing the
+/// counts of each subtree against known-good values.
+///
+/// ### Return Value
+///
+/// The leaf node count for each joint action from the given state,
+/// sorted by the actions' string representations.
+pub fn perft_divide<S, A, I>(simulator: &mut I, state: &S, depth: usize) -> Vec<(Vec<Option<A>>, u64)> where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    if depth == 0 {
+        return vec![];
+    }
+    let legal_actions = simulator.calculate_legal_actions(state);
+    let mut divide = joint_actions(&legal_actions).into_iter()
+        .map(|actions| {
+            let next_state = simulator.state_transition(state, &actions);
+            let nodes = perft(simulator, &next_state, depth - 1);
+            (actions, nodes)
+        })
+        .collect::<Vec<(Vec<Option<A>>, u64)>>();
+    divide.sort_by_key(|(actions, _)| joint_action_to_string(actions));
+    return divide;
+}
+
+/// Enumerates every combination of one legal action per player,
+/// using `None` for players without legal actions.
+/// Returns no combinations if no player has a legal action.
+pub fn joint_actions<A : Action>(legal_actions: &[LegalActions<A>]) -> Vec<Vec<Option<A>>> {
+    if legal_actions.iter().all(|x| x.0.is_empty()) {
+        return vec![];
+    }
+    let mut joint_actions: Vec<Vec<Option<A>>> = vec![vec![]];
+    for player_legal_actions in legal_actions {
+        let choices = if player_legal_actions.0.is_empty() {
+            vec![None]
+        } else {
+            player_legal_actions.iter().cloned().map(Some).collect()
+        };
+        joint_actions = joint_actions.into_iter()
+            .flat_map(|actions| {
+                choices.iter().map(move |choice| {
+                    let mut actions = actions.clone();
+                    actions.push(choice.clone());
+                    actions
+                })
+            })
+            .collect();
+    }
+    return joint_actions;
+}
+
+/// Formats a joint action as a space separated list,
+/// using `-` for players without an action.
+pub fn joint_action_to_string<A : Action>(actions: &[Option<A>]) -> String {
+    return actions.iter()
+        .map(|action| match action {
+            Some(action) => action.to_string(),
+            None => "-".to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::testing::counters::TestAction;
+
+    use super::*;
+
+    #[test]
+    fn joint_actions_no_legal_actions() {
+        let legal_actions: Vec<LegalActions<TestAction>> = vec![LegalActions::new(), LegalActions::new()];
+        assert!(joint_actions(&legal_actions).is_empty());
+    }
+
+    #[test]
+    fn joint_actions_cartesian_product() {
+        let mut legal_actions = vec![LegalActions::new(), LegalActions::new(), LegalActions::new()];
+        legal_actions[0].insert(TestAction(1));
+        legal_actions[0].insert(TestAction(2));
+        legal_actions[2].insert(TestAction(3));
+        legal_actions[2].insert(TestAction(4));
+        legal_actions[2].insert(TestAction(5));
+        let joint_actions = joint_actions(&legal_actions);
+        assert_eq!(joint_actions.len(), 6);
+        assert!(joint_actions.iter().all(|actions| actions.len() == 3 && actions[1].is_none()));
+    }
+
+    #[test]
+    fn joint_action_to_string_with_none() {
+        assert_eq!(joint_action_to_string(&[Some(TestAction(1)), None]), "1 -");
+    }
+}
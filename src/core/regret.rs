@@ -0,0 +1,35 @@
+/// Scales non-negative weights to sum to one, or returns the
+/// uniform distribution if every weight is zero.
+pub fn normalize(weights: &[f64]) -> Vec<f64> {
+    let total = weights.iter().sum::<f64>();
+    if total > 0.0 {
+        return weights.iter().map(|weight| weight / total).collect();
+    }
+    return vec![1.0 / weights.len() as f64; weights.len()];
+}
+
+/// The strategy proportional to positive regrets, or uniform if there are none.
+pub fn regret_matching_strategy(regrets: &[f64]) -> Vec<f64> {
+    return normalize(&regrets.iter().map(|regret| regret.max(0.0)).collect::<Vec<f64>>());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_scales_weights() {
+        assert_eq!(normalize(&[1.0, 3.0]), vec![0.25, 0.75]);
+    }
+
+    #[test]
+    fn normalize_zero_weights_is_uniform() {
+        assert_eq!(normalize(&[0.0, 0.0, 0.0, 0.0]), vec![0.25; 4]);
+    }
+
+    #[test]
+    fn regret_matching_ignores_negative_regrets() {
+        assert_eq!(regret_matching_strategy(&[-1.0, 2.0, 2.0]), vec![0.0, 0.5, 0.5]);
+        assert_eq!(regret_matching_strategy(&[-1.0, -2.0]), vec![0.5, 0.5]);
+    }
+}
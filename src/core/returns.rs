@@ -0,0 +1,247 @@
+use super::history::History;
+use super::simulator::{Action, State};
+
+/// The rewards of every player for each state transition in a history,
+/// with the outcome of a finished game added to the last transition.
+fn step_rewards<S, A>(history: &History<S, A>) -> Vec<Vec<f64>> where
+    S: State,
+    A: Action,
+{
+    let mut rewards = history.rewards().iter()
+        .map(|rewards| rewards.iter().map(|reward| reward.0).collect::<Vec<f64>>())
+        .collect::<Vec<Vec<f64>>>();
+    if let (Some(last), Some(outcome)) = (rewards.last_mut(), history.outcome()) {
+        for (reward, outcome) in last.iter_mut().zip(outcome) {
+            *reward += outcome.0;
+        }
+    }
+    return rewards;
+}
+
+/// Calculates the discounted return of every player from each
+/// state in a history, G(t) = r(t + 1) + gamma * G(t + 1).
+/// The outcome of a finished game counts as part of the last reward.
+///
+/// ### Arguments
+///
+/// * `history` - The states and step rewards of a game.
+/// * `gamma` - The discount factor applied to each later reward.
+///
+/// ### Return Value
+///
+/// The return of each player for every state except the last,
+/// indexed by state and then by player.
+pub fn discounted_returns<S, A>(history: &History<S, A>, gamma: f64) -> Vec<Vec<f64>> where
+    S: State,
+    A: Action,
+{
+    let rewards = step_rewards(history);
+    let number_of_players = rewards.first().map_or(0, |rewards| rewards.len());
+    let mut returns = vec![vec![0.0; number_of_players]; history.len()];
+    let mut next_return = vec![0.0; number_of_players];
+    for (t, rewards) in rewards.iter().enumerate().rev() {
+        for player_id in 0..number_of_players {
+            next_return[player_id] = rewards[player_id] + gamma * next_return[player_id];
+        }
+        returns[t].clone_from(&next_return);
+    }
+    return returns;
+}
+
+/// Calculates the n-step return of every player from each state in
+/// a history, summing up to `n` discounted rewards and bootstrapping
+/// from the value of the state reached. Returns near the end of the
+/// history bootstrap from the value of the last state, which should
+/// be zero if the last state is terminal.
+///
+/// ### Arguments
+///
+/// * `history` - The states and step rewards of a game.
+/// * `gamma` - The discount factor applied to each later reward.
+/// * `n` - The maximum number of rewards summed before bootstrapping.
+/// * `value` - Estimates the value of a state for each player.
+///
+/// ### Return Value
+///
+/// The n-step return of each player for every state except the last,
+/// indexed by state and then by player.
+pub fn n_step_returns<S, A, F>(history: &History<S, A>, gamma: f64, n: usize, mut value: F) -> Vec<Vec<f64>> where
+    S: State,
+    A: Action,
+    F: FnMut(&S) -> Vec<f64>,
+{
+    let values = history.states().into_iter().map(&mut value).collect::<Vec<Vec<f64>>>();
+    let rewards = step_rewards(history);
+    let number_of_players = values[0].len();
+    return (0..history.len())
+        .map(|t| {
+            let end = (t + n).min(history.len());
+            (0..number_of_players)
+                .map(|player_id| {
+                    let mut discount = 1.0;
+                    let mut n_step_return = 0.0;
+                    for step_rewards in &rewards[t..end] {
+                        n_step_return += discount * step_rewards[player_id];
+                        discount *= gamma;
+                    }
+                    n_step_return + discount * values[end][player_id]
+                })
+                .collect()
+        })
+        .collect();
+}
+
+/// Calculates generalized advantage estimates of every player for each
+/// state in a history, the exponentially weighted sum of temporal
+/// difference errors d(t) = r(t + 1) + gamma * V(t + 1) - V(t).
+/// The value of the last state should be zero if it is terminal.
+///
+/// ### Arguments
+///
+/// * `history` - The states and step rewards of a game.
+/// * `gamma` - The discount factor applied to each later reward.
+/// * `lambda` - Trades off bias for variance, from one step temporal
+///   difference errors at 0 to Monte Carlo returns at 1.
+/// * `value` - Estimates the value of a state for each player.
+///
+/// ### Return Value
+///
+/// The advantage of each player for every state except the last,
+/// indexed by state and then by player.
+pub fn gae_advantages<S, A, F>(history: &History<S, A>, gamma: f64, lambda: f64, mut value: F) -> Vec<Vec<f64>> where
+    S: State,
+    A: Action,
+    F: FnMut(&S) -> Vec<f64>,
+{
+    let values = history.states().into_iter().map(&mut value).collect::<Vec<Vec<f64>>>();
+    let number_of_players = values[0].len();
+    let mut advantages = vec![vec![0.0; number_of_players]; history.len()];
+    let mut next_advantage = vec![0.0; number_of_players];
+    for (t, rewards) in step_rewards(history).iter().enumerate().rev() {
+        for player_id in 0..number_of_players {
+            let delta = rewards[player_id] + gamma * values[t + 1][player_id] - values[t][player_id];
+            next_advantage[player_id] = delta + gamma * lambda * next_advantage[player_id];
+        }
+        advantages[t].clone_from(&next_advantage);
+    }
+    return advantages;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fmt;
+
+    use crate::core::reward::Reward;
+
+    use super::*;
+
+    const GAMMA: f64 = 0.5;
+
+    fn assert_close(actual: &[Vec<f64>], expected: &[Vec<f64>]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().flatten().zip(expected.iter().flatten()) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    /// States 0 to 3 where player 0 receives rewards 1, 2, 4 and player 1 receives 0, 0, 8.
+    fn history() -> History<TestState, TestAction> {
+        let mut history = History::new(TestState(0));
+        for (i, rewards) in [[1.0, 0.0], [2.0, 0.0], [4.0, 8.0]].iter().enumerate() {
+            let actions = HashMap::from([(0, TestAction)]);
+            history.push(TestState(i as u8 + 1), actions, rewards.iter().map(|&r| Reward(r)).collect());
+        }
+        return history;
+    }
+
+    /// Values each state by its index, and the terminal state at zero.
+    fn value(state: &TestState) -> Vec<f64> {
+        let value = if state.0 == 3 { 0.0 } else { state.0 as f64 };
+        return vec![value, -value];
+    }
+
+    #[test]
+    fn history_stores_rewards() {
+        let mut history = history();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.states().len(), 4);
+        history.pop();
+        assert_eq!(history.rewards(), &[vec![Reward(1.0), Reward(0.0)], vec![Reward(2.0), Reward(0.0)]]);
+    }
+
+    #[test]
+    fn discounted_returns_of_each_player() {
+        let returns = discounted_returns(&history(), GAMMA);
+        assert_close(&returns, &[vec![3.0, 2.0], vec![4.0, 4.0], vec![4.0, 8.0]]);
+    }
+
+    #[test]
+    fn returns_add_outcome_to_last_reward() {
+        let mut history = history();
+        history.set_outcome(vec![Reward(-4.0), Reward(4.0)]);
+        let returns = discounted_returns(&history, GAMMA);
+        assert_close(&returns, &[vec![2.0, 3.0], vec![2.0, 6.0], vec![0.0, 12.0]]);
+        assert_eq!(history.rewards()[2], vec![Reward(4.0), Reward(8.0)]);
+        history.pop();
+        assert_eq!(history.outcome(), None);
+    }
+
+    #[test]
+    fn discounted_returns_of_empty_history() {
+        let history = History::<TestState, TestAction>::new(TestState(0));
+        assert!(discounted_returns(&history, GAMMA).is_empty());
+    }
+
+    #[test]
+    fn one_step_returns_bootstrap_from_next_state() {
+        let returns = n_step_returns(&history(), GAMMA, 1, value);
+        assert_close(&returns, &[vec![1.5, -0.5], vec![3.0, -1.0], vec![4.0, 8.0]]);
+    }
+
+    #[test]
+    fn n_step_returns_beyond_end_match_discounted_returns() {
+        let history = history();
+        assert_close(&n_step_returns(&history, GAMMA, 10, value), &discounted_returns(&history, GAMMA));
+    }
+
+    #[test]
+    fn gae_advantages_with_lambda_zero_are_temporal_differences() {
+        let advantages = gae_advantages(&history(), GAMMA, 0.0, value);
+        assert_close(&advantages, &[vec![1.5, -0.5], vec![2.0, 0.0], vec![2.0, 10.0]]);
+    }
+
+    #[test]
+    fn gae_advantages_with_lambda_one_are_returns_minus_values() {
+        let history = history();
+        let advantages = gae_advantages(&history, GAMMA, 1.0, value);
+        let returns = discounted_returns(&history, GAMMA);
+        let expected = returns.iter()
+            .zip(history.states())
+            .map(|(g, state)| g.iter().zip(value(state)).map(|(g, v)| g - v).collect())
+            .collect::<Vec<Vec<f64>>>();
+        assert_close(&advantages, &expected);
+    }
+
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct TestState(u8);
+
+    impl State for TestState {}
+
+    impl fmt::Display for TestState {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct TestAction;
+
+    impl Action for TestAction {}
+
+    impl fmt::Display for TestAction {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "TestAction")
+        }
+    }
+}
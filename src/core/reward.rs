@@ -1,22 +1,139 @@
-pub const WIN: Reward = Reward(1);
-pub const LOSS: Reward = Reward(-1);
-pub const DRAW: Reward = Reward(0);
+pub const WIN: Reward = Reward(1.0);
+pub const LOSS: Reward = Reward(-1.0);
+pub const DRAW: Reward = Reward(0.0);
 
 pub const ADVERSARIAL_DRAW: [Reward; 2] = [DRAW, DRAW];
 pub const ADVERSARIAL_P1_WIN: [Reward; 2] = [WIN, LOSS];
 pub const ADVERSARIAL_P1_LOSS: [Reward; 2] = [LOSS, WIN];
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct Reward(pub isize);
+/// A reward for a single player. Used both for the outcome of a
+/// terminal state and for rewards received along the way, so it can
+/// hold scores, probabilities and discounted or normalized utilities.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Reward(pub f64);
+
+impl Reward {
+    /// Scales a score into a reward between 0 and 1.
+    ///
+    /// ### Arguments
+    ///
+    /// * `score` - The raw score.
+    /// * `min` - The lowest possible score.
+    /// * `max` - The highest possible score.
+    ///
+    /// ### Return Value
+    ///
+    /// The position of the score between the lowest and highest scores,
+    /// or 0 when the lowest and highest scores are equal.
+    pub fn normalized(score: f64, min: f64, max: f64) -> Reward {
+        if max == min {
+            return Reward(0.0);
+        }
+        return Reward((score - min) / (max - min));
+    }
+}
+
+/// Ranks each player by score where rank 1 is the highest score.
+/// Tied players share the average of the ranks they span.
+///
+/// ### Arguments
+///
+/// * `scores` - The raw score of each player.
+///
+/// ### Return Value
+///
+/// The rank of each player.
+pub fn ranks(scores: &[f64]) -> Vec<f64> {
+    return scores.iter()
+        .map(|score| {
+            let higher = scores.iter().filter(|other| *other > score).count();
+            let tied = scores.iter().filter(|other| *other == score).count();
+            higher as f64 + (tied + 1) as f64 / 2.0
+        })
+        .collect();
+}
+
+/// Converts scores into rewards by rank, from `WIN` for the
+/// highest score down to `LOSS` for the lowest. Tied players
+/// share their average rank, so two players receive the
+/// adversarial rewards.
+///
+/// ### Arguments
+///
+/// * `scores` - The raw score of each player.
+///
+/// ### Return Value
+///
+/// The reward of each player.
+pub fn rank_rewards(scores: &[f64]) -> Vec<Reward> {
+    if scores.len() < 2 {
+        return vec![DRAW; scores.len()];
+    }
+    let worst_rank = scores.len() as f64;
+    return ranks(scores).iter()
+        .map(|rank| Reward(WIN.0 + (LOSS.0 - WIN.0) * (rank - 1.0) / (worst_rank - 1.0)))
+        .collect();
+}
+
+/// Converts scores into each player's probability of winning,
+/// with ties for the highest score splitting the win equally.
+///
+/// ### Arguments
+///
+/// * `scores` - The raw score of each player.
+///
+/// ### Return Value
+///
+/// The win probability of each player, summing to one.
+pub fn win_probabilities(scores: &[f64]) -> Vec<Reward> {
+    let best = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let winners = scores.iter().filter(|&&score| score == best).count();
+    return scores.iter()
+        .map(|&score| Reward(if score == best { 1.0 / winners as f64 } else { 0.0 }))
+        .collect();
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn reward_adversarial_reward_is_ordered_win_draw_loss() {
         assert!(WIN.0 > DRAW.0);
         assert!(DRAW.0 > LOSS.0);
         assert!(WIN.0 > LOSS.0);
     }
+
+    #[test]
+    fn normalized_reward_is_between_zero_and_one() {
+        assert_eq!(Reward::normalized(0.0, 0.0, 100.0), Reward(0.0));
+        assert_eq!(Reward::normalized(25.0, 0.0, 100.0), Reward(0.25));
+        assert_eq!(Reward::normalized(100.0, 0.0, 100.0), Reward(1.0));
+    }
+
+    #[test]
+    fn normalized_reward_of_empty_range_is_zero() {
+        assert_eq!(Reward::normalized(5.0, 5.0, 5.0), Reward(0.0));
+    }
+
+    #[test]
+    fn ranks_share_ties() {
+        assert_eq!(ranks(&[10.0, 30.0, 20.0]), vec![3.0, 1.0, 2.0]);
+        assert_eq!(ranks(&[10.0, 30.0, 30.0, 5.0]), vec![3.0, 1.5, 1.5, 4.0]);
+    }
+
+    #[test]
+    fn rank_rewards_of_two_players_are_adversarial() {
+        assert_eq!(rank_rewards(&[3.0, 1.0]), ADVERSARIAL_P1_WIN.to_vec());
+        assert_eq!(rank_rewards(&[1.0, 3.0]), ADVERSARIAL_P1_LOSS.to_vec());
+        assert_eq!(rank_rewards(&[2.0, 2.0]), ADVERSARIAL_DRAW.to_vec());
+        assert_eq!(rank_rewards(&[5.0, 9.0, 7.0]), vec![LOSS, WIN, DRAW]);
+    }
+
+    #[test]
+    fn win_probabilities_split_ties() {
+        assert_eq!(win_probabilities(&[1.0, 3.0, 2.0]), vec![Reward(0.0), Reward(1.0), Reward(0.0)]);
+        assert_eq!(win_probabilities(&[3.0, 3.0, 2.0]), vec![Reward(0.5), Reward(0.5), Reward(0.0)]);
+    }
 }
@@ -3,6 +3,8 @@ use std::hash::Hash;
 use std::vec::Vec;
 use std::collections::hash_set::HashSet;
 
+use rand_chacha::ChaCha8Rng;
+
 use super::reward::Reward;
 
 pub trait Action : Clone + fmt::Debug + fmt::Display + Hash + Eq {}
@@ -11,6 +13,12 @@ pub trait State : Clone + fmt::Debug + fmt::Display + Hash + Eq {}
 #[derive(Clone)]
 pub struct LegalActions<A : Action>(pub HashSet<A>);
 
+impl <A : Action> Default for LegalActions<A> {
+    fn default() -> Self {
+        LegalActions::new()
+    }
+}
+
 impl <A : Action> LegalActions<A> {
     pub fn new() -> Self {
         LegalActions(HashSet::<A>::new())
@@ -20,7 +28,7 @@ impl <A : Action> LegalActions<A> {
         self.0.insert(action);
     }
 
-    pub fn iter(&self) -> std::collections::hash_set::Iter<A> {
+    pub fn iter(&self) -> std::collections::hash_set::Iter<'_, A> {
         self.0.iter()
     }
 }
@@ -28,9 +36,7 @@ impl <A : Action> LegalActions<A> {
 impl <A : Action> fmt::Display for LegalActions<A> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "[")?;
-        self.0.iter().fold(Ok(()), |result, action| {
-            result.and_then(|_| writeln!(f, "{}", action))
-        })?;
+        self.0.iter().try_for_each(|action| writeln!(f, "{}", action))?;
         write!(f, "]")
     }
 }
@@ -39,7 +45,7 @@ impl <A : Action> fmt::Display for LegalActions<A> {
 /// and is associated with a domain specific state and action type.
 /// The number of players should be fixed even if a player has
 /// been eleminated from the game.
-pub trait Simulator<S : State, A : Action> {
+pub trait Simulator<S : State, A : Action> : Clone {
     /// Generates an initial state for the domain.
     /// 
     /// ### Return Value
@@ -48,6 +54,8 @@ pub trait Simulator<S : State, A : Action> {
     fn generate_initial_state(&mut self) -> S;
 
     /// This is the rewards function for the given domain.
+    /// In a terminal state the rewards are the outcome of the game,
+    /// see `calculate_outcome`.
     /// 
     /// ### Arguments
     /// 
@@ -58,6 +66,23 @@ pub trait Simulator<S : State, A : Action> {
     /// Returns a reward value for each player.
     fn calculate_rewards(&mut self, state: &S) -> Vec<Reward>;
 
+    /// The rewards received for a single state transition, such as points
+    /// scored during the game, to help learning agents assign credit.
+    /// Zero for every player by default.
+    ///
+    /// ### Arguments
+    ///
+    /// * `prev` - The state transitioned from.
+    /// * `actions` - The actions performed by each player.
+    /// * `next` - The state transitioned to.
+    ///
+    /// ### Return Value
+    ///
+    /// Returns a reward value for each player.
+    fn step_rewards(&mut self, _prev: &S, _actions: &[Option<A>], _next: &S) -> Vec<Reward> {
+        return vec![Reward::default(); self.number_of_players()];
+    }
+
     /// Calculates the legal actions for each player in the domain.
     /// 
     /// ### Arguments
@@ -108,14 +133,35 @@ pub trait Simulator<S : State, A : Action> {
         for player_id in 0..self.number_of_players() {
             let legal_actions = &all_legal_actions[player_id];
             let action = &actions[player_id];
-            if (legal_actions.0.is_empty() && !action.is_none()) ||
-                    (!legal_actions.0.is_empty() && action.is_none()) {
+            let is_legal = match action {
+                Some(action) => legal_actions.0.contains(action),
+                None => legal_actions.0.is_empty(),
+            };
+            if !is_legal {
                 return Err(format!("player {} has illegal action", player_id));
             }
         }
         return Ok(());
     }
 
+    /// Transition from the current state to the next state
+    /// if the given actions are a valid state transition.
+    /// Unlike `state_transition`, illegal actions do not panic.
+    ///
+    /// ### Arguments
+    ///
+    /// * `state` - The state from which to transition.
+    /// * `actions` - Map of actions to be performed by each player.
+    ///
+    /// ### Return Value
+    ///
+    /// Returns the next state, or an error describing why
+    /// the state transition is not valid.
+    fn try_state_transition(&mut self, state: &S, actions: &Vec<Option<A>>) -> Result<S, String> {
+        self.check_valid_state_transition(state, actions)?;
+        return Ok(self.state_transition(state, actions));
+    }
+
     /// A state is terminal if no player has any
     /// legal actions from the current state.
     /// 
@@ -128,11 +174,39 @@ pub trait Simulator<S : State, A : Action> {
     /// True if no player has any legal actions from the given state.
     fn is_terminal_state(&mut self, state: &S) -> bool {
         let legal_actions = self.calculate_legal_actions(state);
-        let result = legal_actions.iter().find(|a| !a.0.is_empty());
-        return match result {
-            Some(_) => false,
-            None => true,
+        return legal_actions.iter().all(|a| a.0.is_empty());
+    }
+
+    /// The final rewards of a finished game, as opposed to
+    /// the rewards received for reaching a non-terminal state.
+    ///
+    /// ### Arguments
+    ///
+    /// * `state` - The state to calculate the outcome of.
+    ///
+    /// ### Return Value
+    ///
+    /// The reward of each player if the state is terminal, otherwise `None`.
+    fn calculate_outcome(&mut self, state: &S) -> Option<Vec<Reward>> {
+        if !self.is_terminal_state(state) {
+            return None;
         }
+        return Some(self.calculate_rewards(state));
+    }
+
+    /// A copy of this simulator for an agent to search with,
+    /// so that searching does not change the game being played.
+    /// Simulators with their own randomness reseed the copy.
+    ///
+    /// ### Arguments
+    ///
+    /// * `rng` - Random number generator used to reseed the copy.
+    ///
+    /// ### Return Value
+    ///
+    /// A simulator that shares no state with this one.
+    fn fork(&self, _rng: &mut ChaCha8Rng) -> Self {
+        return self.clone();
     }
 }
 
@@ -190,6 +264,63 @@ mod tests {
         assert!(simulator.check_valid_state_transition(&TestState, &actions).is_ok());
     }
 
+    #[test]
+    fn check_valid_state_transition_legal_action() {
+        let mut simulator = TestSimulator {
+            legal_actions: vec![LegalActions::new(), legal_actions(&[TestAction])],
+        };
+        let actions = vec![None, Some(TestAction)];
+        assert!(simulator.check_valid_state_transition(&TestState, &actions).is_ok());
+    }
+
+    #[test]
+    fn check_valid_state_transition_action_not_in_legal_actions() {
+        let mut simulator = TestSimulator {
+            legal_actions: vec![legal_actions(&[TestAction]), LegalActions::new()],
+        };
+        let actions = vec![None, Some(TestAction)];
+        assert!(simulator.check_valid_state_transition(&TestState, &actions).is_err());
+    }
+
+    #[test]
+    fn try_state_transition_illegal_action() {
+        let mut simulator = TestSimulator {
+            legal_actions: vec![LegalActions::new(), LegalActions::new()],
+        };
+        let actions = vec![Some(TestAction), None];
+        assert!(simulator.try_state_transition(&TestState, &actions).is_err());
+    }
+
+    #[test]
+    fn calculate_outcome_terminal_state() {
+        let mut simulator = TestSimulator {
+            legal_actions: vec![LegalActions::new(), LegalActions::new()],
+        };
+        assert_eq!(simulator.calculate_outcome(&TestState), Some(ADVERSARIAL_DRAW.to_vec()));
+    }
+
+    #[test]
+    fn calculate_outcome_non_terminal_state() {
+        let mut simulator = TestSimulator {
+            legal_actions: vec![legal_actions(&[TestAction]), LegalActions::new()],
+        };
+        assert_eq!(simulator.calculate_outcome(&TestState), None);
+    }
+
+    #[test]
+    fn step_rewards_default_to_zero() {
+        let mut simulator = TestSimulator {
+            legal_actions: vec![LegalActions::new(), LegalActions::new()],
+        };
+        assert_eq!(simulator.step_rewards(&TestState, &[None, None], &TestState), vec![Reward(0.0), Reward(0.0)]);
+    }
+
+    fn legal_actions(actions: &[TestAction]) -> LegalActions<TestAction> {
+        let mut legal_actions = LegalActions::new();
+        actions.iter().for_each(|action| legal_actions.insert(action.clone()));
+        return legal_actions;
+    }
+
     #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
     struct TestState;
 
@@ -212,6 +343,7 @@ mod tests {
         }
     }
 
+    #[derive(Clone)]
     struct TestSimulator {
         legal_actions: Vec<LegalActions<TestAction>>,
     }
@@ -0,0 +1,518 @@
+use rand::distributions::{Distribution, WeightedIndex};
+use rand_chacha::ChaCha8Rng;
+
+use super::agent::Agent;
+use super::regret::{normalize, regret_matching_strategy};
+use super::simulator::{Action, Simulator, State};
+
+/// Tolerance used when checking equilibrium conditions.
+const EPSILON: f64 = 1e-9;
+
+/// A single simultaneous decision, also known as a normal-form or
+/// matrix game. Every acting player chooses an action without seeing
+/// the others' choices and receives a payoff for the joint action.
+#[derive(Clone, Debug)]
+pub struct StageGame<A : Action> {
+    players: Vec<usize>,
+    actions: Vec<Vec<A>>,
+    payoffs: Vec<Vec<f64>>,
+}
+
+impl <A : Action> StageGame<A> {
+    /// Creates a stage game from explicit payoffs.
+    ///
+    /// ### Arguments
+    ///
+    /// * `players` - The ID of each acting player.
+    /// * `actions` - The actions available to each acting player.
+    /// * `payoffs` - The payoff of each acting player for every joint action,
+    ///   ordered with the last player's action changing fastest.
+    ///
+    /// ### Return Value
+    ///
+    /// The stage game, or an error if the dimensions are inconsistent.
+    pub fn new(players: Vec<usize>, actions: Vec<Vec<A>>, payoffs: Vec<Vec<f64>>) -> Result<Self, String> {
+        if players.len() != actions.len() {
+            return Err(format!("{} players but {} action lists", players.len(), actions.len()));
+        }
+        if actions.iter().any(|player_actions| player_actions.is_empty()) {
+            return Err("Every acting player needs at least one action".to_string());
+        }
+        let joint_actions = actions.iter().map(|player_actions| player_actions.len()).product::<usize>();
+        if payoffs.len() != joint_actions {
+            return Err(format!("{} joint actions but {} payoffs", joint_actions, payoffs.len()));
+        }
+        if payoffs.iter().any(|payoff| payoff.len() != players.len()) {
+            return Err(format!("Every payoff needs a value for each of the {} players", players.len()));
+        }
+        return Ok(StageGame { players, actions, payoffs });
+    }
+
+    /// Creates the stage game played from a state by applying every
+    /// joint action and using the rewards of the resulting states.
+    ///
+    /// The stage is treated as one-shot: later decisions from the
+    /// resulting states are not taken into account.
+    ///
+    /// ### Arguments
+    ///
+    /// * `simulator` - The simulator that determines action outcomes in the domain.
+    /// * `state` - The state where players act simultaneously.
+    ///
+    /// ### Return Value
+    ///
+    /// The stage game between the players with legal actions in the state.
+    pub fn from_state<S, I>(simulator: &mut I, state: &S) -> Self where
+        S: State,
+        I: Simulator<S, A>,
+    {
+        let legal_actions = simulator.calculate_legal_actions(state);
+        let (players, actions): (Vec<usize>, Vec<Vec<A>>) = legal_actions.iter()
+            .enumerate()
+            .filter(|(_, player_legal_actions)| !player_legal_actions.0.is_empty())
+            .map(|(player_id, player_legal_actions)| (player_id, player_legal_actions.iter().cloned().collect()))
+            .unzip();
+        let mut stage_game = StageGame { players, actions, payoffs: vec![] };
+        for joint_action in 0..stage_game.number_of_joint_actions() {
+            let mut selected_actions = vec![None; legal_actions.len()];
+            for (i, action_index) in stage_game.action_indices(joint_action).into_iter().enumerate() {
+                selected_actions[stage_game.players[i]] = Some(stage_game.actions[i][action_index].clone());
+            }
+            let next_state = simulator.state_transition(state, &selected_actions);
+            let rewards = simulator.calculate_rewards(&next_state);
+            let payoff = stage_game.players.iter().map(|&player_id| rewards[player_id].0).collect();
+            stage_game.payoffs.push(payoff);
+        }
+        return stage_game;
+    }
+
+    /// The ID of each acting player.
+    pub fn players(&self) -> &[usize] {
+        &self.players
+    }
+
+    /// The actions available to an acting player.
+    pub fn actions(&self, player_index: usize) -> &[A] {
+        &self.actions[player_index]
+    }
+
+    /// The payoff of each acting player when every player
+    /// chooses the action at the given index.
+    pub fn payoffs(&self, action_indices: &[usize]) -> &[f64] {
+        let joint_action = action_indices.iter()
+            .zip(&self.actions)
+            .fold(0, |joint_action, (index, player_actions)| joint_action * player_actions.len() + index);
+        return &self.payoffs[joint_action];
+    }
+
+    fn number_of_joint_actions(&self) -> usize {
+        return self.actions.iter().map(|player_actions| player_actions.len()).product();
+    }
+
+    fn action_indices(&self, mut joint_action: usize) -> Vec<usize> {
+        let mut indices = vec![0; self.actions.len()];
+        for i in (0..self.actions.len()).rev() {
+            indices[i] = joint_action % self.actions[i].len();
+            joint_action /= self.actions[i].len();
+        }
+        return indices;
+    }
+
+    /// The expected payoff of each of a player's actions
+    /// when the other players follow mixed strategies.
+    fn action_values(&self, strategies: &[Vec<f64>], player_index: usize) -> Vec<f64> {
+        let mut values = vec![0.0; self.actions[player_index].len()];
+        for (joint_action, payoff) in self.payoffs.iter().enumerate() {
+            let indices = self.action_indices(joint_action);
+            let others_probability = indices.iter()
+                .enumerate()
+                .filter(|(i, _)| *i != player_index)
+                .map(|(i, &index)| strategies[i][index])
+                .product::<f64>();
+            values[indices[player_index]] += others_probability * payoff[player_index];
+        }
+        return values;
+    }
+
+    /// The expected payoff of each acting player under mixed strategies.
+    pub fn expected_payoffs(&self, strategies: &[Vec<f64>]) -> Vec<f64> {
+        return (0..self.players.len())
+            .map(|i| self.action_values(strategies, i).iter().zip(&strategies[i]).map(|(v, p)| v * p).sum())
+            .collect();
+    }
+
+    /// The total amount players could gain by deviating from mixed strategies
+    /// to a best response. Zero exactly when the strategies are a Nash equilibrium.
+    pub fn nash_conv(&self, strategies: &[Vec<f64>]) -> f64 {
+        let expected_payoffs = self.expected_payoffs(strategies);
+        return (0..self.players.len())
+            .map(|i| {
+                let best_value = self.action_values(strategies, i).into_iter().fold(f64::NEG_INFINITY, f64::max);
+                best_value - expected_payoffs[i]
+            })
+            .sum();
+    }
+
+    /// Approximates an equilibrium by having every player repeatedly
+    /// play in proportion to their positive regrets.
+    ///
+    /// The average strategies converge to a Nash equilibrium in two-player
+    /// zero-sum games and to a coarse correlated equilibrium otherwise.
+    ///
+    /// ### Arguments
+    ///
+    /// * `iterations` - The number of rounds of regret matching.
+    ///
+    /// ### Return Value
+    ///
+    /// The average mixed strategy of each acting player.
+    pub fn regret_matching(&self, iterations: usize) -> Vec<Vec<f64>> {
+        let mut regrets = self.actions.iter().map(|player_actions| vec![0.0; player_actions.len()]).collect::<Vec<_>>();
+        let mut strategy_sums = regrets.clone();
+        for _ in 0..iterations {
+            let strategies = regrets.iter().map(|player_regrets| regret_matching_strategy(player_regrets)).collect::<Vec<_>>();
+            for i in 0..self.players.len() {
+                let values = self.action_values(&strategies, i);
+                let value = values.iter().zip(&strategies[i]).map(|(v, p)| v * p).sum::<f64>();
+                regrets[i].iter_mut().zip(&values).for_each(|(regret, v)| *regret += v - value);
+                strategy_sums[i].iter_mut().zip(&strategies[i]).for_each(|(sum, p)| *sum += p);
+            }
+        }
+        return strategy_sums.iter().map(|sums| normalize(sums)).collect();
+    }
+
+    /// Finds the Nash equilibria of a two-player stage game by support
+    /// enumeration. Every equilibrium of a nondegenerate game is found;
+    /// degenerate games may have equilibria with unequal support sizes
+    /// that are not returned.
+    ///
+    /// ### Return Value
+    ///
+    /// The mixed strategy of both players for each equilibrium found,
+    /// or an error if the stage game does not have two acting players.
+    pub fn nash_equilibria(&self) -> Result<Vec<Vec<Vec<f64>>>, String> {
+        if self.players.len() != 2 {
+            return Err(format!("Support enumeration requires 2 acting players, found {}", self.players.len()));
+        }
+        let rows = self.actions[0].len();
+        let columns = self.actions[1].len();
+        let mut equilibria: Vec<Vec<Vec<f64>>> = Vec::new();
+        for size in 1..=rows.min(columns) {
+            for row_support in subsets(rows, size) {
+                for column_support in subsets(columns, size) {
+                    let equilibrium = match self.support_equilibrium(&row_support, &column_support) {
+                        Some(equilibrium) => equilibrium,
+                        None => continue,
+                    };
+                    let is_new = equilibria.iter().all(|known| {
+                        known.iter().flatten().zip(equilibrium.iter().flatten()).any(|(a, b)| (a - b).abs() > 1e-6)
+                    });
+                    if is_new {
+                        equilibria.push(equilibrium);
+                    }
+                }
+            }
+        }
+        return Ok(equilibria);
+    }
+
+    /// Finds strategies with the given supports that make each player
+    /// indifferent between their supported actions, if they form an equilibrium.
+    fn support_equilibrium(&self, row_support: &[usize], column_support: &[usize]) -> Option<Vec<Vec<f64>>> {
+        let row_payoffs = row_support.iter()
+            .map(|&row| column_support.iter().map(|&column| self.payoffs(&[row, column])[0]).collect())
+            .collect::<Vec<Vec<f64>>>();
+        let column_payoffs = column_support.iter()
+            .map(|&column| row_support.iter().map(|&row| self.payoffs(&[row, column])[1]).collect())
+            .collect::<Vec<Vec<f64>>>();
+        let column_weights = indifferent_strategy(&row_payoffs)?;
+        let row_weights = indifferent_strategy(&column_payoffs)?;
+
+        let mut strategies = vec![vec![0.0; self.actions[0].len()], vec![0.0; self.actions[1].len()]];
+        row_support.iter().zip(row_weights).for_each(|(&row, p)| strategies[0][row] = p);
+        column_support.iter().zip(column_weights).for_each(|(&column, p)| strategies[1][column] = p);
+        if self.nash_conv(&strategies) > EPSILON {
+            return None;
+        }
+        return Some(strategies);
+    }
+}
+
+/// Solves for the probabilities over the columns of `payoffs` that
+/// give every row the same expected payoff.
+///
+/// ### Return Value
+///
+/// The column probabilities, or `None` if there is no unique
+/// non-negative solution.
+fn indifferent_strategy(payoffs: &[Vec<f64>]) -> Option<Vec<f64>> {
+    let n = payoffs.len();
+    // Unknowns are the n probabilities followed by the common payoff.
+    let mut system = payoffs.iter()
+        .map(|row| {
+            let mut equation = row.clone();
+            equation.extend([-1.0, 0.0]);
+            equation
+        })
+        .collect::<Vec<Vec<f64>>>();
+    let mut total = vec![1.0; n];
+    total.extend([0.0, 1.0]);
+    system.push(total);
+
+    let solution = solve_linear_system(system)?;
+    let probabilities = solution[..n].to_vec();
+    if probabilities.iter().any(|&p| p < -EPSILON) {
+        return None;
+    }
+    return Some(probabilities.iter().map(|p| p.max(0.0)).collect());
+}
+
+/// Solves a square linear system given as rows of coefficients
+/// followed by the constant term, using Gaussian elimination.
+fn solve_linear_system(mut system: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let n = system.len();
+    for column in 0..n {
+        let pivot = (column..n).max_by(|&a, &b| system[a][column].abs().total_cmp(&system[b][column].abs()))?;
+        if system[pivot][column].abs() < EPSILON {
+            return None;
+        }
+        system.swap(column, pivot);
+        for row in 0..n {
+            if row != column {
+                let factor = system[row][column] / system[column][column];
+                for k in column..=n {
+                    system[row][k] -= factor * system[column][k];
+                }
+            }
+        }
+    }
+    return Some((0..n).map(|i| system[i][n] / system[i][i]).collect());
+}
+
+/// Every subset of `0..n` with `size` elements, in increasing order.
+fn subsets(n: usize, size: usize) -> Vec<Vec<usize>> {
+    if size == 0 {
+        return vec![vec![]];
+    }
+    if n < size {
+        return vec![];
+    }
+    let mut result = subsets(n - 1, size);
+    for mut subset in subsets(n - 1, size - 1) {
+        subset.push(n - 1);
+        result.push(subset);
+    }
+    return result;
+}
+
+/// Selects actions in simultaneous-move states by running regret
+/// matching on the stage game and sampling from its average strategy.
+///
+/// Each decision is treated as a one-shot stage game, so the agent is
+/// best suited to domains where each transition is scored immediately.
+#[derive(Debug)]
+pub struct RegretMatchingAgent {
+    iterations: usize,
+    rng: ChaCha8Rng,
+    action_info: Option<String>,
+}
+
+impl RegretMatchingAgent {
+    /// Creates a regret matching agent.
+    ///
+    /// ### Arguments
+    ///
+    /// * `iterations` - The number of rounds of regret matching per decision.
+    /// * `rng` - The random number generator used to sample actions.
+    pub fn new(iterations: usize, rng: ChaCha8Rng) -> Self {
+        RegretMatchingAgent { iterations, rng, action_info: None }
+    }
+}
+
+impl <S : State, A : Action> Agent<S, A> for RegretMatchingAgent {
+    fn select_action<I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
+        I: Simulator<S, A>,
+    {
+        let stage_game = StageGame::from_state(&mut simulator.fork(&mut self.rng), state);
+        let player_index = stage_game.players().iter()
+            .position(|&id| id == player_id)
+            .expect("Player should have legal actions.");
+        let strategies = stage_game.regret_matching(self.iterations);
+        let distribution = WeightedIndex::new(&strategies[player_index]).unwrap();
+        let action = stage_game.actions(player_index)[distribution.sample(&mut self.rng)].clone();
+        self.action_info = Some(format!(
+            "iterations: {}, probability: {:.3}, nash conv: {:.3}",
+            self.iterations,
+            strategies[player_index][stage_game.actions(player_index).iter().position(|a| *a == action).unwrap()],
+            stage_game.nash_conv(&strategies),
+        ));
+        return action;
+    }
+
+    fn action_info(&self) -> Option<String> {
+        self.action_info.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use rand::SeedableRng;
+
+    use crate::core::game::play_game;
+    use crate::core::reward::{Reward, ADVERSARIAL_DRAW, ADVERSARIAL_P1_LOSS, ADVERSARIAL_P1_WIN};
+    use crate::core::simulator::LegalActions;
+
+    use super::*;
+
+    fn assert_strategies_eq(actual: &[Vec<f64>], expected: &[Vec<f64>]) {
+        for (a, e) in actual.iter().flatten().zip(expected.iter().flatten()) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    /// A two action game from row and column player payoff pairs.
+    fn two_by_two(payoffs: [[(f64, f64); 2]; 2]) -> StageGame<RpsAction> {
+        let payoffs = payoffs.iter().flatten().map(|&(row, column)| vec![row, column]).collect();
+        let actions = vec![RpsAction::Rock, RpsAction::Paper];
+        return StageGame::new(vec![0, 1], vec![actions.clone(), actions], payoffs).unwrap();
+    }
+
+    #[test]
+    fn new_rejects_wrong_number_of_payoffs() {
+        let actions = vec![RpsAction::Rock, RpsAction::Paper];
+        assert!(StageGame::new(vec![0, 1], vec![actions.clone(), actions], vec![vec![0.0, 0.0]]).is_err());
+    }
+
+    #[test]
+    fn from_state_builds_rock_paper_scissors() {
+        let mut simulator = RpsSimulator;
+        let stage_game = StageGame::from_state(&mut simulator, &RpsState(None));
+        assert_eq!(stage_game.players(), &[0, 1]);
+        let rock = stage_game.actions(0).iter().position(|a| *a == RpsAction::Rock).unwrap();
+        let scissors = stage_game.actions(1).iter().position(|a| *a == RpsAction::Scissors).unwrap();
+        assert_eq!(stage_game.payoffs(&[rock, scissors]), &[1.0, -1.0]);
+    }
+
+    #[test]
+    fn rock_paper_scissors_equilibrium_is_uniform() {
+        let mut simulator = RpsSimulator;
+        let stage_game = StageGame::from_state(&mut simulator, &RpsState(None));
+        let third = 1.0 / 3.0;
+        let uniform = vec![vec![third; 3], vec![third; 3]];
+        assert_eq!(stage_game.nash_equilibria().unwrap().len(), 1);
+        assert_strategies_eq(&stage_game.nash_equilibria().unwrap()[0], &uniform);
+        assert!(stage_game.nash_conv(&uniform) < EPSILON);
+    }
+
+    #[test]
+    fn prisoners_dilemma_equilibrium_is_mutual_defection() {
+        let stage_game = two_by_two([[(-1.0, -1.0), (-3.0, 0.0)], [(0.0, -3.0), (-2.0, -2.0)]]);
+        let equilibria = stage_game.nash_equilibria().unwrap();
+        assert_eq!(equilibria.len(), 1);
+        assert_strategies_eq(&equilibria[0], &[vec![0.0, 1.0], vec![0.0, 1.0]]);
+    }
+
+    #[test]
+    fn battle_of_the_sexes_has_three_equilibria() {
+        let stage_game = two_by_two([[(2.0, 1.0), (0.0, 0.0)], [(0.0, 0.0), (1.0, 2.0)]]);
+        let equilibria = stage_game.nash_equilibria().unwrap();
+        assert_eq!(equilibria.len(), 3);
+        assert_strategies_eq(&equilibria[2], &[vec![2.0 / 3.0, 1.0 / 3.0], vec![1.0 / 3.0, 2.0 / 3.0]]);
+    }
+
+    #[test]
+    fn regret_matching_approaches_equilibrium() {
+        let stage_game = two_by_two([[(3.0, -3.0), (-1.0, 1.0)], [(-2.0, 2.0), (1.0, -1.0)]]);
+        let strategies = stage_game.regret_matching(10_000);
+        assert!(stage_game.nash_conv(&strategies) < 0.05);
+        assert_strategies_eq(&stage_game.nash_equilibria().unwrap()[0], &[vec![3.0 / 7.0, 4.0 / 7.0], vec![2.0 / 7.0, 5.0 / 7.0]]);
+    }
+
+    #[test]
+    fn regret_matching_agents_play_simultaneously() {
+        let mut simulator = RpsSimulator;
+        let mut agents = [
+            RegretMatchingAgent::new(100, ChaCha8Rng::seed_from_u64(0)),
+            RegretMatchingAgent::new(100, ChaCha8Rng::seed_from_u64(1)),
+        ];
+        let history = play_game(&mut simulator, &mut agents, &mut []);
+        assert_eq!(history.outcome().unwrap().iter().map(|reward| reward.0).sum::<f64>(), 0.0);
+        assert!(Agent::<RpsState, RpsAction>::action_info(&agents[0]).unwrap().starts_with("iterations: 100"));
+    }
+
+    #[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+    enum RpsAction {
+        Rock,
+        Paper,
+        Scissors,
+    }
+
+    impl Action for RpsAction {}
+
+    impl fmt::Display for RpsAction {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    impl RpsAction {
+        fn beats(&self, other: &RpsAction) -> bool {
+            matches!(
+                (self, other),
+                (RpsAction::Rock, RpsAction::Scissors) | (RpsAction::Paper, RpsAction::Rock) | (RpsAction::Scissors, RpsAction::Paper)
+            )
+        }
+    }
+
+    /// Rock paper scissors, holding each player's throw once made.
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct RpsState(Option<[RpsAction; 2]>);
+
+    impl State for RpsState {}
+
+    impl fmt::Display for RpsState {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self.0 {
+                Some([a, b]) => write!(f, "{} {}", a, b),
+                None => write!(f, "-"),
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct RpsSimulator;
+
+    impl Simulator<RpsState, RpsAction> for RpsSimulator {
+        fn generate_initial_state(&mut self) -> RpsState {
+            RpsState(None)
+        }
+
+        fn calculate_rewards(&mut self, state: &RpsState) -> Vec<Reward> {
+            match state.0 {
+                Some([a, b]) if a.beats(&b) => ADVERSARIAL_P1_WIN.to_vec(),
+                Some([a, b]) if b.beats(&a) => ADVERSARIAL_P1_LOSS.to_vec(),
+                _ => ADVERSARIAL_DRAW.to_vec(),
+            }
+        }
+
+        fn calculate_legal_actions(&mut self, state: &RpsState) -> Vec<LegalActions<RpsAction>> {
+            let mut legal_actions = vec![LegalActions::new(), LegalActions::new()];
+            if state.0.is_none() {
+                for player_legal_actions in legal_actions.iter_mut() {
+                    [RpsAction::Rock, RpsAction::Paper, RpsAction::Scissors].into_iter().for_each(|a| player_legal_actions.insert(a));
+                }
+            }
+            return legal_actions;
+        }
+
+        fn state_transition(&mut self, _state: &RpsState, actions: &Vec<Option<RpsAction>>) -> RpsState {
+            RpsState(Some([actions[0].unwrap(), actions[1].unwrap()]))
+        }
+
+        fn number_of_players(&mut self) -> usize {
+            2
+        }
+    }
+}
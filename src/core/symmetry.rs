@@ -0,0 +1,139 @@
+use super::simulator::{Action, State};
+
+/// A state whose symmetric variants, such as reflections and rotations
+/// of a board, play identically once their actions are mapped the same way.
+///
+/// Symmetries are numbered from zero, which is always the identity.
+pub trait Symmetric<A : Action>: State {
+    /// The number of symmetries of the state, including the identity.
+    fn symmetry_count(&self) -> usize;
+
+    /// The symmetric variant of the state.
+    ///
+    /// ### Arguments
+    ///
+    /// * `symmetry` - The symmetry to apply, less than `symmetry_count`.
+    fn transform(&self, symmetry: usize) -> Self;
+
+    /// The action from the symmetric variant of the state
+    /// that corresponds to an action from this state.
+    ///
+    /// ### Arguments
+    ///
+    /// * `action` - An action from this state.
+    /// * `symmetry` - The symmetry to apply, less than `symmetry_count`.
+    fn transform_action(&self, action: &A, symmetry: usize) -> A;
+
+    /// The symmetry that undoes a symmetry. Every symmetry is
+    /// its own inverse unless this is overridden.
+    fn inverse(&self, symmetry: usize) -> usize {
+        symmetry
+    }
+
+    /// Every symmetric variant of the state with the symmetry that produced it,
+    /// starting with the state itself.
+    fn symmetries(&self) -> Vec<(Self, usize)> {
+        return (0..self.symmetry_count()).map(|symmetry| (self.transform(symmetry), symmetry)).collect();
+    }
+}
+
+/// Chooses one variant of a state to represent all of its symmetric
+/// variants, so that symmetric states can share a single entry in a
+/// table keyed by state, such as the Connect 4 opening book.
+///
+/// ### Arguments
+///
+/// * `state` - The state to find the canonical variant of.
+/// * `key` - Orders the variants, the variant with the smallest key is canonical.
+///
+/// ### Return Value
+///
+/// The canonical variant and the symmetry that transforms the state into it.
+pub fn canonical<S, A, K, F>(state: &S, key: F) -> (S, usize) where
+    S: Symmetric<A>,
+    A: Action,
+    K: Ord,
+    F: Fn(&S) -> K,
+{
+    return state.symmetries().into_iter()
+        .min_by_key(|(variant, _)| key(variant))
+        .expect("Symmetries should include the identity.");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use super::*;
+
+    #[test]
+    fn symmetries_start_with_identity() {
+        let state = LineState(vec![1, 2, 3]);
+        let symmetries = Symmetric::<TestAction>::symmetries(&state);
+        assert_eq!(symmetries, vec![(LineState(vec![1, 2, 3]), 0), (LineState(vec![3, 2, 1]), 1)]);
+    }
+
+    #[test]
+    fn canonical_chooses_smallest_key() {
+        let (variant, symmetry) = canonical::<_, TestAction, _, _>(&LineState(vec![3, 1, 2]), |state| state.0.clone());
+        assert_eq!((variant, symmetry), (LineState(vec![2, 1, 3]), 1));
+        let (variant, symmetry) = canonical::<_, TestAction, _, _>(&LineState(vec![1, 2, 3]), |state| state.0.clone());
+        assert_eq!((variant, symmetry), (LineState(vec![1, 2, 3]), 0));
+    }
+
+    #[test]
+    fn transform_action_round_trips_with_inverse() {
+        let state = LineState(vec![5, 6, 7, 8]);
+        for (variant, symmetry) in Symmetric::<TestAction>::symmetries(&state) {
+            for i in 0..4 {
+                let action = state.transform_action(&TestAction(i), symmetry);
+                assert_eq!(variant.0[action.0], state.0[i]);
+                assert_eq!(variant.transform_action(&action, variant.inverse(symmetry)), TestAction(i));
+            }
+        }
+    }
+
+    /// Numbers in a row that are the same reversed.
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct LineState(Vec<u8>);
+
+    impl State for LineState {}
+
+    impl fmt::Display for LineState {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+
+    impl Symmetric<TestAction> for LineState {
+        fn symmetry_count(&self) -> usize {
+            2
+        }
+
+        fn transform(&self, symmetry: usize) -> Self {
+            match symmetry {
+                0 => self.clone(),
+                _ => LineState(self.0.iter().rev().cloned().collect()),
+            }
+        }
+
+        fn transform_action(&self, action: &TestAction, symmetry: usize) -> TestAction {
+            match symmetry {
+                0 => action.clone(),
+                _ => TestAction(self.0.len() - 1 - action.0),
+            }
+        }
+    }
+
+    /// An index into the row.
+    #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+    struct TestAction(usize);
+
+    impl Action for TestAction {}
+
+    impl fmt::Display for TestAction {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+use super::agent::Agent;
+use super::simulator::{Action, LegalActions, Simulator, State};
+
+/// The update rule used to learn action values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TabularMethod {
+    /// Off-policy temporal difference learning towards the best next action.
+    QLearning,
+    /// On-policy temporal difference learning towards the next action taken.
+    Sarsa,
+    /// Learns from the full return of each episode once it ends.
+    MonteCarlo,
+}
+
+/// Learning parameters for `TabularLearner`.
+#[derive(Clone, Copy, Debug)]
+pub struct TabularConfig {
+    /// The step size of each update, between 0 and 1.
+    pub learning_rate: f64,
+    /// The discount applied to rewards for each later decision.
+    pub discount: f64,
+    /// The probability of exploring a random action.
+    pub epsilon: f64,
+    /// Learn from `Simulator::step_rewards` instead of the terminal outcome.
+    pub step_rewards: bool,
+}
+
+impl Default for TabularConfig {
+    fn default() -> Self {
+        TabularConfig { learning_rate: 0.1, discount: 1.0, epsilon: 0.1, step_rewards: false }
+    }
+}
+
+/// A decision made by a player that is waiting for its reward and
+/// the player's next decision before it can be learned from.
+struct Decision<S, A> {
+    state: S,
+    action: A,
+    reward: f64,
+}
+
+/// Learns a table of action values for every state and action visited
+/// while playing against itself, choosing actions epsilon-greedily.
+///
+/// Every player shares the table, so each value is from the point of view
+/// of the player acting in the state. A player's decisions are chained
+/// together with the rewards the player receives in between, which works
+/// for single-player, alternating and simultaneous-move domains.
+#[derive(Debug)]
+pub struct TabularLearner<S : State, A : Action> {
+    method: TabularMethod,
+    config: TabularConfig,
+    values: HashMap<(S, A), f64>,
+    rng: ChaCha8Rng,
+}
+
+impl <S : State, A : Action> TabularLearner<S, A> {
+    /// Creates a learner with an empty table.
+    ///
+    /// ### Arguments
+    ///
+    /// * `method` - The update rule used to learn action values.
+    /// * `config` - The learning parameters.
+    /// * `rng` - The random number generator used for exploration.
+    pub fn new(method: TabularMethod, config: TabularConfig, rng: ChaCha8Rng) -> Self {
+        TabularLearner { method, config, values: HashMap::new(), rng }
+    }
+
+    /// The learned value of taking an action in a state, zero if unvisited.
+    pub fn value(&self, state: &S, action: &A) -> f64 {
+        return self.values.get(&(state.clone(), action.clone())).cloned().unwrap_or(0.0);
+    }
+
+    /// The number of state and action pairs in the table.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// True if nothing has been learned.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Legal actions sorted by their string representations
+    /// so that exploration is reproducible from a seed.
+    fn sorted_actions(legal_actions: &LegalActions<A>) -> Vec<A> {
+        let mut actions = legal_actions.iter().cloned().collect::<Vec<A>>();
+        actions.sort_by_cached_key(|action| action.to_string());
+        return actions;
+    }
+
+    fn max_value(&self, state: &S, actions: &[A]) -> f64 {
+        return actions.iter().map(|action| self.value(state, action)).fold(f64::NEG_INFINITY, f64::max);
+    }
+
+    /// The legal action with the highest value, breaking ties at random.
+    pub fn greedy_action(&mut self, state: &S, legal_actions: &LegalActions<A>) -> A {
+        let actions = Self::sorted_actions(legal_actions);
+        let best = self.max_value(state, &actions);
+        let best_actions = actions.into_iter().filter(|action| self.value(state, action) == best).collect::<Vec<A>>();
+        return best_actions[self.rng.gen_range(0..best_actions.len())].clone();
+    }
+
+    fn epsilon_greedy_action(&mut self, state: &S, legal_actions: &LegalActions<A>) -> A {
+        if self.rng.gen::<f64>() < self.config.epsilon {
+            let actions = Self::sorted_actions(legal_actions);
+            return actions[self.rng.gen_range(0..actions.len())].clone();
+        }
+        return self.greedy_action(state, legal_actions);
+    }
+
+    fn update(&mut self, state: S, action: A, target: f64) {
+        let value = self.values.entry((state, action)).or_insert(0.0);
+        *value += self.config.learning_rate * (target - *value);
+    }
+
+    /// Plays games against itself from the initial state,
+    /// learning after each decision or at the end of each game.
+    ///
+    /// ### Arguments
+    ///
+    /// * `simulator` - The simulator that determines action outcomes in the domain.
+    /// * `episodes` - The number of games to play.
+    pub fn train<I : Simulator<S, A>>(&mut self, simulator: &mut I, episodes: usize) {
+        for _ in 0..episodes {
+            let state = simulator.generate_initial_state();
+            self.train_episode(simulator, state);
+        }
+    }
+
+    /// Plays a single game against itself from a given state.
+    pub fn train_episode<I : Simulator<S, A>>(&mut self, simulator: &mut I, mut state: S) {
+        let number_of_players = simulator.number_of_players();
+        let mut pending: Vec<Option<Decision<S, A>>> = (0..number_of_players).map(|_| None).collect();
+        let mut trajectories: Vec<Vec<Decision<S, A>>> = (0..number_of_players).map(|_| Vec::new()).collect();
+
+        while !simulator.is_terminal_state(&state) {
+            let legal_actions = simulator.calculate_legal_actions(&state);
+            let mut actions = vec![None; number_of_players];
+            for player_id in 0..number_of_players {
+                if legal_actions[player_id].0.is_empty() {
+                    continue;
+                }
+                let action = self.epsilon_greedy_action(&state, &legal_actions[player_id]);
+                if let Some(decision) = pending[player_id].take() {
+                    let next_value = match self.method {
+                        TabularMethod::QLearning => self.max_value(&state, &Self::sorted_actions(&legal_actions[player_id])),
+                        TabularMethod::Sarsa => self.value(&state, &action),
+                        TabularMethod::MonteCarlo => 0.0,
+                    };
+                    self.learn(decision, next_value, &mut trajectories[player_id]);
+                }
+                pending[player_id] = Some(Decision { state: state.clone(), action: action.clone(), reward: 0.0 });
+                actions[player_id] = Some(action);
+            }
+
+            let next_state = simulator.state_transition(&state, &actions);
+            if self.config.step_rewards {
+                let rewards = simulator.step_rewards(&state, &actions, &next_state);
+                pending.iter_mut().zip(rewards).for_each(|(decision, reward)| {
+                    if let Some(decision) = decision {
+                        decision.reward += reward.0;
+                    }
+                });
+            }
+            state = next_state;
+        }
+
+        let rewards = simulator.calculate_rewards(&state);
+        for player_id in 0..number_of_players {
+            if let Some(mut decision) = pending[player_id].take() {
+                if !self.config.step_rewards {
+                    decision.reward += rewards[player_id].0;
+                }
+                self.learn(decision, 0.0, &mut trajectories[player_id]);
+            }
+            let mut episode_return = 0.0;
+            for decision in trajectories[player_id].drain(..).rev() {
+                episode_return = decision.reward + self.config.discount * episode_return;
+                self.update(decision.state, decision.action, episode_return);
+            }
+        }
+    }
+
+    /// Updates the value of a decision from its reward and the value of
+    /// the player's next decision, or keeps it until the end of the
+    /// game for Monte Carlo control.
+    fn learn(&mut self, decision: Decision<S, A>, next_value: f64, trajectory: &mut Vec<Decision<S, A>>) {
+        match self.method {
+            TabularMethod::MonteCarlo => trajectory.push(decision),
+            _ => {
+                let target = decision.reward + self.config.discount * next_value;
+                self.update(decision.state, decision.action, target);
+            },
+        }
+    }
+
+    /// Creates an agent that greedily plays the learned values.
+    /// Unvisited actions have a value of zero.
+    pub fn agent(&self, rng: ChaCha8Rng) -> TabularAgent<S, A> {
+        return TabularAgent { values: self.values.clone(), rng };
+    }
+
+    /// Writes the table as text. Each entry is a header line with the
+    /// lengths of the state and action strings and the value, followed
+    /// by the state and action strings and a newline, so states and
+    /// actions may contain any characters.
+    ///
+    /// ### Arguments
+    ///
+    /// * `writer` - Where the table is written.
+    pub fn save<W : Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let mut entries = self.values.iter()
+            .map(|((state, action), value)| (state.to_string(), action.to_string(), *value))
+            .collect::<Vec<(String, String, f64)>>();
+        entries.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        for (state, action, value) in entries {
+            writeln!(writer, "{} {} {}", state.len(), action.len(), value)?;
+            writeln!(writer, "{}{}", state, action)?;
+        }
+        return Ok(());
+    }
+}
+
+impl <S, A> TabularLearner<S, A> where
+    S: State + FromStr,
+    A: Action + FromStr,
+{
+    /// Reads a table written by `save`, replacing the current table.
+    ///
+    /// ### Arguments
+    ///
+    /// * `reader` - Where the table is read from.
+    ///
+    /// ### Return Value
+    ///
+    /// An error if the table cannot be read or parsed.
+    pub fn load<R : BufRead>(&mut self, reader: &mut R) -> Result<(), String> {
+        let mut values = HashMap::new();
+        let mut header = String::new();
+        loop {
+            header.clear();
+            if reader.read_line(&mut header).map_err(|e| e.to_string())? == 0 {
+                break;
+            }
+            let fields = header.split_whitespace().collect::<Vec<&str>>();
+            let (state_length, action_length, value) = match fields.as_slice() {
+                [state_length, action_length, value] => (
+                    state_length.parse::<usize>().map_err(|e| e.to_string())?,
+                    action_length.parse::<usize>().map_err(|e| e.to_string())?,
+                    value.parse::<f64>().map_err(|e| e.to_string())?,
+                ),
+                _ => return Err(format!("invalid entry header: {}", header.trim_end())),
+            };
+            let mut bytes = vec![0; state_length + action_length + 1];
+            reader.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+            if bytes.pop() != Some(b'\n') {
+                return Err("entry is not terminated by a newline".to_string());
+            }
+            let action_bytes = bytes.split_off(state_length);
+            let state = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+            let action = String::from_utf8(action_bytes).map_err(|e| e.to_string())?;
+            let state = S::from_str(&state).map_err(|_| format!("invalid state: {}", state))?;
+            let action = A::from_str(&action).map_err(|_| format!("invalid action: {}", action))?;
+            values.insert((state, action), value);
+        }
+        self.values = values;
+        return Ok(());
+    }
+}
+
+/// Plays the values learned by `TabularLearner`, choosing the legal
+/// action with the highest value and breaking ties at random.
+#[derive(Debug)]
+pub struct TabularAgent<S : State, A : Action> {
+    values: HashMap<(S, A), f64>,
+    rng: ChaCha8Rng,
+}
+
+impl <S : State, A : Action> Agent<S, A> for TabularAgent<S, A> {
+    fn select_action<I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
+        I: Simulator<S, A>,
+    {
+        let legal_actions = simulator.calculate_legal_actions(state).swap_remove(player_id);
+        let mut actions = legal_actions.0.into_iter()
+            .map(|action| {
+                let value = self.values.get(&(state.clone(), action.clone())).cloned().unwrap_or(0.0);
+                (action, value)
+            })
+            .collect::<Vec<(A, f64)>>();
+        actions.sort_by_cached_key(|(action, _)| action.to_string());
+        let best = actions.iter().map(|(_, value)| *value).fold(f64::NEG_INFINITY, f64::max);
+        let best_actions = actions.into_iter().filter(|(_, value)| *value == best).collect::<Vec<_>>();
+        return best_actions[self.rng.gen_range(0..best_actions.len())].0.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::core::game::play_game;
+    use crate::core::reward::ADVERSARIAL_P1_WIN;
+    use crate::core::testing::counters::{CountersSimulator, CountersState, TestAction};
+
+    use super::*;
+
+    const EPISODES: usize = 2000;
+
+    fn train(method: TabularMethod) -> TabularLearner<CountersState, TestAction> {
+        let config = TabularConfig { learning_rate: 0.2, epsilon: 0.2, ..TabularConfig::default() };
+        let mut learner = TabularLearner::new(method, config, ChaCha8Rng::seed_from_u64(0));
+        learner.train(&mut CountersSimulator::new(5), EPISODES);
+        return learner;
+    }
+
+    /// Leaving a multiple of three counters wins.
+    fn assert_learns_winning_moves(mut learner: TabularLearner<CountersState, TestAction>) {
+        let mut simulator = CountersSimulator::new(5);
+        for (counters, turn, winning_action) in [(5, 0, 2), (4, 1, 1), (2, 1, 2), (1, 0, 1)] {
+            let state = CountersState { counters, turn };
+            let legal_actions = simulator.calculate_legal_actions(&state).swap_remove(turn);
+            assert_eq!(learner.greedy_action(&state, &legal_actions), TestAction(winning_action), "from {}", counters);
+        }
+        assert!(learner.value(&CountersState { counters: 5, turn: 0 }, &TestAction(2)) > 0.5);
+    }
+
+    #[test]
+    fn q_learning_learns_winning_moves() {
+        assert_learns_winning_moves(train(TabularMethod::QLearning));
+    }
+
+    #[test]
+    fn sarsa_learns_winning_moves() {
+        assert_learns_winning_moves(train(TabularMethod::Sarsa));
+    }
+
+    #[test]
+    fn monte_carlo_learns_winning_moves() {
+        assert_learns_winning_moves(train(TabularMethod::MonteCarlo));
+    }
+
+    #[test]
+    fn agent_wins_as_first_player() {
+        let learner = train(TabularMethod::QLearning);
+        let mut agents = [learner.agent(ChaCha8Rng::seed_from_u64(0)), learner.agent(ChaCha8Rng::seed_from_u64(1))];
+        let history = play_game(&mut CountersSimulator::new(5), &mut agents, &mut []);
+        assert_eq!(history.outcome(), Some(&ADVERSARIAL_P1_WIN[..]));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let learner = train(TabularMethod::QLearning);
+        let mut saved = Vec::new();
+        learner.save(&mut saved).unwrap();
+        let mut loaded = TabularLearner::new(TabularMethod::QLearning, TabularConfig::default(), ChaCha8Rng::seed_from_u64(0));
+        loaded.load(&mut saved.as_slice()).unwrap();
+        assert_eq!(loaded.len(), learner.len());
+        for (state, action) in learner.values.keys() {
+            assert_eq!(loaded.value(state, action), learner.value(state, action));
+        }
+    }
+
+    #[test]
+    fn load_rejects_truncated_table() {
+        let mut learner = TabularLearner::<CountersState, TestAction>::new(TabularMethod::QLearning, TabularConfig::default(), ChaCha8Rng::seed_from_u64(0));
+        assert!(learner.load(&mut "9 1 0.5\ncounters".as_bytes()).is_err());
+        assert!(learner.load(&mut "not a header\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+//! Conformance checks that any `Simulator` implementation should pass.
+//!
+//! Each check panics with a descriptive message on failure so it can be
+//! called directly from a `#[test]` function. The
+//! [`simulator_conformance_tests`](crate::simulator_conformance_tests)
+//! macro generates one test per check for a simulator.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+use super::simulator::{Action, LegalActions, Simulator, State};
+
+#[cfg(test)]
+pub mod counters;
+
+/// Number of random games played by each conformance check.
+pub const DEFAULT_GAMES: usize = 20;
+
+/// Number of transitions after which a random game is
+/// considered to never terminate.
+pub const DEFAULT_MAX_STEPS: usize = 10_000;
+
+/// Selects a uniformly random legal action for every player that has
+/// legal actions and no action for every player that does not.
+pub fn random_joint_action<A : Action>(legal_actions: &[LegalActions<A>], rng: &mut ChaCha8Rng) -> Vec<Option<A>> {
+    return legal_actions.iter()
+        .map(|player_legal_actions| {
+            if player_legal_actions.0.is_empty() {
+                None
+            } else {
+                let index = rng.gen_range(0..player_legal_actions.0.len());
+                player_legal_actions.iter().nth(index).cloned()
+            }
+        })
+        .collect();
+}
+
+/// Plays a game from the initial state using random legal actions.
+///
+/// ### Return Value
+///
+/// Every state visited, starting at the initial state and ending at
+/// a terminal state, or an error if the game did not terminate
+/// within `max_steps` transitions.
+pub fn random_playout<S, A, I>(simulator: &mut I, rng: &mut ChaCha8Rng, max_steps: usize) -> Result<Vec<S>, String> where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    let mut states = vec![simulator.generate_initial_state()];
+    for _ in 0..max_steps {
+        let state = states.last().unwrap();
+        if simulator.is_terminal_state(state) {
+            return Ok(states);
+        }
+        let legal_actions = simulator.calculate_legal_actions(state);
+        let actions = random_joint_action(&legal_actions, rng);
+        let next_state = simulator.state_transition(state, &actions);
+        states.push(next_state);
+    }
+    return Err(format!("game did not terminate within {} steps", max_steps));
+}
+
+/// Random games always reach a terminal state.
+pub fn check_random_games_terminate<S, A, I>(simulator: &mut I, rng: &mut ChaCha8Rng, games: usize, max_steps: usize) where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    for game in 0..games {
+        if let Err(message) = random_playout(simulator, rng, max_steps) {
+            panic!("game {}: {}", game, message);
+        }
+    }
+}
+
+/// `is_terminal_state` is true exactly when no player has legal actions
+/// and there is one list of legal actions per player.
+pub fn check_terminal_state_agrees_with_legal_actions<S, A, I>(simulator: &mut I, rng: &mut ChaCha8Rng, games: usize, max_steps: usize) where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    for state in visited_states(simulator, rng, games, max_steps) {
+        let legal_actions = simulator.calculate_legal_actions(&state);
+        let number_of_players = simulator.number_of_players();
+        assert_eq!(legal_actions.len(), number_of_players, "legal actions for each player expected:\n{}", state);
+        let no_legal_actions = legal_actions.iter().all(|x| x.0.is_empty());
+        assert_eq!(simulator.is_terminal_state(&state), no_legal_actions, "terminal check disagrees with legal actions:\n{}", state);
+    }
+}
+
+/// Rewards have one entry per player for every reachable state.
+pub fn check_rewards_for_each_player<S, A, I>(simulator: &mut I, rng: &mut ChaCha8Rng, games: usize, max_steps: usize) where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    for state in visited_states(simulator, rng, games, max_steps) {
+        let rewards = simulator.calculate_rewards(&state);
+        let number_of_players = simulator.number_of_players();
+        assert_eq!(rewards.len(), number_of_players, "reward for each player expected:\n{}", state);
+    }
+}
+
+/// Every legal action of every player is accepted as a valid transition
+/// and can be applied without panicking.
+pub fn check_legal_transitions<S, A, I>(simulator: &mut I, rng: &mut ChaCha8Rng, games: usize, max_steps: usize) where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    for state in visited_states(simulator, rng, games, max_steps) {
+        let legal_actions = simulator.calculate_legal_actions(&state);
+        let default_actions = random_joint_action(&legal_actions, rng);
+        for (player_id, player_legal_actions) in legal_actions.iter().enumerate() {
+            for action in player_legal_actions.iter() {
+                let mut actions = default_actions.clone();
+                actions[player_id] = Some(action.clone());
+                if let Err(message) = simulator.check_valid_state_transition(&state, &actions) {
+                    panic!("legal action {} rejected for player {}: {}\n{}", action, player_id, message, state);
+                }
+                simulator.state_transition(&state, &actions);
+            }
+        }
+    }
+}
+
+/// Equal states have equal hashes. States reached by different moves
+/// or in different games are bucketed by their `Display` output and
+/// compared with `==`, so equal states are found without their hashes.
+pub fn check_equal_states_hash_equally<S, A, I>(simulator: &mut I, rng: &mut ChaCha8Rng, games: usize, max_steps: usize) where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    let mut buckets: HashMap<String, Vec<S>> = HashMap::new();
+    for state in visited_states(simulator, rng, games, max_steps) {
+        let bucket = buckets.entry(state.to_string()).or_default();
+        match bucket.iter().find(|other| **other == state) {
+            Some(other) => assert_eq!(hash(&state), hash(other), "equal states hash differently:\n{}", state),
+            None => bucket.push(state),
+        }
+    }
+}
+
+/// States and legal actions parse back from their `Display` output
+/// into equal values with equal hashes.
+pub fn check_display_from_str_round_trip<S, A, I>(simulator: &mut I, rng: &mut ChaCha8Rng, games: usize, max_steps: usize) where
+    S: State + FromStr,
+    A: Action + FromStr,
+    S::Err: fmt::Debug,
+    A::Err: fmt::Debug,
+    I: Simulator<S, A>,
+{
+    for state in visited_states(simulator, rng, games, max_steps) {
+        check_round_trip(&state);
+        for player_legal_actions in simulator.calculate_legal_actions(&state) {
+            player_legal_actions.iter().for_each(check_round_trip);
+        }
+    }
+}
+
+fn check_round_trip<T>(value: &T) where
+    T: fmt::Debug + fmt::Display + Hash + Eq + FromStr,
+    T::Err: fmt::Debug,
+{
+    let text = value.to_string();
+    let parsed = text.parse::<T>()
+        .unwrap_or_else(|error| panic!("failed to parse {:?}: {:?}", text, error));
+    assert_eq!(&parsed, value, "round trip through {:?} changed the value", text);
+    assert_eq!(hash(&parsed), hash(value), "round trip through {:?} changed the hash", text);
+}
+
+fn visited_states<S, A, I>(simulator: &mut I, rng: &mut ChaCha8Rng, games: usize, max_steps: usize) -> Vec<S> where
+    S: State,
+    A: Action,
+    I: Simulator<S, A>,
+{
+    return (0..games)
+        .flat_map(|_| random_playout(simulator, rng, max_steps).unwrap())
+        .collect();
+}
+
+fn hash<T : Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    return hasher.finish();
+}
+
+/// Generates a test module running every conformance check
+/// against a simulator.
+///
+/// The simulator expression may use the named `&mut ChaCha8Rng`,
+/// which is seeded independently of the rng used to select actions.
+///
+/// ```ignore
+/// simulator_conformance_tests!(conformance, |_rng| Connect4Simulator::new());
+/// simulator_conformance_tests!(conformance, |rng| YahtzeeSimulator::new(rng.clone()));
+/// ```
+#[macro_export]
+macro_rules! simulator_conformance_tests {
+    ($module:ident, |$rng:ident| $simulator:expr) => {
+        mod $module {
+            use super::*;
+            use rand::SeedableRng;
+            use rand_chacha::ChaCha8Rng;
+            use $crate::core::testing::{self, DEFAULT_GAMES, DEFAULT_MAX_STEPS};
+
+            macro_rules! conformance_test {
+                ($name:ident) => {
+                    #[test]
+                    fn $name() {
+                        let mut simulator_rng = ChaCha8Rng::seed_from_u64(0);
+                        let $rng = &mut simulator_rng;
+                        let mut simulator = $simulator;
+                        let mut rng = ChaCha8Rng::seed_from_u64(1);
+                        testing::$name(&mut simulator, &mut rng, DEFAULT_GAMES, DEFAULT_MAX_STEPS);
+                    }
+                };
+            }
+
+            conformance_test!(check_random_games_terminate);
+            conformance_test!(check_terminal_state_agrees_with_legal_actions);
+            conformance_test!(check_rewards_for_each_player);
+            conformance_test!(check_legal_transitions);
+            conformance_test!(check_equal_states_hash_equally);
+            conformance_test!(check_display_from_str_round_trip);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::core::reward::Reward;
+
+    use super::counters::{CountersSimulator, TestAction};
+    use super::*;
+
+    #[test]
+    fn equal_counters_states_hash_equally() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        check_equal_states_hash_equally(&mut CountersSimulator::new(5), &mut rng, DEFAULT_GAMES, DEFAULT_MAX_STEPS);
+    }
+
+    #[test]
+    #[should_panic(expected = "equal states hash differently")]
+    fn equal_states_hashing_their_moves_fail() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        check_equal_states_hash_equally(&mut PathSimulator, &mut rng, DEFAULT_GAMES, DEFAULT_MAX_STEPS);
+    }
+
+    /// A count that equality compares and a number
+    /// of moves played that only the hash includes.
+    #[derive(Clone, fmt::Debug)]
+    struct PathState {
+        count: u8,
+        moves: u8,
+    }
+
+    impl PartialEq for PathState {
+        fn eq(&self, other: &Self) -> bool {
+            self.count == other.count
+        }
+    }
+
+    impl Eq for PathState {}
+
+    impl Hash for PathState {
+        fn hash<H : Hasher>(&self, state: &mut H) {
+            self.count.hash(state);
+            self.moves.hash(state);
+        }
+    }
+
+    impl State for PathState {}
+
+    impl fmt::Display for PathState {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.count)
+        }
+    }
+
+    /// A single player counts down from four by one or two.
+    #[derive(Clone)]
+    struct PathSimulator;
+
+    impl Simulator<PathState, TestAction> for PathSimulator {
+        fn generate_initial_state(&mut self) -> PathState {
+            PathState { count: 4, moves: 0 }
+        }
+
+        fn calculate_rewards(&mut self, _state: &PathState) -> Vec<Reward> {
+            vec![Reward(0.0)]
+        }
+
+        fn calculate_legal_actions(&mut self, state: &PathState) -> Vec<LegalActions<TestAction>> {
+            let mut legal_actions = LegalActions::new();
+            (1..=state.count.min(2)).for_each(|x| legal_actions.insert(TestAction(x)));
+            return vec![legal_actions];
+        }
+
+        fn state_transition(&mut self, state: &PathState, actions: &Vec<Option<TestAction>>) -> PathState {
+            let action = actions[0].as_ref().unwrap();
+            return PathState { count: state.count - action.0, moves: state.moves + 1 };
+        }
+
+        fn number_of_players(&mut self) -> usize {
+            1
+        }
+    }
+}
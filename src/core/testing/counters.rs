@@ -0,0 +1,157 @@
+//! A small two-player game shared by the tests of agents and learners.
+//!
+//! Players alternate removing one or two counters
+//! and the player to take the last counter wins.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand_chacha::ChaCha8Rng;
+
+use crate::core::encoder::Encoder;
+use crate::core::imperfect_info::{ImperfectInfoSimulator, Observation};
+use crate::core::reward::{Reward, ADVERSARIAL_DRAW, ADVERSARIAL_P1_LOSS, ADVERSARIAL_P1_WIN};
+use crate::core::simulator::{Action, LegalActions, Simulator, State};
+use crate::core::tt::{zobrist_key, ZobristHash};
+
+/// Removes the given number of counters.
+#[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+pub struct TestAction(pub u8);
+
+impl Action for TestAction {}
+
+impl fmt::Display for TestAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for TestAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(TestAction).map_err(|_| format!("invalid action: {}", s))
+    }
+}
+
+#[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+pub struct CountersState {
+    pub counters: u8,
+    pub turn: usize,
+}
+
+impl State for CountersState {}
+
+impl Observation for CountersState {}
+
+impl fmt::Display for CountersState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.counters, self.turn)
+    }
+}
+
+impl FromStr for CountersState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(' ').map(|(counters, turn)| (counters.parse(), turn.parse())) {
+            Some((Ok(counters), Ok(turn))) => Ok(CountersState { counters, turn }),
+            _ => Err(format!("invalid state: {}", s)),
+        }
+    }
+}
+
+impl ZobristHash for CountersState {
+    fn zobrist_hash(&self) -> u64 {
+        zobrist_key(self.counters as u64 * 2 + self.turn as u64)
+    }
+
+    fn updated_zobrist_hash(&self, hash: u64, next: &Self) -> u64 {
+        return hash ^ self.zobrist_hash() ^ next.zobrist_hash();
+    }
+}
+
+/// Starts games with a fixed number of counters.
+#[derive(Clone, fmt::Debug)]
+pub struct CountersSimulator {
+    counters: u8,
+}
+
+impl CountersSimulator {
+    pub fn new(counters: u8) -> Self {
+        CountersSimulator { counters }
+    }
+}
+
+impl Simulator<CountersState, TestAction> for CountersSimulator {
+    fn generate_initial_state(&mut self) -> CountersState {
+        CountersState { counters: self.counters, turn: 0 }
+    }
+
+    fn calculate_rewards(&mut self, state: &CountersState) -> Vec<Reward> {
+        match (state.counters, state.turn) {
+            (0, 0) => ADVERSARIAL_P1_LOSS.to_vec(),
+            (0, _) => ADVERSARIAL_P1_WIN.to_vec(),
+            _ => ADVERSARIAL_DRAW.to_vec(),
+        }
+    }
+
+    fn calculate_legal_actions(&mut self, state: &CountersState) -> Vec<LegalActions<TestAction>> {
+        let mut legal_actions = vec![LegalActions::new(), LegalActions::new()];
+        (1..=state.counters.min(2)).for_each(|x| legal_actions[state.turn].insert(TestAction(x)));
+        return legal_actions;
+    }
+
+    fn state_transition(&mut self, state: &CountersState, actions: &Vec<Option<TestAction>>) -> CountersState {
+        let action = actions[state.turn].as_ref().unwrap();
+        return CountersState { counters: state.counters - action.0, turn: 1 - state.turn };
+    }
+
+    fn number_of_players(&mut self) -> usize {
+        2
+    }
+}
+
+/// Both players see the whole state.
+impl ImperfectInfoSimulator<CountersState, TestAction, CountersState> for CountersSimulator {
+    fn observe(&mut self, state: &CountersState, _player_id: usize) -> CountersState {
+        state.clone()
+    }
+
+    fn sample_determinization(&mut self, observation: &CountersState, _player_id: usize, _rng: &mut ChaCha8Rng) -> CountersState {
+        observation.clone()
+    }
+}
+
+/// One-hot encodes the number of counters and whose turn it is.
+#[derive(Clone, fmt::Debug)]
+pub struct CountersEncoder {
+    max_counters: u8,
+}
+
+impl CountersEncoder {
+    pub fn new(max_counters: u8) -> Self {
+        CountersEncoder { max_counters }
+    }
+}
+
+impl Encoder<CountersState, TestAction> for CountersEncoder {
+    fn input_size(&self) -> usize {
+        self.max_counters as usize + 3
+    }
+
+    fn encode(&self, state: &CountersState) -> Vec<f64> {
+        let mut input = vec![0.0; self.input_size()];
+        input[state.counters as usize] = 1.0;
+        input[self.max_counters as usize + 1 + state.turn] = 1.0;
+        return input;
+    }
+
+    fn policy_size(&self) -> usize {
+        2
+    }
+
+    fn action_index(&self, action: &TestAction) -> usize {
+        action.0 as usize - 1
+    }
+}
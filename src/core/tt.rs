@@ -0,0 +1,205 @@
+//! Zobrist hashing and the transposition table of the alpha-beta agent.
+
+use super::simulator::State;
+
+/// A state that can be hashed by combining, with exclusive or, a random
+/// key for each of its features, such as a piece on a square.
+///
+/// Unlike the derived `Hash`, a Zobrist hash is the same on every platform
+/// and Rust version, and the hash of the next state can be updated from the
+/// hash of the previous one by only combining the keys of what changed.
+pub trait ZobristHash: State {
+    /// The hash of the state.
+    fn zobrist_hash(&self) -> u64;
+
+    /// The hash of a state reached from this one.
+    ///
+    /// ### Arguments
+    ///
+    /// * `hash` - The hash of this state.
+    /// * `next` - A state reached from this one.
+    ///
+    /// ### Return Value
+    ///
+    /// The same hash as `next.zobrist_hash()`, which is what is
+    /// returned unless the update is implemented incrementally.
+    fn updated_zobrist_hash(&self, hash: u64, next: &Self) -> u64 {
+        let _ = hash;
+        return next.zobrist_hash();
+    }
+}
+
+/// The random key of a feature of a state, numbered by the domain.
+///
+/// Keys are generated by the SplitMix64 mixing function, so they are
+/// the same everywhere and can be computed in constant tables.
+pub const fn zobrist_key(feature: u64) -> u64 {
+    let mut z = feature.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    return z ^ (z >> 31);
+}
+
+/// Decides which entry is kept when two entries map to the same slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplacementPolicy {
+    /// New entries always replace the entry in their slot.
+    Always,
+    /// New entries replace entries stored with a greater depth only
+    /// if those entries were stored before the current search.
+    DepthPreferred,
+}
+
+/// A stored search result.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TtEntry<V> {
+    /// The full hash of the state, to detect other states in the same slot.
+    pub key: u64,
+    /// How deep the state was searched, where deeper results are more valuable.
+    pub depth: u32,
+    pub value: V,
+    generation: u32,
+}
+
+/// A fixed-size table of search results indexed by state hash,
+/// used by `AlphaBetaAgent` to reuse the values of searched states.
+///
+/// The table never grows, so when two states map to the same slot the
+/// replacement policy decides which one is kept.
+#[derive(Clone, Debug)]
+pub struct TranspositionTable<V> {
+    entries: Vec<Option<TtEntry<V>>>,
+    policy: ReplacementPolicy,
+    generation: u32,
+    len: usize,
+}
+
+impl <V> TranspositionTable<V> {
+    /// Creates an empty table.
+    ///
+    /// ### Arguments
+    ///
+    /// * `capacity` - The maximum number of entries, rounded
+    ///   down to a power of two and at least one.
+    /// * `policy` - Which entry is kept when two entries map to the same slot.
+    pub fn new(capacity: usize, policy: ReplacementPolicy) -> Self {
+        let capacity = if capacity <= 1 { 1 } else { 1 << (usize::BITS - 1 - capacity.leading_zeros()) };
+        let entries = (0..capacity).map(|_| None).collect();
+        TranspositionTable { entries, policy, generation: 0, len: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The number of stored entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+
+    /// The entry stored for a hash, or `None` if there is none.
+    pub fn get(&self, key: u64) -> Option<&TtEntry<V>> {
+        return self.entries[self.index(key)].as_ref().filter(|entry| entry.key == key);
+    }
+
+    /// Stores a search result unless the replacement policy keeps
+    /// the entry already in its slot.
+    ///
+    /// ### Return Value
+    ///
+    /// Whether the result was stored.
+    pub fn insert(&mut self, key: u64, depth: u32, value: V) -> bool {
+        let index = self.index(key);
+        let generation = self.generation;
+        let replace = match &self.entries[index] {
+            None => true,
+            Some(entry) => match self.policy {
+                ReplacementPolicy::Always => true,
+                ReplacementPolicy::DepthPreferred => entry.key == key || depth >= entry.depth || entry.generation != generation,
+            },
+        };
+        if replace {
+            if self.entries[index].is_none() {
+                self.len += 1;
+            }
+            self.entries[index] = Some(TtEntry { key, depth, value, generation });
+        }
+        return replace;
+    }
+
+    /// Starts a new search, so that entries from previous searches
+    /// can be replaced regardless of their depth.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zobrist_keys_are_stable() {
+        assert_eq!(zobrist_key(0), 0xe220_a839_7b1d_cdaf);
+        assert_ne!(zobrist_key(1), zobrist_key(0));
+    }
+
+    #[test]
+    fn capacity_is_a_power_of_two() {
+        assert_eq!(TranspositionTable::<u8>::new(0, ReplacementPolicy::Always).capacity(), 1);
+        assert_eq!(TranspositionTable::<u8>::new(1000, ReplacementPolicy::Always).capacity(), 512);
+        assert_eq!(TranspositionTable::<u8>::new(1024, ReplacementPolicy::Always).capacity(), 1024);
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let mut table = TranspositionTable::new(16, ReplacementPolicy::Always);
+        assert!(table.is_empty());
+        table.insert(3, 2, "three");
+        assert_eq!(table.get(3).map(|entry| (entry.depth, entry.value)), Some((2, "three")));
+        assert_eq!(table.get(19), None);
+        assert_eq!(table.len(), 1);
+        table.clear();
+        assert_eq!(table.get(3), None);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn always_replaces_colliding_entries() {
+        let mut table = TranspositionTable::new(16, ReplacementPolicy::Always);
+        table.insert(3, 5, "deep");
+        assert!(table.insert(19, 1, "shallow"));
+        assert_eq!(table.get(3), None);
+        assert_eq!(table.get(19).unwrap().value, "shallow");
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn depth_preferred_keeps_deeper_entries_of_current_search() {
+        let mut table = TranspositionTable::new(16, ReplacementPolicy::DepthPreferred);
+        table.insert(3, 5, "deep");
+        assert!(!table.insert(19, 1, "shallow"));
+        assert_eq!(table.get(3).unwrap().value, "deep");
+        assert!(table.insert(3, 1, "same state"));
+        assert!(table.insert(19, 1, "as deep"));
+
+        table.insert(3, 5, "deep");
+        table.new_search();
+        assert!(table.insert(19, 1, "new search"));
+        assert_eq!(table.get(19).unwrap().value, "new search");
+    }
+}
@@ -1,2 +1,6 @@
 pub mod connect4;
-pub mod yahtzee;
\ No newline at end of file
+pub mod connectn;
+pub mod gomoku;
+pub mod mnk;
+pub mod othello;
+pub mod yahtzee;
@@ -1,4 +1,9 @@
 pub mod connect4_action;
+pub mod connect4_book;
+pub mod connect4_encoder;
+pub mod connect4_evaluator;
+pub mod connect4_pop_simulator;
+pub mod connect4_pop_state;
 pub mod connect4_state;
 pub mod connect4_simulator;
 
@@ -1,18 +1,62 @@
 use std::fmt;
+use std::str::FromStr;
 
 use crate::core::simulator::Action;
 
-/// Represents a slot location to place a piece.
+use super::constants::BOARD_WIDTH;
+
+/// Whether an action adds a piece to the top of a column or,
+/// in the Pop variants, removes one from the bottom.
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Connect4ActionKind {
+    Drop,
+    Pop,
+}
+
+/// Represents a slot location to place a piece or pop a piece from.
 #[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
 pub struct Connect4Action {
     pub location: u8,
+    pub kind: Connect4ActionKind,
+}
+
+impl Connect4Action {
+    /// Drops a piece into a column.
+    pub const fn drop(location: u8) -> Self {
+        Connect4Action { location, kind: Connect4ActionKind::Drop }
+    }
+
+    /// Pops a piece out of the bottom of a column.
+    pub const fn pop(location: u8) -> Self {
+        Connect4Action { location, kind: Connect4ActionKind::Pop }
+    }
 }
 
 impl Action for Connect4Action {}
 
+/// Drops are written as their column from one, as in `4`,
+/// and pops with a leading `p`, as in `p4`.
 impl fmt::Display for Connect4Action {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.location + 1)
+        match self.kind {
+            Connect4ActionKind::Drop => write!(f, "{}", self.location + 1),
+            Connect4ActionKind::Pop => write!(f, "p{}", self.location + 1),
+        }
+    }
+}
+
+impl FromStr for Connect4Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (slot, kind) = match s.trim().strip_prefix('p') {
+            Some(slot) => (slot, Connect4ActionKind::Pop),
+            None => (s.trim(), Connect4ActionKind::Drop),
+        };
+        match slot.parse::<usize>() {
+            Ok(slot) if (1..=BOARD_WIDTH).contains(&slot) => Ok(Connect4Action { location: (slot - 1) as u8, kind }),
+            _ => Err(format!("invalid connect 4 action: {}", s)),
+        }
     }
 }
 
@@ -22,7 +66,22 @@ mod tests {
 
     #[test]
     fn connect4_action_to_string() {
-        let action = Connect4Action { location: 0 };
+        let action = Connect4Action::drop(0);
         assert_eq!(action.to_string(), "1");
+        assert_eq!(Connect4Action::pop(3).to_string(), "p4");
+    }
+
+    #[test]
+    fn connect4_action_from_str() {
+        assert_eq!("7".parse::<Connect4Action>(), Ok(Connect4Action::drop(6)));
+        assert_eq!("p1".parse::<Connect4Action>(), Ok(Connect4Action::pop(0)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn connect4_action_from_str_out_of_range() {
+        assert!("0".parse::<Connect4Action>().is_err());
+        assert!("8".parse::<Connect4Action>().is_err());
+        assert!("p8".parse::<Connect4Action>().is_err());
+        assert!("p".parse::<Connect4Action>().is_err());
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use crate::core::agent::Agent;
+use crate::core::simulator::Simulator;
+use crate::core::symmetry::{canonical, Symmetric};
+
+use super::connect4_action::Connect4Action;
+use super::connect4_simulator::Connect4Simulator;
+use super::connect4_state::Connect4State;
+use super::constants::BOARD_WIDTH;
+
+/// The scores of every legal action from a position, from the point
+/// of view of the player to move, where a higher score is better.
+pub type ActionScores = Vec<(Connect4Action, i8)>;
+
+/// Solved Connect 4 opening positions.
+///
+/// Symmetric positions, a position and its mirror image, are stored once
+/// under the smallest of their keys, so the book only needs about half
+/// of the positions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpeningBook {
+    entries: HashMap<u64, ActionScores>,
+}
+
+impl OpeningBook {
+    pub fn new() -> Self {
+        OpeningBook { entries: HashMap::new() }
+    }
+
+    /// Builds a book of every position reachable within a number of moves
+    /// that is not already decided.
+    ///
+    /// ### Arguments
+    ///
+    /// * `depth` - The maximum number of moves played to reach a position.
+    /// * `solver` - Scores every legal action of a position from the point of
+    ///   view of the player to move, where a higher score is better, such as
+    ///   the `AlphaBetaAgent::action_values` of each action scaled to fit in
+    ///   an `i8`. Scores only need to be comparable within a position.
+    pub fn generate<F>(depth: usize, mut solver: F) -> Self where
+        F: FnMut(&Connect4State) -> ActionScores,
+    {
+        let mut book = OpeningBook::new();
+        let mut simulator = Connect4Simulator::new();
+        let mut positions = vec![simulator.generate_initial_state()];
+        for moves in 0..=depth {
+            let mut next_positions = Vec::new();
+            for state in positions {
+                if book.get(&state).is_some() || simulator.is_terminal_state(&state) {
+                    continue;
+                }
+                book.insert(&state, solver(&state));
+                if moves == depth {
+                    continue;
+                }
+                let legal_actions = simulator.calculate_legal_actions(&state);
+                for (player_id, player_legal_actions) in legal_actions.iter().enumerate() {
+                    for action in player_legal_actions.iter() {
+                        let mut actions = vec![None; legal_actions.len()];
+                        actions[player_id] = Some(*action);
+                        next_positions.push(simulator.state_transition(&state, &actions));
+                    }
+                }
+            }
+            positions = next_positions;
+        }
+        return book;
+    }
+
+    /// The number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds or replaces the action scores of a position.
+    pub fn insert(&mut self, state: &Connect4State, scores: ActionScores) {
+        let (key, symmetry) = canonical_key(state);
+        self.entries.insert(key, transform_scores(state, &scores, symmetry));
+    }
+
+    /// The action scores of a position, or `None` if it is not in the book.
+    pub fn get(&self, state: &Connect4State) -> Option<ActionScores> {
+        let (key, symmetry) = canonical_key(state);
+        let canonical_state = state.transform(symmetry);
+        return self.entries.get(&key).map(|scores| transform_scores(&canonical_state, scores, state.inverse(symmetry)));
+    }
+
+    /// The highest scoring action of a position, preferring
+    /// central columns when actions are tied.
+    pub fn best_action(&self, state: &Connect4State) -> Option<Connect4Action> {
+        let center = (BOARD_WIDTH as i32 - 1) / 2;
+        return self.get(state)?.into_iter()
+            .max_by_key(|(action, score)| (*score, -(action.location as i32 - center).abs(), -(action.location as i32)))
+            .map(|(action, _)| action);
+    }
+
+    /// Writes the book as text, one position per line: the key
+    /// followed by each action and its score, as in `4:1`.
+    pub fn save<W : Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let mut keys = self.entries.keys().collect::<Vec<&u64>>();
+        keys.sort();
+        for key in keys {
+            let scores = self.entries[key].iter()
+                .map(|(action, score)| format!("{}:{}", action, score))
+                .collect::<Vec<String>>();
+            writeln!(writer, "{} {}", key, scores.join(" "))?;
+        }
+        return Ok(());
+    }
+
+    /// Reads a book written by `save`.
+    ///
+    /// ### Return Value
+    ///
+    /// The book, or an error if it cannot be read or parsed.
+    pub fn load<R : BufRead>(reader: &mut R) -> Result<Self, String> {
+        let mut entries = HashMap::new();
+        for line in reader.lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            let mut fields = line.split_whitespace();
+            let Some(key) = fields.next() else {
+                continue;
+            };
+            let key = key.parse::<u64>().map_err(|_| format!("invalid key: {}", key))?;
+            let scores = fields
+                .map(|field| match field.split_once(':') {
+                    Some((action, score)) => Ok((
+                        Connect4Action::from_str(action)?,
+                        score.parse::<i8>().map_err(|_| format!("invalid score: {}", field))?,
+                    )),
+                    None => Err(format!("invalid action score: {}", field)),
+                })
+                .collect::<Result<ActionScores, String>>()?;
+            entries.insert(key, scores);
+        }
+        return Ok(OpeningBook { entries });
+    }
+}
+
+/// The key a position is stored under and the symmetry
+/// that transforms the position into the stored one.
+fn canonical_key(state: &Connect4State) -> (u64, usize) {
+    let (canonical_state, symmetry) = canonical(state, Connect4State::key);
+    return (canonical_state.key(), symmetry);
+}
+
+fn transform_scores(state: &Connect4State, scores: &ActionScores, symmetry: usize) -> ActionScores {
+    let mut transformed = scores.iter()
+        .map(|(action, score)| (state.transform_action(action, symmetry), *score))
+        .collect::<ActionScores>();
+    transformed.sort_by_key(|(action, _)| action.location);
+    return transformed;
+}
+
+/// Plays the best book action in positions that are in an opening book
+/// and lets another agent select actions everywhere else.
+#[derive(Debug)]
+pub struct OpeningBookAgent<G> {
+    book: OpeningBook,
+    fallback: G,
+    info: Option<String>,
+}
+
+impl <G> OpeningBookAgent<G> {
+    /// Creates an agent that consults a book before a fallback agent.
+    pub fn new(book: OpeningBook, fallback: G) -> Self {
+        OpeningBookAgent { book, fallback, info: None }
+    }
+}
+
+impl <G : Agent<Connect4State, Connect4Action>> Agent<Connect4State, Connect4Action> for OpeningBookAgent<G> {
+    fn select_action<I>(&mut self, player_id: usize, state: &Connect4State, simulator: &mut I) -> Connect4Action where
+        I: Simulator<Connect4State, Connect4Action>,
+    {
+        if let Some(action) = self.book.best_action(state) {
+            if simulator.calculate_legal_actions(state)[player_id].0.contains(&action) {
+                self.info = Some(format!("book: {}", action));
+                return action;
+            }
+        }
+        let action = self.fallback.select_action(player_id, state, simulator);
+        self.info = self.fallback.action_info();
+        return action;
+    }
+
+    fn action_info(&self) -> Option<String> {
+        self.info.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::core::agent::DefaultAgents;
+    use crate::core::alphabeta::AlphaBetaAgent;
+    use crate::domains::connect4::connect4_evaluator::ThreatEvaluator;
+
+    use super::*;
+
+    /// Scores each action by its column, so scores are not symmetric.
+    fn column_solver(state: &Connect4State) -> ActionScores {
+        let legal_actions = Connect4Simulator::new().calculate_legal_actions(state);
+        let mut scores = legal_actions.iter()
+            .flat_map(|legal_actions| legal_actions.iter())
+            .map(|action| (*action, action.location as i8))
+            .collect::<ActionScores>();
+        scores.sort_by_key(|(action, _)| action.location);
+        return scores;
+    }
+
+    /// Scores each action by its alpha-beta value in hundredths of a win.
+    fn alpha_beta_solver(depth: u32) -> impl FnMut(&Connect4State) -> ActionScores {
+        let mut agent = AlphaBetaAgent::new(depth, ThreatEvaluator, 1 << 12);
+        let mut simulator = Connect4Simulator::new();
+        return move |state| {
+            let player_id = if state.player_1_turn() { 0 } else { 1 };
+            agent.action_values(player_id, state, &mut simulator).into_iter()
+                .map(|(action, value)| (action, (value * 100.0).round().clamp(i8::MIN as f64, i8::MAX as f64) as i8))
+                .collect()
+        };
+    }
+
+    fn play(moves: &str) -> Connect4State {
+        let mut simulator = Connect4Simulator::new();
+        let mut state = simulator.generate_initial_state();
+        for (i, action) in moves.split_whitespace().enumerate() {
+            let mut actions = vec![None; 2];
+            actions[i % 2] = Some(action.parse().unwrap());
+            state = simulator.state_transition(&state, &actions);
+        }
+        return state;
+    }
+
+    #[test]
+    fn generate_stores_mirrored_positions_once() {
+        assert_eq!(OpeningBook::generate(0, column_solver).len(), 1);
+        assert_eq!(OpeningBook::generate(1, column_solver).len(), 1 + 4);
+        assert_eq!(OpeningBook::generate(2, column_solver).len(), 1 + 4 + 25);
+    }
+
+    #[test]
+    fn generate_with_alpha_beta_solver() {
+        let mut solver = alpha_beta_solver(4);
+        let book = OpeningBook::generate(1, &mut solver);
+        assert_eq!(book.len(), 1 + 4);
+        assert!(book.get(&play("")).unwrap().iter().all(|(_, score)| score.abs() < 100));
+
+        let mut book = OpeningBook::new();
+        let state = play("1 2 1 2 1 2");
+        book.insert(&state, solver(&state));
+        assert_eq!(book.best_action(&state), Some(Connect4Action::drop(0)));
+        assert_eq!(book.get(&state).unwrap()[0].1, 100);
+        assert!(book.get(&state).unwrap()[2..].iter().all(|(_, score)| *score < 0));
+    }
+
+    #[test]
+    fn get_mirrors_scores() {
+        let mut book = OpeningBook::new();
+        let state = play("1");
+        let scores = column_solver(&state);
+        book.insert(&state, scores.clone());
+        assert_eq!(book.get(&state), Some(scores.clone()));
+        let mirrored = book.get(&play("7")).unwrap();
+        assert_eq!(mirrored, transform_scores(&state, &scores, 1));
+        assert_eq!(mirrored[0], (Connect4Action::drop(0), 6));
+        assert_eq!(book.get(&play("2")), None);
+    }
+
+    #[test]
+    fn best_action_prefers_center_on_ties() {
+        let mut book = OpeningBook::new();
+        let state = play("");
+        let scores = (0..BOARD_WIDTH as u8).map(|location| (Connect4Action::drop(location), 0)).collect();
+        book.insert(&state, scores);
+        assert_eq!(book.best_action(&state), Some(Connect4Action::drop(3)));
+        book.insert(&state, column_solver(&state));
+        assert_eq!(book.best_action(&state), Some(Connect4Action::drop(6)));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let book = OpeningBook::generate(2, column_solver);
+        let mut bytes = Vec::new();
+        book.save(&mut bytes).unwrap();
+        assert_eq!(OpeningBook::load(&mut bytes.as_slice()), Ok(book));
+        assert!(OpeningBook::load(&mut "1 4:x".as_bytes()).is_err());
+        assert!(OpeningBook::load(&mut "1 8:0".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn agent_uses_book_then_fallback() {
+        let book = OpeningBook::generate(0, column_solver);
+        let mut agent = OpeningBookAgent::new(book, DefaultAgents::Random(ChaCha8Rng::seed_from_u64(0)));
+        let mut simulator = Connect4Simulator::new();
+        let state = simulator.generate_initial_state();
+        assert_eq!(agent.select_action(0, &state, &mut simulator), Connect4Action::drop(6));
+        assert_eq!(agent.action_info(), Some("book: 7".to_string()));
+
+        let state = play("4");
+        let action: Connect4Action = agent.select_action(1, &state, &mut simulator);
+        assert!(simulator.calculate_legal_actions(&state)[1].0.contains(&action));
+        assert_eq!(agent.action_info(), None);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::core::encoder::Encoder;
+
+use super::connect4_action::Connect4Action;
+use super::connect4_state::Connect4State;
+use super::constants::{BOARD_HEIGHT, BOARD_WIDTH};
+
+const BOARD_SIZE: usize = BOARD_WIDTH * BOARD_HEIGHT;
+
+/// Encodes a board as three planes of one value per square: the first
+/// player's pieces, the second player's pieces and whether it is the
+/// first player's turn. Squares are ordered by column, bottom row first.
+///
+/// Only drops have a policy index, so the encoder is for standard Connect 4.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Connect4Encoder;
+
+impl Encoder<Connect4State, Connect4Action> for Connect4Encoder {
+    fn input_size(&self) -> usize {
+        3 * BOARD_SIZE
+    }
+
+    fn encode(&self, state: &Connect4State) -> Vec<f64> {
+        let mut input = vec![0.0; self.input_size()];
+        for column in 0..BOARD_WIDTH {
+            for row in 0..BOARD_HEIGHT {
+                let mask = 1 << (column * (BOARD_HEIGHT + 1) + row);
+                let square = column * BOARD_HEIGHT + row;
+                for player in 0..2 {
+                    if state.bit_board[player] & mask != 0 {
+                        input[player * BOARD_SIZE + square] = 1.0;
+                    }
+                }
+            }
+        }
+        if state.player_1_turn() {
+            input[2 * BOARD_SIZE..].fill(1.0);
+        }
+        return input;
+    }
+
+    fn policy_size(&self) -> usize {
+        BOARD_WIDTH
+    }
+
+    fn action_index(&self, action: &Connect4Action) -> usize {
+        action.location as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::core::agent::DefaultAgents;
+    use crate::core::alphazero::{AlphaZero, PuctConfig};
+    use crate::core::dataset::record_symmetric_game;
+    use crate::core::mlp::Mlp;
+    use crate::core::simulator::Simulator;
+    use crate::domains::connect4::connect4_simulator::Connect4Simulator;
+
+    use super::*;
+
+    #[test]
+    fn encode_initial_state() {
+        let state = Connect4Simulator::new().generate_initial_state();
+        let input = Connect4Encoder.encode(&state);
+        assert_eq!(input.len(), 3 * BOARD_SIZE);
+        assert_eq!(input[..2 * BOARD_SIZE].iter().sum::<f64>(), 0.0);
+        assert_eq!(input[2 * BOARD_SIZE..].iter().sum::<f64>(), BOARD_SIZE as f64);
+    }
+
+    #[test]
+    fn encode_pieces_by_player() {
+        let mut simulator = Connect4Simulator::new();
+        let mut state = simulator.generate_initial_state();
+        state = simulator.state_transition(&state, &vec![Some(Connect4Action::drop(3)), None]);
+        state = simulator.state_transition(&state, &vec![None, Some(Connect4Action::drop(3))]);
+        state = simulator.state_transition(&state, &vec![Some(Connect4Action::drop(6)), None]);
+        let input = Connect4Encoder.encode(&state);
+        let squares = |plane: usize| (0..BOARD_SIZE).filter(|&i| input[plane * BOARD_SIZE + i] == 1.0).collect::<Vec<usize>>();
+        assert_eq!(squares(0), vec![3 * BOARD_HEIGHT, 6 * BOARD_HEIGHT]);
+        assert_eq!(squares(1), vec![3 * BOARD_HEIGHT + 1]);
+        assert!(squares(2).is_empty());
+    }
+
+    #[test]
+    fn record_symmetric_game_mirrors_decisions() {
+        let mut agents = [DefaultAgents::Random(ChaCha8Rng::seed_from_u64(0)), DefaultAgents::Random(ChaCha8Rng::seed_from_u64(1))];
+        let records = record_symmetric_game(&mut Connect4Simulator::new(), &mut agents, &Connect4Encoder);
+        let state = Connect4Simulator::new().generate_initial_state();
+        assert_eq!(records[0].observation, records[1].observation);
+        for pair in records.chunks(2) {
+            let [record, mirrored] = pair else { panic!("records should come in pairs") };
+            assert_eq!(record.player_id, mirrored.player_id);
+            let mut policy = record.policy.clone();
+            policy.reverse();
+            assert_eq!(policy, mirrored.policy);
+            let mut legal_mask = record.legal_mask.clone();
+            legal_mask.reverse();
+            assert_eq!(legal_mask, mirrored.legal_mask);
+        }
+        let first_move = records[0].policy.iter().position(|&p| p == 1.0).unwrap() as u8;
+        let next = Connect4Simulator::new().state_transition(&state, &vec![Some(Connect4Action::drop(first_move)), None]);
+        let observation = Connect4Encoder.encode(&next.mirrored()).into_iter().map(|x| x as f32).collect::<Vec<f32>>();
+        assert_eq!(records[3].observation, observation);
+    }
+
+    #[test]
+    fn alphazero_self_play() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let network = Mlp::new(Connect4Encoder.input_size(), &[32], Connect4Encoder.policy_size(), 2, &mut rng);
+        let config = PuctConfig { simulations: 8, ..PuctConfig::default() };
+        let mut alphazero = AlphaZero::new(network, Connect4Encoder, config, rng);
+        let samples = alphazero.self_play(&mut Connect4Simulator::new());
+        assert!(samples.len() >= 7);
+        assert!(samples.iter().all(|sample| sample.policy.len() == BOARD_WIDTH));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::core::encoder::Encoder;
+use crate::core::evaluator::Evaluator;
+
+use super::connect4_encoder::Connect4Encoder;
+use super::connect4_state::{Connect4State, ThreatParity};
+use super::constants::{BOARD_HEIGHT, BOARD_WIDTH};
+
+/// The estimate of a position that is won or lost on the next move,
+/// short of the reward of an actual win.
+const DECIDED_ESTIMATE: f64 = 0.9;
+
+/// Every four squares in a row on the board as masks of the bit board.
+fn windows() -> Vec<u64> {
+    let mut windows = Vec::new();
+    for (column_step, row_step) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+        for column in 0..BOARD_WIDTH as i32 {
+            for row in 0..BOARD_HEIGHT as i32 {
+                let end = (column + 3 * column_step, row + 3 * row_step);
+                if end.0 >= BOARD_WIDTH as i32 || end.1 < 0 || end.1 >= BOARD_HEIGHT as i32 {
+                    continue;
+                }
+                let window = (0..4).fold(0, |window, i| {
+                    let bit = (column + i * column_step) * (BOARD_HEIGHT as i32 + 1) + row + i * row_step;
+                    window | (1 << bit)
+                });
+                windows.push(window);
+            }
+        }
+    }
+    return windows;
+}
+
+/// The estimate of each player from the first player's point of view.
+fn zero_sum(value: f64) -> Vec<f64> {
+    vec![value, -value]
+}
+
+/// Counts the lines of four that each player could still complete,
+/// where lines that already hold more of the player's pieces count more.
+#[derive(Clone, Debug)]
+pub struct OpenLinesEvaluator {
+    windows: Vec<u64>,
+}
+
+impl OpenLinesEvaluator {
+    /// The weight of an open line by the number of pieces in it.
+    const WEIGHTS: [f64; 4] = [0.0, 1.0, 4.0, 16.0];
+
+    pub fn new() -> Self {
+        OpenLinesEvaluator { windows: windows() }
+    }
+
+    /// The weighted number of open lines of a player.
+    fn score(&self, state: &Connect4State, player_id: usize) -> f64 {
+        let (pieces, opponent) = (state.bit_board[player_id], state.bit_board[1 - player_id]);
+        return self.windows.iter()
+            .filter(|&&window| window & opponent == 0)
+            .map(|&window| OpenLinesEvaluator::WEIGHTS[(window & pieces).count_ones().min(3) as usize])
+            .sum();
+    }
+}
+
+impl Default for OpenLinesEvaluator {
+    fn default() -> Self {
+        OpenLinesEvaluator::new()
+    }
+}
+
+impl Evaluator<Connect4State> for OpenLinesEvaluator {
+    fn evaluate(&self, state: &Connect4State) -> Vec<f64> {
+        let difference = self.score(state, 0) - self.score(state, 1);
+        return zero_sum((difference / 32.0).tanh());
+    }
+}
+
+/// Scores the threats of each player, the empty squares that would
+/// complete four in a row.
+///
+/// A player to move with an immediate threat wins and one facing two
+/// immediate threats loses. Otherwise threats count more on the rows
+/// where they usually decide the game once the board fills up, odd rows
+/// for the first player and even rows for the second.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreatEvaluator;
+
+impl ThreatEvaluator {
+    fn score(state: &Connect4State, player_id: usize) -> f64 {
+        let useful_parity = if player_id == 0 { ThreatParity::Odd } else { ThreatParity::Even };
+        return state.threats(player_id).iter()
+            .map(|square| if square.parity() == useful_parity { 1.0 } else { 0.4 })
+            .sum();
+    }
+}
+
+impl Evaluator<Connect4State> for ThreatEvaluator {
+    fn evaluate(&self, state: &Connect4State) -> Vec<f64> {
+        let player_id = if state.player_1_turn() { 0 } else { 1 };
+        let sign = if player_id == 0 { 1.0 } else { -1.0 };
+        if !state.immediate_threats(player_id).is_empty() {
+            return zero_sum(sign * DECIDED_ESTIMATE);
+        }
+        if state.immediate_threats(1 - player_id).len() >= 2 {
+            return zero_sum(-sign * DECIDED_ESTIMATE);
+        }
+        let difference = ThreatEvaluator::score(state, 0) - ThreatEvaluator::score(state, 1);
+        return zero_sum((difference / 2.0).tanh() * DECIDED_ESTIMATE);
+    }
+}
+
+/// Weighs every piece by the number of lines of four through its square,
+/// which favors the center of the board.
+#[derive(Clone, Debug)]
+pub struct CenterControlEvaluator {
+    square_weights: Vec<(u64, f64)>,
+}
+
+impl CenterControlEvaluator {
+    pub fn new() -> Self {
+        let windows = windows();
+        let square_weights = (0..BOARD_WIDTH)
+            .flat_map(|column| (0..BOARD_HEIGHT).map(move |row| 1 << (column * (BOARD_HEIGHT + 1) + row)))
+            .map(|square| (square, windows.iter().filter(|&&window| window & square != 0).count() as f64))
+            .collect();
+        CenterControlEvaluator { square_weights }
+    }
+
+    fn score(&self, state: &Connect4State, player_id: usize) -> f64 {
+        return self.square_weights.iter()
+            .filter(|(square, _)| state.bit_board[player_id] & square != 0)
+            .map(|(_, weight)| weight)
+            .sum();
+    }
+}
+
+impl Default for CenterControlEvaluator {
+    fn default() -> Self {
+        CenterControlEvaluator::new()
+    }
+}
+
+impl Evaluator<Connect4State> for CenterControlEvaluator {
+    fn evaluate(&self, state: &Connect4State) -> Vec<f64> {
+        let difference = self.score(state, 0) - self.score(state, 1);
+        return zero_sum((difference / 64.0).tanh());
+    }
+}
+
+/// A learned weight for every feature of the `Connect4Encoder` encoding
+/// of a board plus a bias, estimating the first player's reward as the
+/// hyperbolic tangent of their weighted sum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearEvaluator {
+    weights: Vec<f64>,
+}
+
+impl LinearEvaluator {
+    /// The number of weights, one per encoded feature followed by the bias.
+    pub fn weight_count() -> usize {
+        Connect4Encoder.input_size() + 1
+    }
+
+    /// Creates an evaluator from learned weights.
+    ///
+    /// ### Return Value
+    ///
+    /// The evaluator, or an error if there is not exactly one weight
+    /// per encoded feature followed by the bias.
+    pub fn new(weights: Vec<f64>) -> Result<Self, String> {
+        if weights.len() != LinearEvaluator::weight_count() {
+            return Err(format!("expected {} weights, got {}", LinearEvaluator::weight_count(), weights.len()));
+        }
+        return Ok(LinearEvaluator { weights });
+    }
+
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    fn activation(&self, features: &[f64]) -> f64 {
+        let bias = self.weights[features.len()];
+        return features.iter().zip(&self.weights).map(|(feature, weight)| feature * weight).sum::<f64>() + bias;
+    }
+
+    /// Fits the weights to positions and the first player's rewards
+    /// from them by stochastic gradient descent on the squared error.
+    ///
+    /// ### Arguments
+    ///
+    /// * `examples` - Positions and the first player's reward from each.
+    /// * `learning_rate` - The step size of each update.
+    /// * `epochs` - The number of passes over the examples.
+    ///
+    /// ### Return Value
+    ///
+    /// The mean squared error over the examples after training.
+    pub fn train(&mut self, examples: &[(Connect4State, f64)], learning_rate: f64, epochs: usize) -> f64 {
+        let examples = examples.iter()
+            .map(|(state, reward)| (Connect4Encoder.encode(state), *reward))
+            .collect::<Vec<(Vec<f64>, f64)>>();
+        for _ in 0..epochs {
+            for (features, reward) in &examples {
+                let estimate = self.activation(features).tanh();
+                let gradient = (estimate - reward) * (1.0 - estimate * estimate);
+                for (weight, feature) in self.weights.iter_mut().zip(features.iter().chain([1.0].iter())) {
+                    *weight -= learning_rate * gradient * feature;
+                }
+            }
+        }
+        let error = examples.iter()
+            .map(|(features, reward)| (self.activation(features).tanh() - reward).powi(2))
+            .sum::<f64>();
+        return error / examples.len().max(1) as f64;
+    }
+}
+
+impl Default for LinearEvaluator {
+    fn default() -> Self {
+        LinearEvaluator { weights: vec![0.0; LinearEvaluator::weight_count()] }
+    }
+}
+
+impl Evaluator<Connect4State> for LinearEvaluator {
+    fn evaluate(&self, state: &Connect4State) -> Vec<f64> {
+        return zero_sum(self.activation(&Connect4Encoder.encode(state)).tanh());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::core::agent::Agent;
+    use crate::core::alphabeta::AlphaBetaAgent;
+    use crate::core::simulator::Simulator;
+    use crate::domains::connect4::connect4_action::Connect4Action;
+    use crate::domains::connect4::connect4_simulator::Connect4Simulator;
+
+    use super::*;
+
+    fn play(moves: &str) -> Connect4State {
+        let mut simulator = Connect4Simulator::new();
+        let mut state = simulator.generate_initial_state();
+        for (i, action) in moves.split_whitespace().enumerate() {
+            let mut actions = vec![None; 2];
+            actions[i % 2] = Some(Connect4Action::from_str(action).unwrap());
+            state = simulator.state_transition(&state, &actions);
+        }
+        return state;
+    }
+
+    fn assert_zero_sum(values: Vec<f64>) -> f64 {
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0], -values[1]);
+        assert!(values[0].abs() < 1.0);
+        return values[0];
+    }
+
+    #[test]
+    fn windows_cover_every_line() {
+        assert_eq!(windows().len(), 69);
+    }
+
+    #[test]
+    fn empty_board_is_even() {
+        let state = play("");
+        assert_eq!(assert_zero_sum(OpenLinesEvaluator::new().evaluate(&state)), 0.0);
+        assert_eq!(assert_zero_sum(ThreatEvaluator.evaluate(&state)), 0.0);
+        assert_eq!(assert_zero_sum(CenterControlEvaluator::new().evaluate(&state)), 0.0);
+        assert_eq!(assert_zero_sum(LinearEvaluator::default().evaluate(&state)), 0.0);
+    }
+
+    #[test]
+    fn center_pieces_are_better() {
+        let state = play("4 1");
+        assert!(assert_zero_sum(OpenLinesEvaluator::new().evaluate(&state)) > 0.0);
+        assert!(assert_zero_sum(CenterControlEvaluator::new().evaluate(&state)) > 0.0);
+        let state = play("1 4");
+        assert!(assert_zero_sum(OpenLinesEvaluator::new().evaluate(&state)) < 0.0);
+        assert!(assert_zero_sum(CenterControlEvaluator::new().evaluate(&state)) < 0.0);
+    }
+
+    #[test]
+    fn threats_decide_evaluation() {
+        // The first player's threat on the bottom row counts most.
+        assert!(assert_zero_sum(ThreatEvaluator.evaluate(&play("1 1 2 2 3"))) > 0.0);
+        // The first player can complete the bottom row on their turn.
+        assert_eq!(assert_zero_sum(ThreatEvaluator.evaluate(&play("1 1 2 2 3 7"))), DECIDED_ESTIMATE);
+        // The second player must block two squares at once.
+        assert_eq!(assert_zero_sum(ThreatEvaluator.evaluate(&play("2 7 3 7 4"))), DECIDED_ESTIMATE);
+        // The second player can complete the bottom row on their turn.
+        assert_eq!(assert_zero_sum(ThreatEvaluator.evaluate(&play("1 2 1 3 7 4 7"))), -DECIDED_ESTIMATE);
+    }
+
+    #[test]
+    fn linear_evaluator_checks_weights() {
+        assert!(LinearEvaluator::new(vec![0.0; 3]).is_err());
+        let weights = vec![0.5; LinearEvaluator::weight_count()];
+        assert_eq!(LinearEvaluator::new(weights.clone()).unwrap().weights(), weights.as_slice());
+    }
+
+    #[test]
+    fn linear_evaluator_fits_examples() {
+        let examples = vec![(play("4 1"), 0.5), (play("1 4"), -0.5), (play("4 4"), 0.0)];
+        let mut evaluator = LinearEvaluator::default();
+        let initial_error = evaluator.train(&examples, 0.0, 1);
+        let error = evaluator.train(&examples, 0.05, 200);
+        assert!(error < initial_error / 10.0, "error {} from {}", error, initial_error);
+        assert!(assert_zero_sum(evaluator.evaluate(&examples[0].0)) > 0.4);
+        assert!(assert_zero_sum(evaluator.evaluate(&examples[1].0)) < -0.4);
+    }
+
+    #[test]
+    fn alpha_beta_takes_and_blocks_wins() {
+        let mut simulator = Connect4Simulator::new();
+        let mut agent = AlphaBetaAgent::new(4, OpenLinesEvaluator::new(), 1 << 12);
+        let action = agent.select_action(0, &play("1 1 2 2 3 7"), &mut simulator);
+        assert_eq!(action, Connect4Action::drop(3));
+        let action = agent.select_action(1, &play("1 1 2 2 3"), &mut simulator);
+        assert_eq!(action, Connect4Action::drop(3));
+
+        let mut agent = AlphaBetaAgent::new(2, ThreatEvaluator, 1 << 12);
+        let action = agent.select_action(1, &play("1 7 2 7 3"), &mut simulator);
+        assert_eq!(action, Connect4Action::drop(3));
+    }
+}
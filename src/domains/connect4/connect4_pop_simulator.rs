@@ -0,0 +1,327 @@
+use crate::core::reward::{Reward, ADVERSARIAL_DRAW, ADVERSARIAL_P1_LOSS, ADVERSARIAL_P1_WIN};
+use crate::core::imperfect_info::ImperfectInfoSimulator;
+use crate::core::simulator::{Simulator, LegalActions};
+
+use super::connect4_action::{Connect4Action, Connect4ActionKind};
+use super::connect4_pop_state::{Connect4PopState, PopPhase};
+use super::connect4_state::{Connect4State, Square};
+use super::constants::{BOARD_HEIGHT, BOARD_MASK, BOARD_WIDTH, FIRST_COLUMN};
+
+use rand_chacha::ChaCha8Rng;
+
+const N_PLAYERS: usize = 2;
+
+/// The number of pieces a Pop 10 player collects to win.
+const POP_10_TARGET: u8 = 10;
+
+/// The number of times a position occurs before the game is drawn.
+const REPETITION_LIMIT: usize = 3;
+
+/// The Connect 4 variants in which pieces can be popped
+/// out of the bottom of a column, shifting the column down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PopVariant {
+    /// Players either drop a piece or pop one of their own pieces and
+    /// the first to get four in a row wins. If a pop gives both players
+    /// four in a row, the player who popped wins.
+    PopOut,
+    /// Players first fill the board, lowest row first, without lines
+    /// counting. Then they pop their own pieces: a piece that was part
+    /// of four in a row of their color is collected and they pop again,
+    /// otherwise they drop it back into any column that is not full and
+    /// the turn passes. A player who cannot pop passes, and the first
+    /// player to collect ten pieces wins.
+    Pop10,
+}
+
+/// Connect 4 in which pieces can be popped out of the bottom row.
+///
+/// In both variants, a position that occurs for the third time is a draw.
+#[derive(Clone, Debug)]
+pub struct Connect4PopSimulator {
+    variant: PopVariant,
+}
+
+impl Connect4PopSimulator {
+    pub fn new(variant: PopVariant) -> Self {
+        Connect4PopSimulator { variant }
+    }
+
+    pub fn variant(&self) -> PopVariant {
+        self.variant
+    }
+
+    /// The rewards of a finished game, or `None` if the game goes on.
+    fn outcome(&self, state: &Connect4PopState) -> Option<Vec<Reward>> {
+        let winner = match self.variant {
+            PopVariant::PopOut => {
+                let lines = [0, 1].map(|player_id| !state.board.line_squares(player_id).is_empty());
+                match lines {
+                    [true, true] => Some(1 - state.turn),
+                    [true, false] => Some(0),
+                    [false, true] => Some(1),
+                    [false, false] => None,
+                }
+            },
+            PopVariant::Pop10 => (0..N_PLAYERS).find(|&player_id| state.collected[player_id] >= POP_10_TARGET),
+        };
+        return match winner {
+            Some(0) => Some(ADVERSARIAL_P1_WIN.to_vec()),
+            Some(_) => Some(ADVERSARIAL_P1_LOSS.to_vec()),
+            None if state.repetitions() >= REPETITION_LIMIT => Some(ADVERSARIAL_DRAW.to_vec()),
+            None if actions(self.variant, state).is_empty() => Some(ADVERSARIAL_DRAW.to_vec()),
+            None => None,
+        };
+    }
+}
+
+impl Simulator<Connect4PopState, Connect4Action> for Connect4PopSimulator {
+    fn generate_initial_state(&mut self) -> Connect4PopState {
+        match self.variant {
+            PopVariant::PopOut => Connect4PopState::new(PopPhase::Move),
+            PopVariant::Pop10 => Connect4PopState::new(PopPhase::Setup),
+        }
+    }
+
+    fn calculate_rewards(&mut self, state: &Connect4PopState) -> Vec<Reward> {
+        return self.outcome(state).unwrap_or_else(|| ADVERSARIAL_DRAW.to_vec());
+    }
+
+    fn calculate_legal_actions(&mut self, state: &Connect4PopState) -> Vec<LegalActions<Connect4Action>> {
+        let mut legal_actions = vec![LegalActions::<Connect4Action>::new(); N_PLAYERS];
+        if self.outcome(state).is_none() {
+            actions(self.variant, state).into_iter().for_each(|action| legal_actions[state.turn].insert(action));
+        }
+        return legal_actions;
+    }
+
+    fn state_transition(&mut self, state: &Connect4PopState, actions: &Vec<Option<Connect4Action>>) -> Connect4PopState {
+        self.check_valid_state_transition(state, actions).unwrap();
+        let action = actions[state.turn].unwrap();
+        let column = action.location as usize;
+        let mut next = state.clone();
+        next.history.push(state.key());
+        match (state.phase, action.kind) {
+            (PopPhase::Setup, _) => {
+                next.board.bit_board[state.turn] |= drop_square(&state.board, column);
+                next.turn = 1 - state.turn;
+                next.history.clear();
+                if next.board.bit_board[0] | next.board.bit_board[1] == BOARD_MASK {
+                    next.phase = PopPhase::Move;
+                }
+            },
+            (_, Connect4ActionKind::Drop) => {
+                next.board.bit_board[state.turn] |= drop_square(&state.board, column);
+                next.turn = 1 - state.turn;
+                next.phase = PopPhase::Move;
+            },
+            (_, Connect4ActionKind::Pop) => {
+                next.board = popped(&state.board, column);
+                if self.variant == PopVariant::PopOut {
+                    next.turn = 1 - state.turn;
+                } else if state.board.line_squares(state.turn).contains(&Square { column: column as u8, row: 0 }) {
+                    next.collected[state.turn] += 1;
+                    next.history.clear();
+                } else {
+                    next.phase = PopPhase::Replace;
+                }
+            },
+        }
+        if self.variant == PopVariant::Pop10 && next.phase == PopPhase::Move && self::actions(self.variant, &next).is_empty() {
+            next.turn = 1 - next.turn;
+        }
+        return next;
+    }
+
+    fn number_of_players(&mut self) -> usize {
+        2
+    }
+}
+
+impl ImperfectInfoSimulator<Connect4PopState, Connect4Action, Connect4PopState> for Connect4PopSimulator {
+    fn observe(&mut self, state: &Connect4PopState, _player_id: usize) -> Connect4PopState {
+        state.clone()
+    }
+
+    fn sample_determinization(&mut self, observation: &Connect4PopState, _player_id: usize, _rng: &mut ChaCha8Rng) -> Connect4PopState {
+        observation.clone()
+    }
+}
+
+/// The actions the player to act may take, ignoring whether the game is over.
+fn actions(variant: PopVariant, state: &Connect4PopState) -> Vec<Connect4Action> {
+    let heights = (0..BOARD_WIDTH).map(|column| height(&state.board, column)).collect::<Vec<usize>>();
+    let lowest = heights.iter().copied().min().unwrap_or(0);
+    let drops = (0..BOARD_WIDTH)
+        .filter(|&column| match state.phase {
+            PopPhase::Setup => heights[column] == lowest && lowest < BOARD_HEIGHT,
+            PopPhase::Move => variant == PopVariant::PopOut && heights[column] < BOARD_HEIGHT,
+            PopPhase::Replace => heights[column] < BOARD_HEIGHT,
+        })
+        .map(|column| Connect4Action::drop(column as u8));
+    let pops = (0..BOARD_WIDTH)
+        .filter(|&column| state.phase == PopPhase::Move && state.board.bit_board[state.turn] & bottom_square(column) != 0)
+        .map(|column| Connect4Action::pop(column as u8));
+    return drops.chain(pops).collect();
+}
+
+fn column_mask(column: usize) -> u64 {
+    return (FIRST_COLUMN << (column * (BOARD_HEIGHT + 1))) & BOARD_MASK;
+}
+
+fn bottom_square(column: usize) -> u64 {
+    return 1 << (column * (BOARD_HEIGHT + 1));
+}
+
+/// The number of pieces in a column.
+fn height(board: &Connect4State, column: usize) -> usize {
+    return ((board.bit_board[0] | board.bit_board[1]) & column_mask(column)).count_ones() as usize;
+}
+
+/// The lowest empty square of a column, or zero if the column is full.
+fn drop_square(board: &Connect4State, column: usize) -> u64 {
+    let pieces = (board.bit_board[0] | board.bit_board[1]) & column_mask(column);
+    return (pieces + bottom_square(column)) & column_mask(column);
+}
+
+/// The board with the bottom piece of a column removed
+/// and the rest of the column shifted down.
+fn popped(board: &Connect4State, column: usize) -> Connect4State {
+    let mask = column_mask(column);
+    let bit_board = board.bit_board.map(|bit_board| {
+        let pieces = bit_board & mask & !bottom_square(column);
+        (bit_board & !mask) | (pieces >> 1)
+    });
+    return Connect4State { bit_board };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::simulator_conformance_tests!(pop_out_conformance, |_rng| Connect4PopSimulator::new(PopVariant::PopOut));
+    crate::simulator_conformance_tests!(pop_10_conformance, |_rng| Connect4PopSimulator::new(PopVariant::Pop10));
+
+    fn play(simulator: &mut Connect4PopSimulator, state: Connect4PopState, moves: &str) -> Connect4PopState {
+        let mut state = state;
+        for action in moves.split_whitespace() {
+            let mut actions = vec![None; N_PLAYERS];
+            actions[state.turn] = Some(action.parse().unwrap());
+            state = simulator.state_transition(&state, &actions);
+        }
+        return state;
+    }
+
+    fn parse(board: &str, status: &str) -> Connect4PopState {
+        return format!("{}\n{}\nhistory:", board, status).parse().unwrap();
+    }
+
+    fn legal_actions(simulator: &mut Connect4PopSimulator, state: &Connect4PopState) -> Vec<String> {
+        let mut actions = simulator.calculate_legal_actions(state)[state.turn].iter()
+            .map(|action| action.to_string())
+            .collect::<Vec<String>>();
+        actions.sort();
+        return actions;
+    }
+
+    #[test]
+    fn pop_out_pops_own_pieces_only() {
+        let mut simulator = Connect4PopSimulator::new(PopVariant::PopOut);
+        let state = simulator.generate_initial_state();
+        let state = play(&mut simulator, state, "4 4");
+        assert_eq!(legal_actions(&mut simulator, &state), ["1", "2", "3", "4", "5", "6", "7", "p4"]);
+        let state = play(&mut simulator, state, "p4");
+        assert_eq!(state.board.to_string(), "-------\n-------\n-------\n-------\n-------\n---O---");
+        assert_eq!(state.turn, 1);
+        assert_eq!(legal_actions(&mut simulator, &state), ["1", "2", "3", "4", "5", "6", "7", "p4"]);
+    }
+
+    #[test]
+    fn pop_out_pop_completing_both_lines_wins_for_popper() {
+        let mut simulator = Connect4PopSimulator::new(PopVariant::PopOut);
+        let board = "-------\n-------\n-------\nX------\nOXXX---\nXOOO---";
+        let state = parse(board, "X to move, collected 0 0");
+        assert!(!simulator.is_terminal_state(&state));
+        let next = play(&mut simulator, state.clone(), "p1");
+        assert_eq!(simulator.calculate_rewards(&next), ADVERSARIAL_P1_WIN.to_vec());
+        assert!(simulator.is_terminal_state(&next));
+
+        let mut state = state;
+        state.board = state.board.mirrored();
+        state.board.bit_board.swap(0, 1);
+        state.turn = 1;
+        let next = play(&mut simulator, state, "p7");
+        assert_eq!(simulator.calculate_rewards(&next), ADVERSARIAL_P1_LOSS.to_vec());
+    }
+
+    #[test]
+    fn pop_out_third_repetition_draws() {
+        let mut simulator = Connect4PopSimulator::new(PopVariant::PopOut);
+        let state = simulator.generate_initial_state();
+        let state = play(&mut simulator, state, "1 2 p1 p2");
+        assert_eq!(state.repetitions(), 2);
+        assert!(!simulator.is_terminal_state(&state));
+        let state = play(&mut simulator, state, "1 2 p1 p2");
+        assert_eq!(state.repetitions(), 3);
+        assert!(simulator.is_terminal_state(&state));
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_DRAW.to_vec());
+    }
+
+    #[test]
+    fn pop_10_setup_fills_lowest_row_first() {
+        let mut simulator = Connect4PopSimulator::new(PopVariant::Pop10);
+        let state = simulator.generate_initial_state();
+        assert_eq!(state.phase, PopPhase::Setup);
+        let state = play(&mut simulator, state, "4");
+        assert_eq!(legal_actions(&mut simulator, &state), ["1", "2", "3", "5", "6", "7"]);
+        let moves = (0..BOARD_HEIGHT).map(|_| "1 2 3 4 5 6 7").collect::<Vec<&str>>().join(" ");
+        let initial_state = simulator.generate_initial_state();
+        let state = play(&mut simulator, initial_state, &moves);
+        assert_eq!(state.phase, PopPhase::Move);
+        assert_eq!(state.board.bit_board[0] | state.board.bit_board[1], BOARD_MASK);
+        assert!(!state.board.line_squares(0).is_empty());
+        assert!(!simulator.is_terminal_state(&state));
+        assert_eq!(legal_actions(&mut simulator, &state), ["p1", "p3", "p5", "p7"]);
+    }
+
+    #[test]
+    fn pop_10_collects_pieces_from_lines() {
+        let mut simulator = Connect4PopSimulator::new(PopVariant::Pop10);
+        let board = "-------\n-------\n-------\n-------\nOOO-O--\nXXXXO--";
+        let state = parse(board, "X to move, collected 0 0");
+        let state = play(&mut simulator, state, "p1");
+        assert_eq!((state.turn, state.phase, state.collected), (0, PopPhase::Move, [1, 0]));
+        assert_eq!(state.board.to_string(), "-------\n-------\n-------\n-------\n-OO-O--\nOXXXO--");
+
+        let state = play(&mut simulator, state, "p2");
+        assert_eq!((state.turn, state.phase, state.collected), (0, PopPhase::Replace, [1, 0]));
+        assert_eq!(legal_actions(&mut simulator, &state), ["1", "2", "3", "4", "5", "6", "7"]);
+        let state = play(&mut simulator, state, "4");
+        assert_eq!((state.turn, state.phase), (1, PopPhase::Move));
+    }
+
+    #[test]
+    fn pop_10_player_who_cannot_pop_passes() {
+        let mut simulator = Connect4PopSimulator::new(PopVariant::Pop10);
+        let board = "-------\n-------\n-------\n-------\nO------\nXO-----";
+        let state = parse(board, "X to move, collected 0 0");
+        let state = play(&mut simulator, state, "p1 2");
+        assert_eq!(state.board.to_string(), "-------\n-------\n-------\n-------\n-X-----\nOO-----");
+        assert_eq!(state.turn, 1);
+        let state = play(&mut simulator, state, "p1 1");
+        assert_eq!(state.board.to_string(), "-------\n-------\n-------\n-------\n-X-----\nOO-----");
+        assert_eq!((state.turn, state.repetitions()), (1, 2));
+    }
+
+    #[test]
+    fn pop_10_tenth_collected_piece_wins() {
+        let mut simulator = Connect4PopSimulator::new(PopVariant::Pop10);
+        let board = "-------\n-------\n-------\n-------\n-------\nOOOO-X-";
+        let state = parse(board, "O to move, collected 3 9");
+        assert!(!simulator.is_terminal_state(&state));
+        let state = play(&mut simulator, state, "p2");
+        assert_eq!(state.collected, [3, 10]);
+        assert!(simulator.is_terminal_state(&state));
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_P1_LOSS.to_vec());
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::{Display, self};
+use std::str::FromStr;
+
+use crate::core::imperfect_info::Observation;
+use crate::core::simulator::State;
+
+use super::connect4_state::Connect4State;
+use super::constants::BOARD_HEIGHT;
+
+/// Which part of a turn the player to act is taking.
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+pub enum PopPhase {
+    /// Pop 10 players fill the board before playing, lowest row first.
+    Setup,
+    /// The player drops or pops a piece.
+    Move,
+    /// A Pop 10 player drops the piece they popped without collecting it.
+    Replace,
+}
+
+impl PopPhase {
+    fn name(&self) -> &'static str {
+        match self {
+            PopPhase::Setup => "set up",
+            PopPhase::Move => "move",
+            PopPhase::Replace => "replace",
+        }
+    }
+}
+
+/// Connect 4 board state for the Pop variants.
+///
+/// Unlike standard Connect 4, pieces can be removed, so the player to act
+/// is stored rather than counted from the pieces, and the positions since
+/// the last irreversible move are kept to detect repetitions.
+#[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+pub struct Connect4PopState {
+    pub board: Connect4State,
+    /// The ID of the player to act.
+    pub turn: usize,
+    pub phase: PopPhase,
+    /// The number of pieces each player has collected in Pop 10.
+    pub collected: [u8; 2],
+    /// The keys of the earlier positions that this position may repeat, oldest first.
+    pub history: Vec<u64>,
+}
+
+impl Connect4PopState {
+    /// An empty board with the first player to act.
+    pub fn new(phase: PopPhase) -> Self {
+        Connect4PopState {
+            board: Connect4State { bit_board: [0, 0] },
+            turn: 0,
+            phase,
+            collected: [0, 0],
+            history: Vec::new(),
+        }
+    }
+
+    /// A number that uniquely identifies the position, including the
+    /// player to act and the phase but not the history.
+    pub fn key(&self) -> u64 {
+        return self.board.key() | ((self.turn as u64) << 62) | ((self.phase as u64) << 60);
+    }
+
+    /// The number of times the position has occurred, including now.
+    pub fn repetitions(&self) -> usize {
+        let key = self.key();
+        return 1 + self.history.iter().filter(|&&previous| previous == key).count();
+    }
+}
+
+impl State for Connect4PopState {}
+
+/// The board is fully visible to every player.
+impl Observation for Connect4PopState {}
+
+/// Writes the board followed by the player to act and
+/// the collected pieces, then the history keys in hex.
+impl Display for Connect4PopState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.board)?;
+        let player = if self.turn == 0 { 'X' } else { 'O' };
+        writeln!(f, "{} to {}, collected {} {}", player, self.phase.name(), self.collected[0], self.collected[1])?;
+        write!(f, "history:")?;
+        for key in &self.history {
+            write!(f, " {:x}", key)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Connect4PopState {
+    type Err = String;
+
+    /// Parses a state in the format written by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().collect::<Vec<&str>>();
+        if lines.len() != BOARD_HEIGHT + 2 {
+            return Err(format!("expected {} lines, found {}", BOARD_HEIGHT + 2, lines.len()));
+        }
+        let board = lines[..BOARD_HEIGHT].join("\n").parse::<Connect4State>()?;
+
+        let status = lines[BOARD_HEIGHT];
+        let invalid_status = || format!("invalid status: {}", status);
+        let (turn, collected) = status.split_once(", collected ").ok_or_else(invalid_status)?;
+        let turn = match turn.split_once(" to ") {
+            Some(("X", phase)) => (0, phase),
+            Some(("O", phase)) => (1, phase),
+            _ => return Err(invalid_status()),
+        };
+        let phase = [PopPhase::Setup, PopPhase::Move, PopPhase::Replace].into_iter()
+            .find(|phase| phase.name() == turn.1)
+            .ok_or_else(invalid_status)?;
+        let collected = collected.split(' ')
+            .map(|count| count.parse::<u8>().map_err(|_| invalid_status()))
+            .collect::<Result<Vec<u8>, String>>()?;
+        let collected: [u8; 2] = collected.try_into().map_err(|_| invalid_status())?;
+
+        let history = lines[BOARD_HEIGHT + 1].strip_prefix("history:")
+            .ok_or_else(|| format!("invalid history: {}", lines[BOARD_HEIGHT + 1]))?
+            .split_whitespace()
+            .map(|key| u64::from_str_radix(key, 16).map_err(|_| format!("invalid history key: {}", key)))
+            .collect::<Result<Vec<u64>, String>>()?;
+        return Ok(Connect4PopState { board, turn: turn.0, phase, collected, history });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_string_and_from_str() {
+        let mut state = Connect4PopState::new(PopPhase::Replace);
+        state.board.bit_board = [0b1, 0b10];
+        state.turn = 1;
+        state.collected = [2, 3];
+        state.history = vec![0x1f, 0xa0];
+        let expected = "-------\n\
+                        -------\n\
+                        -------\n\
+                        -------\n\
+                        O------\n\
+                        X------\n\
+                        O to replace, collected 2 3\n\
+                        history: 1f a0";
+        assert_eq!(state.to_string(), expected);
+        assert_eq!(expected.parse::<Connect4PopState>(), Ok(state));
+    }
+
+    #[test]
+    fn from_str_invalid_status() {
+        let board = "-------\n".repeat(BOARD_HEIGHT);
+        assert!(format!("{}X to move, collected 0 0\nhistory:", board).parse::<Connect4PopState>().is_ok());
+        assert!(format!("{}Y to move, collected 0 0\nhistory:", board).parse::<Connect4PopState>().is_err());
+        assert!(format!("{}X to jump, collected 0 0\nhistory:", board).parse::<Connect4PopState>().is_err());
+        assert!(format!("{}X to move, collected 0\nhistory:", board).parse::<Connect4PopState>().is_err());
+        assert!(format!("{}X to move, collected 0 0\nhistory: g", board).parse::<Connect4PopState>().is_err());
+    }
+
+    #[test]
+    fn repetitions_count_history() {
+        let mut state = Connect4PopState::new(PopPhase::Move);
+        assert_eq!(state.repetitions(), 1);
+        let mut other = state.clone();
+        other.turn = 1;
+        state.history = vec![state.key(), other.key(), state.key()];
+        assert_eq!(state.repetitions(), 3);
+    }
+}
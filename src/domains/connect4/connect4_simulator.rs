@@ -1,24 +1,30 @@
 use crate::core::reward::{Reward, ADVERSARIAL_DRAW, ADVERSARIAL_P1_LOSS, ADVERSARIAL_P1_WIN, DRAW};
+use crate::core::imperfect_info::ImperfectInfoSimulator;
 use crate::core::simulator::{Simulator, LegalActions};
 
-use super::constants::{BOARD_WIDTH, BOARD_HEIGHT};
+use super::constants::{ABOVE_TOP_ROW, BOARD_WIDTH, BOARD_HEIGHT};
 use super::connect4_state::Connect4State;
 use super::connect4_action::Connect4Action;
 
+use rand_chacha::ChaCha8Rng;
+
 use std::collections::HashMap;
         
 const N_PLAYERS: usize = 2;
-const ALL_LOCATIONS: u64 = (1 << (BOARD_HEIGHT + 1) * BOARD_WIDTH) - 1;
-const FIRST_COLUMN: u64 = (1 << BOARD_HEIGHT + 1) - 1;
-const BOTTOM_ROW: u64 = ALL_LOCATIONS / FIRST_COLUMN;
-const ABOVE_TOP_ROW: u64 = BOTTOM_ROW << BOARD_HEIGHT;
 
+#[derive(Clone)]
 pub struct Connect4Simulator {
     column_heights_cache: HashMap<Connect4State, [u8; BOARD_WIDTH]>,
     rewards_cache: HashMap<Connect4State, Vec<Reward>>,
     legal_actions_cache: HashMap<Connect4State, Vec<LegalActions<Connect4Action>>>,
 }
 
+impl Default for Connect4Simulator {
+    fn default() -> Self {
+        Connect4Simulator::new()
+    }
+}
+
 impl Connect4Simulator {
     pub fn new() -> Self {
         Connect4Simulator {
@@ -30,7 +36,7 @@ impl Connect4Simulator {
 
     fn calculate_column_heights(&mut self, state: &Connect4State) -> [u8; BOARD_WIDTH] {
         let column_heights = match self.column_heights_cache.get(state) {
-            Some(column_heights) => column_heights.clone(),
+            Some(column_heights) => *column_heights,
             None => calculate_column_heights(state),
         };
         self.column_heights_cache.clear();
@@ -70,10 +76,9 @@ impl Simulator<Connect4State, Connect4Action> for Connect4Simulator {
         let mut state = state.clone();
         let agent_turn: usize = if state.player_1_turn() { 0 } else { 1 };
         let action = actions[agent_turn].unwrap();
-        let mut column_heights = self.calculate_column_heights(&state);
-        self.check_valid_state_transition(&state, &actions).unwrap();
-        column_heights[action.location as usize] += 1;
-        state.bit_board[agent_turn] = state.bit_board[agent_turn] ^ (1 << column_heights[action.location as usize]);
+        let column_heights = self.calculate_column_heights(&state);
+        self.check_valid_state_transition(&state, actions).unwrap();
+        state.bit_board[agent_turn] |= 1 << column_heights[action.location as usize];
         return state;
     }
     
@@ -82,6 +87,16 @@ impl Simulator<Connect4State, Connect4Action> for Connect4Simulator {
     }
 }
 
+impl ImperfectInfoSimulator<Connect4State, Connect4Action, Connect4State> for Connect4Simulator {
+    fn observe(&mut self, state: &Connect4State, _player_id: usize) -> Connect4State {
+        state.clone()
+    }
+
+    fn sample_determinization(&mut self, observation: &Connect4State, _player_id: usize, _rng: &mut ChaCha8Rng) -> Connect4State {
+        observation.clone()
+    }
+}
+
 fn calculate_legal_actions(
     state: &Connect4State,
     rewards: &Vec<Reward>,
@@ -92,25 +107,17 @@ fn calculate_legal_actions(
         let agent_turn = if state.player_1_turn() { 0 } else { 1 };
         (0..BOARD_WIDTH)
             .filter(|&x| { 1 << column_heights[x] & ABOVE_TOP_ROW == 0 })
-            .for_each(|x| { legal_actions[agent_turn].insert(Connect4Action { location: x as u8 }); });
+            .for_each(|x| { legal_actions[agent_turn].insert(Connect4Action::drop(x as u8)); });
     }
     return legal_actions;
 }
 
 fn calculate_rewards(state: &Connect4State) -> Vec<Reward> {
-    let height = BOARD_HEIGHT;
-    for i in 0..N_PLAYERS {
-        let bit_board = state.bit_board[1];
-        let diagonal1 = bit_board & (bit_board >> height);
-        let horizontal = bit_board & (bit_board >> height + 1);
-        let diagonal2 = bit_board & (bit_board >> height + 2);
-        let vertical = bit_board & (bit_board >> 1);
-        if diagonal1 & (diagonal1 >> 2 * height)| 
-            (horizontal & (horizontal >> 2 * (height + 1))) | 
-            (diagonal2 & (diagonal2 >> 2 * (height + 2))) |
-            (vertical & (vertical >> 2)) != 0 {
-            return if i == 0 { ADVERSARIAL_P1_WIN.to_vec() } else { ADVERSARIAL_P1_LOSS.to_vec() };
-        }
+    if state.has_line(0) {
+        return ADVERSARIAL_P1_WIN.to_vec();
+    }
+    if state.has_line(1) {
+        return ADVERSARIAL_P1_LOSS.to_vec();
     }
     return ADVERSARIAL_DRAW.to_vec();
 }
@@ -126,3 +133,127 @@ fn calculate_column_heights(state: &Connect4State) -> [u8; BOARD_WIDTH] {
     }
     return column_heights;
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::core::perft::{perft, perft_divide};
+    use crate::domains::connect4::constants::{ALL_LOCATIONS, BOTTOM_ROW};
+    use crate::domains::connect4::util::count_ones;
+
+    use super::*;
+
+    /// Leaf node counts from the initial position by depth.
+    const INITIAL_PERFT: [u64; 8] = [1, 7, 49, 343, 2401, 16807, 117649, 823536];
+
+    /// The deepest `INITIAL_PERFT` count checked by the default test run.
+    const FAST_PERFT_DEPTH: usize = 6;
+
+    crate::simulator_conformance_tests!(conformance, |_rng| Connect4Simulator::new());
+
+    fn play(simulator: &mut Connect4Simulator, moves: &str) -> Connect4State {
+        let mut state = simulator.generate_initial_state();
+        for (i, action) in moves.split_whitespace().enumerate() {
+            let mut actions = vec![None; N_PLAYERS];
+            actions[i % N_PLAYERS] = Some(action.parse().unwrap());
+            state = simulator.state_transition(&state, &actions);
+        }
+        return state;
+    }
+
+    #[test]
+    fn perft_initial_state() {
+        let mut simulator = Connect4Simulator::new();
+        let state = simulator.generate_initial_state();
+        for (depth, &expected) in INITIAL_PERFT.iter().enumerate().take(FAST_PERFT_DEPTH + 1) {
+            assert_eq!(perft(&mut simulator, &state, depth), expected, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    #[ignore = "slow; run with --ignored"]
+    fn perft_initial_state_deep() {
+        let mut simulator = Connect4Simulator::new();
+        let state = simulator.generate_initial_state();
+        for (depth, &expected) in INITIAL_PERFT.iter().enumerate().skip(FAST_PERFT_DEPTH + 1) {
+            assert_eq!(perft(&mut simulator, &state, depth), expected, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn perft_divide_with_threats() {
+        let mut simulator = Connect4Simulator::new();
+        let state = play(&mut simulator, "4 4 5 5 3");
+        let divide = perft_divide(&mut simulator, &state, 4).into_iter()
+            .map(|(actions, nodes)| (actions[1].unwrap().to_string(), nodes))
+            .collect::<Vec<(String, u64)>>();
+        let expected = [("1", 245), ("2", 294), ("3", 245), ("4", 245), ("5", 245), ("6", 294), ("7", 245)]
+            .map(|(action, nodes)| (action.to_string(), nodes));
+        assert_eq!(divide, expected);
+    }
+
+    #[test]
+    fn calculate_rewards_player_1_wins() {
+        let mut simulator = Connect4Simulator::new();
+        let state = play(&mut simulator, "1 2 1 2 1 2 1");
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_P1_WIN.to_vec());
+        assert!(simulator.is_terminal_state(&state));
+    }
+
+    #[test]
+    fn calculate_rewards_player_2_wins() {
+        let mut simulator = Connect4Simulator::new();
+        let state = play(&mut simulator, "1 2 3 2 3 2 3 2");
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_P1_LOSS.to_vec());
+    }
+
+    #[test]
+    fn state_transition_drops_to_lowest_empty_slot() {
+        let mut simulator = Connect4Simulator::new();
+        let state = play(&mut simulator, "1 1");
+        assert_eq!(state.bit_board, [0b01, 0b10]);
+    }
+
+    /// Checks that pieces do not overlap, stay on the board, rest on top of
+    /// each other and that players alternate turns.
+    fn check_board(state: &Connect4State) -> Result<(), TestCaseError> {
+        let [player_1, player_2] = state.bit_board;
+        prop_assert_eq!(player_1 & player_2, 0);
+        prop_assert_eq!((player_1 | player_2) & !(ALL_LOCATIONS ^ ABOVE_TOP_ROW), 0);
+        let pieces = [count_ones(player_1), count_ones(player_2)];
+        prop_assert!(pieces[0] == pieces[1] || pieces[0] == pieces[1] + 1);
+        let occupied = player_1 | player_2;
+        prop_assert_eq!(occupied & !BOTTOM_ROW & !(occupied << 1), 0, "floating piece:\n{}", state);
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_actions_preserve_board(moves in prop::collection::vec((0..N_PLAYERS, 0..BOARD_WIDTH as u8 + 2), 0..100)) {
+            let mut simulator = Connect4Simulator::new();
+            let mut state = simulator.generate_initial_state();
+            let mut pieces = 0;
+            for (player_id, location) in moves {
+                let mut actions = vec![None; N_PLAYERS];
+                actions[player_id] = Some(Connect4Action::drop(location));
+                let turn = if state.player_1_turn() { 0 } else { 1 };
+                let top_slot = (location as usize * (BOARD_HEIGHT + 1)) + BOARD_HEIGHT - 1;
+                let is_legal = player_id == turn &&
+                    (location as usize) < BOARD_WIDTH &&
+                    (state.bit_board[0] | state.bit_board[1]) & (1 << top_slot) == 0 &&
+                    simulator.calculate_rewards(&state)[0] == DRAW;
+                match simulator.try_state_transition(&state, &actions) {
+                    Ok(next_state) => {
+                        prop_assert!(is_legal, "illegal action {} accepted for player {}:\n{}", location + 1, player_id, state);
+                        state = next_state;
+                        pieces += 1;
+                    },
+                    Err(_) => prop_assert!(!is_legal, "legal action {} rejected for player {}:\n{}", location + 1, player_id, state),
+                }
+                check_board(&state)?;
+                prop_assert_eq!(count_ones(state.bit_board[0]) + count_ones(state.bit_board[1]), pieces);
+            }
+        }
+    }
+}
@@ -1,10 +1,63 @@
 use std::fmt::{Display, self};
+use std::str::FromStr;
 
+use crate::core::imperfect_info::Observation;
 use crate::core::simulator::State;
+use crate::core::symmetry::Symmetric;
+use crate::core::tt::{zobrist_key, ZobristHash};
 
-use super::constants::{BOARD_HEIGHT, BOARD_WIDTH};
+use super::connect4_action::{Connect4Action, Connect4ActionKind};
+use super::constants::{BOARD_HEIGHT, BOARD_MASK, BOARD_WIDTH, BOTTOM_ROW, FIRST_COLUMN};
 use super::util::count_ones;
 
+/// Shifts between neighboring squares of a line: vertical, diagonal
+/// down to the right, horizontal and diagonal up to the right.
+const DIRECTIONS: [usize; 4] = [1, BOARD_HEIGHT, BOARD_HEIGHT + 1, BOARD_HEIGHT + 2];
+
+const BOARD_BITS: usize = (BOARD_HEIGHT + 1) * BOARD_WIDTH;
+
+/// The Zobrist key of each player's piece on each bit of the bit board.
+const ZOBRIST_KEYS: [[u64; BOARD_BITS]; 2] = {
+    let mut keys = [[0; BOARD_BITS]; 2];
+    let mut i = 0;
+    while i < 2 * BOARD_BITS {
+        keys[i / BOARD_BITS][i % BOARD_BITS] = zobrist_key(i as u64);
+        i += 1;
+    }
+    keys
+};
+
+/// A square on the board, with columns numbered from the left
+/// and rows numbered from the bottom, both starting at zero.
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Square {
+    pub column: u8,
+    pub row: u8,
+}
+
+/// Whether a threat is on an odd or even row counting from one at the bottom.
+///
+/// When the board fills up without either player being able to win
+/// elsewhere, the first player can usually only make use of odd threats
+/// and the second player of even threats.
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+pub enum ThreatParity {
+    Odd,
+    Even,
+}
+
+impl Square {
+    fn from_bit(bit: u32) -> Self {
+        let bit = bit as usize;
+        Square { column: (bit / (BOARD_HEIGHT + 1)) as u8, row: (bit % (BOARD_HEIGHT + 1)) as u8 }
+    }
+
+    /// The parity of a threat on this square.
+    pub fn parity(&self) -> ThreatParity {
+        if self.row.is_multiple_of(2) { ThreatParity::Odd } else { ThreatParity::Even }
+    }
+}
+
 /// Connect 4 board state.
 /// 
 /// State is represented by a bit board described below:
@@ -24,35 +77,255 @@ impl Connect4State {
     pub fn player_1_turn(&self) -> bool {
         return count_ones(self.bit_board[0]) <= count_ones(self.bit_board[1]);
     }
+
+    /// The board reflected left to right.
+    pub fn mirrored(&self) -> Self {
+        let bit_board = self.bit_board.map(|bit_board| {
+            (0..BOARD_WIDTH).fold(0, |mirrored, column| {
+                let pieces = (bit_board >> (column * (BOARD_HEIGHT + 1))) & FIRST_COLUMN;
+                mirrored | (pieces << ((BOARD_WIDTH - 1 - column) * (BOARD_HEIGHT + 1)))
+            })
+        });
+        return Connect4State { bit_board };
+    }
+
+    /// A number that uniquely identifies the position, the first
+    /// player's pieces plus one bit above the pieces of every column.
+    pub fn key(&self) -> u64 {
+        return self.bit_board[0] + (self.bit_board[0] | self.bit_board[1]) + BOTTOM_ROW;
+    }
+
+    /// Whether a player has four in a row.
+    pub fn has_line(&self, player_id: usize) -> bool {
+        return DIRECTIONS.iter().any(|&shift| lines(self.bit_board[player_id], shift) != 0);
+    }
+
+    /// The first four in a row found for either player.
+    ///
+    /// ### Return Value
+    ///
+    /// The four squares of the line in order, or `None` if neither player has won.
+    pub fn winning_line(&self) -> Option<[Square; 4]> {
+        for bit_board in self.bit_board {
+            for shift in DIRECTIONS {
+                let starts = lines(bit_board, shift);
+                if starts != 0 {
+                    let start = starts.trailing_zeros();
+                    return Some([0, 1, 2, 3].map(|i| Square::from_bit(start + i * shift as u32)));
+                }
+            }
+        }
+        return None;
+    }
+
+    /// Every square of a player's pieces that is part of four in a row.
+    ///
+    /// ### Return Value
+    ///
+    /// The squares ordered by column and then row.
+    pub fn line_squares(&self, player_id: usize) -> Vec<Square> {
+        return squares(lines_mask(self.bit_board[player_id]));
+    }
+
+    /// The empty squares where a player's piece would complete four in a
+    /// row, whether or not they can be played yet.
+    ///
+    /// ### Return Value
+    ///
+    /// The squares ordered by column and then row.
+    pub fn threats(&self, player_id: usize) -> Vec<Square> {
+        return squares(self.threat_mask(player_id));
+    }
+
+    /// The threats that can be played on the current turn, which win the
+    /// game for the player to move or must be blocked by them otherwise.
+    pub fn immediate_threats(&self, player_id: usize) -> Vec<Square> {
+        return squares(self.threat_mask(player_id) & self.playable_mask());
+    }
+
+    /// Whether playing an action leaves the opponent without a winning
+    /// reply. Winning moves are safe, while moves that are illegal, that
+    /// play below an opponent's threat or that ignore an opponent's
+    /// immediate threat are not.
+    pub fn is_safe_move(&self, action: Connect4Action) -> bool {
+        let column = action.location as usize;
+        if column >= BOARD_WIDTH || action.kind != Connect4ActionKind::Drop || self.winning_line().is_some() {
+            return false;
+        }
+        let column_mask = BOARD_MASK & (FIRST_COLUMN << (column * (BOARD_HEIGHT + 1)));
+        let square = self.playable_mask() & column_mask;
+        if square == 0 {
+            return false;
+        }
+        let player_id = if self.player_1_turn() { 0 } else { 1 };
+        let mut next = self.clone();
+        next.bit_board[player_id] |= square;
+        return lines_mask(next.bit_board[player_id]) != 0 || next.immediate_threats(1 - player_id).is_empty();
+    }
+
+    fn threat_mask(&self, player_id: usize) -> u64 {
+        let bit_board = self.bit_board[player_id];
+        let mut threats = 0;
+        for shift in DIRECTIONS {
+            // An empty square completes a line if the three squares of
+            // a line through it on either side belong to the player.
+            for before in 0..4 {
+                let mut line = BOARD_MASK;
+                for i in 1..=before {
+                    line &= bit_board << (i * shift);
+                }
+                for i in 1..=3 - before {
+                    line &= bit_board >> (i * shift);
+                }
+                threats |= line;
+            }
+        }
+        return threats & BOARD_MASK & !(self.bit_board[0] | self.bit_board[1]);
+    }
+
+    /// The lowest empty square of every column that is not full.
+    fn playable_mask(&self) -> u64 {
+        return ((self.bit_board[0] | self.bit_board[1]) + BOTTOM_ROW) & BOARD_MASK;
+    }
+}
+
+/// The first square of every four in a row in a direction.
+fn lines(bit_board: u64, shift: usize) -> u64 {
+    let pairs = bit_board & (bit_board >> shift);
+    return pairs & (pairs >> (2 * shift));
+}
+
+/// Every square that is part of a four in a row.
+fn lines_mask(bit_board: u64) -> u64 {
+    return DIRECTIONS.iter()
+        .map(|&shift| {
+            let starts = lines(bit_board, shift);
+            (0..4).fold(0, |mask, i| mask | (starts << (i * shift)))
+        })
+        .fold(0, |mask, line| mask | line);
+}
+
+fn squares(mut mask: u64) -> Vec<Square> {
+    let mut squares = Vec::new();
+    while mask != 0 {
+        squares.push(Square::from_bit(mask.trailing_zeros()));
+        mask &= mask - 1;
+    }
+    return squares;
 }
 
 impl State for Connect4State {}
 
+/// The board is fully visible to every player.
+impl Observation for Connect4State {}
+
+impl ZobristHash for Connect4State {
+    fn zobrist_hash(&self) -> u64 {
+        return zobrist_pieces(self.bit_board);
+    }
+
+    fn updated_zobrist_hash(&self, hash: u64, next: &Self) -> u64 {
+        return hash ^ zobrist_pieces([self.bit_board[0] ^ next.bit_board[0], self.bit_board[1] ^ next.bit_board[1]]);
+    }
+}
+
+fn zobrist_pieces(bit_board: [u64; 2]) -> u64 {
+    let mut hash = 0;
+    for (player, mut pieces) in bit_board.into_iter().enumerate() {
+        while pieces != 0 {
+            hash ^= ZOBRIST_KEYS[player][pieces.trailing_zeros() as usize];
+            pieces &= pieces - 1;
+        }
+    }
+    return hash;
+}
+
+/// The board reflected left to right is symmetry 1.
+impl Symmetric<Connect4Action> for Connect4State {
+    fn symmetry_count(&self) -> usize {
+        2
+    }
+
+    fn transform(&self, symmetry: usize) -> Self {
+        match symmetry {
+            0 => self.clone(),
+            _ => self.mirrored(),
+        }
+    }
+
+    fn transform_action(&self, action: &Connect4Action, symmetry: usize) -> Connect4Action {
+        match symmetry {
+            0 => *action,
+            _ => Connect4Action { location: (BOARD_WIDTH - 1) as u8 - action.location, ..*action },
+        }
+    }
+}
+
+/// Pieces that are part of four in a row are written in lowercase.
 impl Display for Connect4State {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let winning = lines_mask(self.bit_board[0]) | lines_mask(self.bit_board[1]);
         for i in (0..BOARD_HEIGHT).rev() {
             let mut j = i;
             while j < (BOARD_HEIGHT + 1) * BOARD_WIDTH {
                 let mask = 1 << j;
                 if (self.bit_board[0] & mask) != 0 {
-                    write!(f, "X")?;
+                    write!(f, "{}", if winning & mask != 0 { 'x' } else { 'X' })?;
                 } else if (self.bit_board[1] & mask) != 0 {
-                    write!(f, "O")?;
+                    write!(f, "{}", if winning & mask != 0 { 'o' } else { 'O' })?;
                 } else {
                     write!(f, "-")?;
                 }
                 j += BOARD_HEIGHT + 1;
             }
             if i != 0 {
-                write!(f, "\n")?;
+                writeln!(f)?;
             }
         }
         Ok(())
     }
 }
 
+impl FromStr for Connect4State {
+    type Err = String;
+
+    /// Parses a board in the format written by `Display`,
+    /// in which pieces may be either case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s.lines().collect::<Vec<&str>>();
+        if rows.len() != BOARD_HEIGHT {
+            return Err(format!("expected {} rows, found {}", BOARD_HEIGHT, rows.len()));
+        }
+        let mut bit_board = [0u64; 2];
+        for (i, row) in rows.iter().enumerate() {
+            let cells = row.chars().collect::<Vec<char>>();
+            if cells.len() != BOARD_WIDTH {
+                return Err(format!("expected {} columns, found {}", BOARD_WIDTH, cells.len()));
+            }
+            let height = BOARD_HEIGHT - 1 - i;
+            for (column, cell) in cells.iter().enumerate() {
+                let mask = 1 << (column * (BOARD_HEIGHT + 1) + height);
+                match cell {
+                    'X' | 'x' => bit_board[0] |= mask,
+                    'O' | 'o' => bit_board[1] |= mask,
+                    '-' => {},
+                    _ => return Err(format!("invalid cell: {}", cell)),
+                }
+            }
+        }
+        return Ok(Connect4State { bit_board });
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::core::simulator::Simulator;
+    use crate::core::testing::{random_playout, DEFAULT_MAX_STEPS};
+    use crate::domains::connect4::connect4_simulator::Connect4Simulator;
+
     use super::*;
 
     #[test]
@@ -79,9 +352,173 @@ mod tests {
         assert_eq!(state.to_string(), expected);
     }
 
+    #[test]
+    fn connect4_state_from_str() {
+        let board = "-------\n\
+                     -------\n\
+                     -------\n\
+                     -------\n\
+                     ----X--\n\
+                     -O-XO--";
+        let expected = Connect4State { bit_board: [0b10_0000001_0000000_0000000_0000000, 0b1_0000000_0000000_0000001_0000000] };
+        assert_eq!(board.parse::<Connect4State>(), Ok(expected));
+    }
+
+    #[test]
+    fn connect4_state_from_str_invalid_cell() {
+        let board = "-------\n-------\n-------\n-------\n-------\n---Y---";
+        assert!(board.parse::<Connect4State>().is_err());
+    }
+
+    fn square(column: u8, row: u8) -> Square {
+        Square { column, row }
+    }
+
+    #[test]
+    fn winning_line_horizontal() {
+        let board = "-------\n-------\n-------\n-------\nOOO----\nXXXX---";
+        let state = board.parse::<Connect4State>().unwrap();
+        assert_eq!(state.winning_line(), Some([square(0, 0), square(1, 0), square(2, 0), square(3, 0)]));
+    }
+
+    #[test]
+    fn winning_line_vertical() {
+        let board = "-------\n-------\n-O-----\n-O-----\nXOX----\nXOX----";
+        let state = board.parse::<Connect4State>().unwrap();
+        assert_eq!(state.winning_line(), Some([square(1, 0), square(1, 1), square(1, 2), square(1, 3)]));
+    }
+
+    #[test]
+    fn winning_line_diagonal() {
+        let board = "-------\n-------\n---X---\n--XO---\n-XOO---\nXOOX---";
+        let state = board.parse::<Connect4State>().unwrap();
+        assert_eq!(state.winning_line(), Some([square(0, 0), square(1, 1), square(2, 2), square(3, 3)]));
+    }
+
+    #[test]
+    fn winning_line_none() {
+        let board = "-------\n-------\n-------\n-------\n-XXX---\nOOXO---";
+        assert_eq!(board.parse::<Connect4State>().unwrap().winning_line(), None);
+    }
+
+    #[test]
+    fn line_squares_of_each_player() {
+        let board = "-------\n-------\n-------\nO------\nOXXXX--\nOXOOOO-";
+        let state = board.parse::<Connect4State>().unwrap();
+        assert_eq!(state.line_squares(0), (1..5).map(|column| square(column, 1)).collect::<Vec<Square>>());
+        assert_eq!(state.line_squares(1), (2..6).map(|column| square(column, 0)).collect::<Vec<Square>>());
+    }
+
+    #[test]
+    fn to_string_highlights_winning_line() {
+        let board = "-------\n-------\n-------\n-------\nOOO----\nXXXX---";
+        let state = board.parse::<Connect4State>().unwrap();
+        let expected = "-------\n-------\n-------\n-------\nOOO----\nxxxx---";
+        assert_eq!(state.to_string(), expected);
+        assert_eq!(expected.parse::<Connect4State>(), Ok(state));
+    }
+
+    #[test]
+    fn threats_and_immediate_threats() {
+        let board = "-------\n-------\n-------\n-------\n-XXX---\nOOXO---";
+        let state = board.parse::<Connect4State>().unwrap();
+        assert_eq!(state.threats(0), vec![square(0, 1), square(4, 1)]);
+        assert_eq!(state.immediate_threats(0), vec![square(0, 1)]);
+        assert!(state.threats(1).is_empty());
+    }
+
+    #[test]
+    fn threat_parity() {
+        assert_eq!(square(4, 0).parity(), ThreatParity::Odd);
+        assert_eq!(square(4, 1).parity(), ThreatParity::Even);
+        assert_eq!(square(0, 5).parity(), ThreatParity::Even);
+    }
+
+    #[test]
+    fn is_safe_move_blocks_and_avoids_threats() {
+        let board = "-------\n-------\n-------\n-------\n-XXX---\nOOXO---";
+        let state = board.parse::<Connect4State>().unwrap();
+        assert!(state.is_safe_move(Connect4Action::drop(0)));
+        assert!(!state.is_safe_move(Connect4Action::drop(4)));
+        assert!(!state.is_safe_move(Connect4Action::drop(6)));
+        assert!(!state.is_safe_move(Connect4Action::drop(7)));
+    }
+
+    #[test]
+    fn is_safe_move_winning_move() {
+        let board = "-------\n-------\n-------\n-------\n-OOO---\n-XXX---";
+        let state = board.parse::<Connect4State>().unwrap();
+        assert!(state.is_safe_move(Connect4Action::drop(0)));
+        assert!(state.is_safe_move(Connect4Action::drop(4)));
+        assert!(state.is_safe_move(Connect4Action::drop(6)));
+    }
+
+    #[test]
+    fn is_safe_move_full_column() {
+        let board = "X------\nO------\nX------\nO------\nX------\nO------";
+        let state = board.parse::<Connect4State>().unwrap();
+        assert!(!state.is_safe_move(Connect4Action::drop(0)));
+        assert!(state.is_safe_move(Connect4Action::drop(1)));
+    }
+
+    #[test]
+    fn mirrored() {
+        let board = "-------\n-------\n-------\n-------\n----X--\n-O-XO--";
+        let mirrored = "-------\n-------\n-------\n-------\n--X----\n--OX-O-";
+        let state = board.parse::<Connect4State>().unwrap();
+        assert_eq!(state.mirrored().to_string(), mirrored);
+        assert_eq!(state.mirrored().mirrored(), state);
+    }
+
+    #[test]
+    fn symmetric_actions_play_the_same() {
+        let state = "-------\n-------\n-------\n-------\n----X--\n-O-XO--".parse::<Connect4State>().unwrap();
+        let mut simulator = Connect4Simulator::new();
+        for (variant, symmetry) in state.symmetries() {
+            for location in 0..BOARD_WIDTH as u8 {
+                let action = Connect4Action::drop(location);
+                let next = simulator.state_transition(&state, &vec![Some(action), None]);
+                let variant_action = state.transform_action(&action, symmetry);
+                let variant_next = simulator.state_transition(&variant, &vec![Some(variant_action), None]);
+                assert_eq!(next.transform(symmetry), variant_next);
+            }
+        }
+    }
+
+    #[test]
+    fn zobrist_hash_updates_incrementally() {
+        let mut simulator = Connect4Simulator::new();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let states = random_playout(&mut simulator, &mut rng, DEFAULT_MAX_STEPS).unwrap();
+        let mut hash = states[0].zobrist_hash();
+        assert_eq!(hash, 0);
+        for pair in states.windows(2) {
+            hash = pair[0].updated_zobrist_hash(hash, &pair[1]);
+            assert_eq!(hash, pair[1].zobrist_hash());
+        }
+    }
+
+    #[test]
+    fn zobrist_hash_of_transpositions() {
+        let a = "-------\n-------\n-------\n-------\n-------\nXO-X---".parse::<Connect4State>().unwrap();
+        let b = "-------\n-------\n-------\n-------\n-------\nX--XO--".parse::<Connect4State>().unwrap();
+        assert_eq!(a.zobrist_hash(), a.clone().zobrist_hash());
+        assert_ne!(a.zobrist_hash(), b.zobrist_hash());
+    }
+
+    #[test]
+    fn key_is_unique() {
+        let empty = Connect4State { bit_board: [0, 0] };
+        let x = "-------\n-------\n-------\n-------\n-------\nX------".parse::<Connect4State>().unwrap();
+        let o = "-------\n-------\n-------\n-------\n-------\nO------".parse::<Connect4State>().unwrap();
+        assert_eq!(empty.key(), BOTTOM_ROW);
+        assert_ne!(x.key(), o.key());
+        assert_ne!(x.key(), empty.key());
+    }
+
     #[test]
     fn player_1_turn_empty() {
         let state = Connect4State { bit_board: [0, 0] };
-        assert_eq!(state.player_1_turn(), true);
+        assert!(state.player_1_turn());
     }
 }
\ No newline at end of file
@@ -1,2 +1,9 @@
 pub const BOARD_WIDTH: usize = 7;
 pub const BOARD_HEIGHT: usize = 6;
+
+pub const ALL_LOCATIONS: u64 = (1 << ((BOARD_HEIGHT + 1) * BOARD_WIDTH)) - 1;
+pub const FIRST_COLUMN: u64 = (1 << (BOARD_HEIGHT + 1)) - 1;
+pub const BOTTOM_ROW: u64 = ALL_LOCATIONS / FIRST_COLUMN;
+pub const ABOVE_TOP_ROW: u64 = BOTTOM_ROW << BOARD_HEIGHT;
+/// Every square on the board, excluding the row above the top row.
+pub const BOARD_MASK: u64 = ALL_LOCATIONS ^ ABOVE_TOP_ROW;
@@ -5,7 +5,7 @@ pub fn count_ones(value: u64) -> u8 {
         if new_value & 1 == 1 {
             count += 1;
         }
-        new_value = new_value >> 1;
+        new_value >>= 1;
     }
     return count;
 }
@@ -0,0 +1,3 @@
+pub mod connectn_action;
+pub mod connectn_state;
+pub mod connectn_simulator;
@@ -0,0 +1,47 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::core::simulator::Action;
+
+/// Represents a column to drop a piece in.
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+pub struct ConnectNAction {
+    pub location: u8,
+}
+
+impl Action for ConnectNAction {}
+
+impl fmt::Display for ConnectNAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.location + 1)
+    }
+}
+
+impl FromStr for ConnectNAction {
+    type Err = String;
+
+    /// Parses a column numbered from 1. Whether the column is on
+    /// the board is checked by the simulator.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().parse::<u8>() {
+            Ok(slot) if slot >= 1 => Ok(ConnectNAction { location: slot - 1 }),
+            _ => Err(format!("invalid connect n action: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connectn_action_to_string() {
+        assert_eq!(ConnectNAction { location: 8 }.to_string(), "9");
+    }
+
+    #[test]
+    fn connectn_action_from_str() {
+        assert_eq!("12".parse::<ConnectNAction>(), Ok(ConnectNAction { location: 11 }));
+        assert!("0".parse::<ConnectNAction>().is_err());
+    }
+}
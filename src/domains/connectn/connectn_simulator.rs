@@ -0,0 +1,252 @@
+use crate::core::reward::{Reward, ADVERSARIAL_DRAW, ADVERSARIAL_P1_LOSS, ADVERSARIAL_P1_WIN};
+use crate::core::imperfect_info::ImperfectInfoSimulator;
+use crate::core::simulator::{Simulator, LegalActions};
+
+use super::connectn_state::{fits, has_line, ConnectNState};
+use super::connectn_action::ConnectNAction;
+
+use rand_chacha::ChaCha8Rng;
+
+const N_PLAYERS: usize = 2;
+
+/// Players take turns dropping pieces into the columns of a board of
+/// any width and height, and the first player to get `n` pieces in a
+/// row horizontally, vertically or diagonally wins.
+#[derive(Clone, Debug)]
+pub struct ConnectNSimulator {
+    width: usize,
+    height: usize,
+    n: usize,
+}
+
+impl ConnectNSimulator {
+    /// Creates a simulator for a board size and line length.
+    ///
+    /// ### Arguments
+    ///
+    /// * `width` - The number of columns.
+    /// * `height` - The number of rows.
+    /// * `n` - The number of pieces in a row needed to win.
+    ///
+    /// ### Return Value
+    ///
+    /// The simulator, or an error if `n` is zero or the board, with
+    /// a row above the top row, does not fit in 128 bits.
+    pub fn new(width: usize, height: usize, n: usize) -> Result<Self, String> {
+        if !fits(width, height) {
+            return Err(format!("a {}x{} board does not fit in a bit board", width, height));
+        }
+        if n == 0 {
+            return Err("n must be positive".to_string());
+        }
+        return Ok(ConnectNSimulator { width, height, n });
+    }
+
+    /// Standard Connect 4 on a 7x6 board.
+    pub fn connect4() -> Self {
+        ConnectNSimulator { width: 7, height: 6, n: 4 }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Will panic if a state does not have the simulator's board size,
+    /// since its bits would be laid out differently.
+    fn check_board_size(&self, state: &ConnectNState) {
+        assert_eq!(
+            (state.width as usize, state.height as usize),
+            (self.width, self.height),
+            "state board size does not match the simulator",
+        );
+    }
+
+    /// The bit of the lowest empty square in a column, which is in the
+    /// row above the top row if the column is full.
+    fn drop_location(&self, state: &ConnectNState, column: usize) -> u128 {
+        let column_mask = ((1u128 << self.height) - 1) << (column * (self.height + 1));
+        let pieces = ((state.bit_board[0] | state.bit_board[1]) & column_mask).count_ones() as usize;
+        return 1 << (column * (self.height + 1) + pieces);
+    }
+}
+
+impl Simulator<ConnectNState, ConnectNAction> for ConnectNSimulator {
+    fn generate_initial_state(&mut self) -> ConnectNState {
+        ConnectNState::new(self.width, self.height)
+    }
+
+    fn calculate_rewards(&mut self, state: &ConnectNState) -> Vec<Reward> {
+        self.check_board_size(state);
+        if has_line(state.bit_board[0], self.height, self.n) {
+            return ADVERSARIAL_P1_WIN.to_vec();
+        }
+        if has_line(state.bit_board[1], self.height, self.n) {
+            return ADVERSARIAL_P1_LOSS.to_vec();
+        }
+        return ADVERSARIAL_DRAW.to_vec();
+    }
+
+    fn calculate_legal_actions(&mut self, state: &ConnectNState) -> Vec<LegalActions<ConnectNAction>> {
+        self.check_board_size(state);
+        let mut legal_actions = vec![LegalActions::<ConnectNAction>::new(); N_PLAYERS];
+        if has_line(state.bit_board[0], self.height, self.n) || has_line(state.bit_board[1], self.height, self.n) {
+            return legal_actions;
+        }
+        let agent_turn = if state.player_1_turn() { 0 } else { 1 };
+        (0..self.width)
+            .filter(|&column| self.drop_location(state, column) != state.mask(column, self.height))
+            .for_each(|column| legal_actions[agent_turn].insert(ConnectNAction { location: column as u8 }));
+        return legal_actions;
+    }
+
+    fn state_transition(&mut self, state: &ConnectNState, actions: &Vec<Option<ConnectNAction>>) -> ConnectNState {
+        self.check_valid_state_transition(state, actions).unwrap();
+        let mut state = state.clone();
+        let agent_turn: usize = if state.player_1_turn() { 0 } else { 1 };
+        let action = actions[agent_turn].unwrap();
+        state.bit_board[agent_turn] |= self.drop_location(&state, action.location as usize);
+        return state;
+    }
+
+    fn number_of_players(&mut self) -> usize {
+        2
+    }
+}
+
+impl ImperfectInfoSimulator<ConnectNState, ConnectNAction, ConnectNState> for ConnectNSimulator {
+    fn observe(&mut self, state: &ConnectNState, _player_id: usize) -> ConnectNState {
+        state.clone()
+    }
+
+    fn sample_determinization(&mut self, observation: &ConnectNState, _player_id: usize, _rng: &mut ChaCha8Rng) -> ConnectNState {
+        observation.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::core::perft::perft;
+    use crate::core::testing::random_joint_action;
+    use crate::domains::connect4::connect4_simulator::Connect4Simulator;
+    use crate::domains::connect4::connect4_state::Connect4State;
+
+    use super::*;
+
+    crate::simulator_conformance_tests!(connect4_conformance, |_rng| ConnectNSimulator::connect4());
+    crate::simulator_conformance_tests!(connect3_conformance, |_rng| ConnectNSimulator::new(5, 4, 3).unwrap());
+    crate::simulator_conformance_tests!(connect5_conformance, |_rng| ConnectNSimulator::new(11, 9, 5).unwrap());
+
+    fn play(simulator: &mut ConnectNSimulator, moves: &str) -> ConnectNState {
+        let mut state = simulator.generate_initial_state();
+        for (i, action) in moves.split_whitespace().enumerate() {
+            let mut actions = vec![None; N_PLAYERS];
+            actions[i % N_PLAYERS] = Some(action.parse().unwrap());
+            state = simulator.state_transition(&state, &actions);
+        }
+        return state;
+    }
+
+    #[test]
+    fn new_rejects_boards_too_large() {
+        assert!(ConnectNSimulator::new(16, 7, 4).is_ok());
+        assert!(ConnectNSimulator::new(16, 8, 4).is_err());
+        assert!(ConnectNSimulator::new(0, 6, 4).is_err());
+        assert!(ConnectNSimulator::new(7, 6, 0).is_err());
+        assert!(ConnectNSimulator::new(1, 127, 4).is_ok());
+        assert!(ConnectNSimulator::new(1, 128, 4).is_err());
+    }
+
+    #[test]
+    fn tallest_boards_do_not_overflow() {
+        for height in [126, 127] {
+            let mut simulator = ConnectNSimulator::new(1, height, 4).unwrap();
+            let state = play(&mut simulator, "1 1 1 1 1 1 1");
+            assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_DRAW.to_vec());
+            assert!(!simulator.is_terminal_state(&state));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "state board size does not match the simulator")]
+    fn state_of_another_board_size_panics() {
+        let mut simulator = ConnectNSimulator::new(5, 4, 3).unwrap();
+        simulator.calculate_legal_actions(&ConnectNState::new(7, 6));
+    }
+
+    #[test]
+    fn perft_connect4_preset() {
+        let mut simulator = ConnectNSimulator::connect4();
+        let state = simulator.generate_initial_state();
+        for (depth, expected) in [1, 7, 49, 343, 2401, 16807, 117649].into_iter().enumerate() {
+            assert_eq!(perft(&mut simulator, &state, depth), expected, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn connect4_preset_matches_connect4() {
+        let mut simulator = ConnectNSimulator::connect4();
+        let mut connect4 = Connect4Simulator::new();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..50 {
+            let mut state = simulator.generate_initial_state();
+            loop {
+                let connect4_state = state.to_string().parse::<Connect4State>().unwrap();
+                assert_eq!(simulator.calculate_rewards(&state), connect4.calculate_rewards(&connect4_state), "\n{}", state);
+                let legal_actions = simulator.calculate_legal_actions(&state);
+                let connect4_legal_actions = connect4.calculate_legal_actions(&connect4_state);
+                for (legal_actions, connect4_legal_actions) in legal_actions.iter().zip(&connect4_legal_actions) {
+                    let mut locations = legal_actions.iter().map(|action| action.location).collect::<Vec<u8>>();
+                    let mut connect4_locations = connect4_legal_actions.iter().map(|action| action.location).collect::<Vec<u8>>();
+                    locations.sort();
+                    connect4_locations.sort();
+                    assert_eq!(locations, connect4_locations);
+                }
+                if simulator.is_terminal_state(&state) {
+                    break;
+                }
+                state = simulator.state_transition(&state, &random_joint_action(&legal_actions, &mut rng));
+            }
+        }
+    }
+
+    #[test]
+    fn connect3_wins() {
+        let mut simulator = ConnectNSimulator::new(5, 4, 3).unwrap();
+        let horizontal = play(&mut simulator, "1 1 2 2 3");
+        assert_eq!(simulator.calculate_rewards(&horizontal), ADVERSARIAL_P1_WIN.to_vec());
+        let vertical = play(&mut simulator, "1 2 1 2 3 2");
+        assert_eq!(simulator.calculate_rewards(&vertical), ADVERSARIAL_P1_LOSS.to_vec());
+        let diagonal = play(&mut simulator, "1 2 2 3 3 5 3");
+        assert_eq!(simulator.calculate_rewards(&diagonal), ADVERSARIAL_P1_WIN.to_vec());
+        assert!(simulator.is_terminal_state(&diagonal));
+        let no_line = play(&mut simulator, "1 1 2 2");
+        assert_eq!(simulator.calculate_rewards(&no_line), ADVERSARIAL_DRAW.to_vec());
+    }
+
+    #[test]
+    fn lines_do_not_wrap_between_columns() {
+        let mut simulator = ConnectNSimulator::new(3, 3, 3).unwrap();
+        // The top of column 1 and the bottom of column 2 are only
+        // separated by the empty row above the top row.
+        let state = "X--\nX--\nOX-".parse::<ConnectNState>().unwrap();
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_DRAW.to_vec());
+    }
+
+    #[test]
+    fn full_board_is_a_draw() {
+        let mut simulator = ConnectNSimulator::new(2, 2, 3).unwrap();
+        let state = play(&mut simulator, "1 1 2 2");
+        assert!(simulator.is_terminal_state(&state));
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_DRAW.to_vec());
+    }
+}
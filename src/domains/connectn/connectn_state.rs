@@ -0,0 +1,174 @@
+use std::fmt::{Display, self};
+use std::str::FromStr;
+
+use crate::core::imperfect_info::Observation;
+use crate::core::simulator::State;
+
+/// The number of bits in a bit board.
+pub const BOARD_BITS: usize = 128;
+
+/// Connect N board state for a board of any size that fits in a bit board.
+///
+/// Like `Connect4State`, each column takes `height + 1` bits, bottom row
+/// first, with an empty row above the top row so that lines of pieces
+/// never wrap from one column to the next. For a 5x4 board:
+/// .  .  .  .  . Row above top row
+/// 3  8 13 18 23
+/// 2  7 12 17 22
+/// 1  6 11 16 21
+/// 0  5 10 15 20
+#[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+pub struct ConnectNState {
+    pub width: u8,
+    pub height: u8,
+    pub bit_board: [u128; 2],
+}
+
+impl ConnectNState {
+    /// Creates an empty board.
+    ///
+    /// Will panic if the board, with a row above the top row,
+    /// does not fit in `BOARD_BITS` bits.
+    pub fn new(width: usize, height: usize) -> Self {
+        assert!(fits(width, height), "a {}x{} board does not fit in a bit board", width, height);
+        ConnectNState { width: width as u8, height: height as u8, bit_board: [0, 0] }
+    }
+
+    pub fn player_1_turn(&self) -> bool {
+        return self.bit_board[0].count_ones() <= self.bit_board[1].count_ones();
+    }
+
+    /// The bit of a square, where rows are numbered from the bottom.
+    pub fn mask(&self, column: usize, row: usize) -> u128 {
+        return 1 << (column * (self.height as usize + 1) + row);
+    }
+}
+
+/// Whether a board with a row above the top row fits in a bit board.
+pub fn fits(width: usize, height: usize) -> bool {
+    return width > 0 && height > 0 && height < BOARD_BITS && width <= BOARD_BITS / (height + 1);
+}
+
+/// Whether a bit board laid out like `ConnectNState` contains a line of
+/// pieces in any direction.
+///
+/// ### Arguments
+///
+/// * `bit_board` - One player's pieces.
+/// * `height` - The number of rows of the board.
+/// * `length` - The number of pieces in a line.
+pub fn has_line(bit_board: u128, height: usize, length: usize) -> bool {
+    // Shifts to the next square of a line: down-right diagonal,
+    // horizontal, up-right diagonal and vertical.
+    return [height, height + 1, height + 2, 1].iter().any(|&shift| {
+        let mut line = bit_board;
+        for _ in 1..length {
+            // A shift past the end of the bit board leaves no pieces,
+            // as on a single column board with 127 rows.
+            line &= line.checked_shr(shift as u32).unwrap_or(0);
+        }
+        line != 0
+    });
+}
+
+impl State for ConnectNState {}
+
+/// The board is fully visible to every player.
+impl Observation for ConnectNState {}
+
+impl Display for ConnectNState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in (0..self.height as usize).rev() {
+            for column in 0..self.width as usize {
+                let mask = self.mask(column, row);
+                if (self.bit_board[0] & mask) != 0 {
+                    write!(f, "X")?;
+                } else if (self.bit_board[1] & mask) != 0 {
+                    write!(f, "O")?;
+                } else {
+                    write!(f, "-")?;
+                }
+            }
+            if row != 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ConnectNState {
+    type Err = String;
+
+    /// Parses a board in the format written by `Display`,
+    /// taking the board size from the number of rows and columns.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s.lines().collect::<Vec<&str>>();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if !fits(width, rows.len()) {
+            return Err(format!("a {}x{} board does not fit in a bit board", width, rows.len()));
+        }
+        let mut state = ConnectNState::new(width, rows.len());
+        for (i, row) in rows.iter().enumerate() {
+            let cells = row.chars().collect::<Vec<char>>();
+            if cells.len() != width {
+                return Err(format!("expected {} columns, found {}", width, cells.len()));
+            }
+            let height = rows.len() - 1 - i;
+            for (column, cell) in cells.iter().enumerate() {
+                let mask = state.mask(column, height);
+                match cell {
+                    'X' => state.bit_board[0] |= mask,
+                    'O' => state.bit_board[1] |= mask,
+                    '-' => {},
+                    _ => return Err(format!("invalid cell: {}", cell)),
+                }
+            }
+        }
+        return Ok(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connectn_state_to_string() {
+        let mut state = ConnectNState::new(5, 4);
+        state.bit_board[0] |= state.mask(0, 0) | state.mask(2, 1);
+        state.bit_board[1] |= state.mask(2, 0);
+        let expected = "-----\n\
+                        -----\n\
+                        --X--\n\
+                        X-O--";
+        assert_eq!(state.to_string(), expected);
+        assert_eq!(expected.parse::<ConnectNState>(), Ok(state));
+    }
+
+    #[test]
+    fn connectn_state_from_str_uses_wide_bit_board() {
+        let board = ["-----------"; 9].join("\n");
+        let state = board.parse::<ConnectNState>().unwrap();
+        assert_eq!((state.width, state.height), (11, 9));
+        assert_eq!(state.mask(10, 8), 1 << 108);
+    }
+
+    #[test]
+    fn has_line_on_tallest_board() {
+        let state = ConnectNState::new(1, 127);
+        assert!(has_line((1 << 127) - 1, 127, 4));
+        assert!(!has_line(state.bit_board[0], 127, 4));
+        assert!(!has_line(0b1011, 126, 3));
+        assert!(!fits(1, 128));
+        assert!(!fits(usize::MAX, 1));
+    }
+
+    #[test]
+    fn connectn_state_from_str_invalid() {
+        assert!("---\n--".parse::<ConnectNState>().is_err());
+        assert!("--Y".parse::<ConnectNState>().is_err());
+        assert!("".parse::<ConnectNState>().is_err());
+        assert!(["----------------"; 8].join("\n").parse::<ConnectNState>().is_err());
+    }
+}
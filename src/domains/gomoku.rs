@@ -0,0 +1,3 @@
+pub mod gomoku_action;
+pub mod gomoku_simulator;
+pub mod gomoku_state;
@@ -0,0 +1,83 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::core::simulator::Action;
+
+/// Places a stone or, in the swap2 opening, chooses a color.
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+pub enum GomokuAction {
+    /// Places a stone of the color to move on an empty intersection,
+    /// with columns and rows numbered from the bottom left corner.
+    Place { column: u8, row: u8 },
+    /// Takes the black stones, leaving the opponent to move with white.
+    TakeBlack,
+    /// Places two more opening stones and lets the opponent choose a color.
+    Defer,
+}
+
+impl Action for GomokuAction {}
+
+/// Intersections are written as their column letter and row number,
+/// as in `h8`, and choices as `black` and `defer`.
+impl fmt::Display for GomokuAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GomokuAction::Place { column, row } => write!(f, "{}{}", (b'a' + column) as char, row + 1),
+            GomokuAction::TakeBlack => write!(f, "black"),
+            GomokuAction::Defer => write!(f, "defer"),
+        }
+    }
+}
+
+impl FromStr for GomokuAction {
+    type Err = String;
+
+    /// Parses an action in the format written by `Display`. Whether
+    /// the intersection is on the board is checked by the simulator.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "black" => return Ok(GomokuAction::TakeBlack),
+            "defer" => return Ok(GomokuAction::Defer),
+            _ => {},
+        }
+        let mut chars = s.chars();
+        let column = chars.next().filter(char::is_ascii_lowercase).map(|letter| letter as u8 - b'a');
+        let row = chars.as_str().parse::<u8>().ok().filter(|&row| row >= 1);
+        return match (column, row) {
+            (Some(column), Some(row)) if chars.as_str().starts_with(|c: char| c.is_ascii_digit()) => {
+                Ok(GomokuAction::Place { column, row: row - 1 })
+            },
+            _ => Err(format!("invalid gomoku action: {}", s)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gomoku_action_to_string() {
+        assert_eq!(GomokuAction::Place { column: 7, row: 7 }.to_string(), "h8");
+        assert_eq!(GomokuAction::Place { column: 18, row: 18 }.to_string(), "s19");
+        assert_eq!(GomokuAction::TakeBlack.to_string(), "black");
+        assert_eq!(GomokuAction::Defer.to_string(), "defer");
+    }
+
+    #[test]
+    fn gomoku_action_from_str() {
+        assert_eq!("a1".parse::<GomokuAction>(), Ok(GomokuAction::Place { column: 0, row: 0 }));
+        assert_eq!("o15".parse::<GomokuAction>(), Ok(GomokuAction::Place { column: 14, row: 14 }));
+        assert_eq!("defer".parse::<GomokuAction>(), Ok(GomokuAction::Defer));
+    }
+
+    #[test]
+    fn gomoku_action_from_str_invalid() {
+        assert!("a0".parse::<GomokuAction>().is_err());
+        assert!("A1".parse::<GomokuAction>().is_err());
+        assert!("a+1".parse::<GomokuAction>().is_err());
+        assert!("h".parse::<GomokuAction>().is_err());
+        assert!("white".parse::<GomokuAction>().is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::core::reward::{Reward, ADVERSARIAL_DRAW, ADVERSARIAL_P1_LOSS, ADVERSARIAL_P1_WIN};
+use crate::core::imperfect_info::ImperfectInfoSimulator;
+use crate::core::simulator::{Simulator, LegalActions};
+
+use super::gomoku_action::GomokuAction;
+use super::gomoku_state::{GomokuPhase, GomokuState, Stone};
+
+use rand_chacha::ChaCha8Rng;
+
+const N_PLAYERS: usize = 2;
+
+/// The number of stones in a row that wins.
+const LINE_LENGTH: usize = 5;
+
+/// The largest board supported, the size of a Go board.
+const MAX_SIZE: usize = 19;
+
+/// Which lines of stones win the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GomokuRule {
+    /// Five or more stones in a row win.
+    Freestyle,
+    /// Exactly five stones in a row win, while six or more do not.
+    Standard,
+}
+
+/// Players take turns placing black and white stones on the intersections
+/// of a square board, black first, and the first player to get five in a
+/// row horizontally, vertically or diagonally wins.
+///
+/// With the swap2 opening, the first player places two black stones and
+/// one white stone. The second player then takes black, places a white
+/// stone as white, or places a black and a white stone and lets the first
+/// player take black or place a white stone as white.
+#[derive(Clone, Debug)]
+pub struct GomokuSimulator {
+    size: usize,
+    rule: GomokuRule,
+    swap2: bool,
+    /// Every row, column and diagonal long enough for a line of five,
+    /// as the indices of its intersections in order.
+    lines: Vec<Vec<usize>>,
+    /// The lines through each intersection and its position in each of them.
+    intersection_lines: Vec<Vec<(usize, usize)>>,
+}
+
+impl GomokuSimulator {
+    /// Creates a simulator for a board size and rule.
+    ///
+    /// ### Arguments
+    ///
+    /// * `size` - The number of rows and columns, usually 15 or 19.
+    /// * `rule` - Which lines of stones win the game.
+    /// * `swap2` - Whether the game starts with the swap2 opening.
+    ///
+    /// ### Return Value
+    ///
+    /// The simulator, or an error if the board is too small
+    /// for a line of five or larger than 19x19.
+    pub fn new(size: usize, rule: GomokuRule, swap2: bool) -> Result<Self, String> {
+        if !(LINE_LENGTH..=MAX_SIZE).contains(&size) {
+            return Err(format!("board size must be from {} to {}, found {}", LINE_LENGTH, MAX_SIZE, size));
+        }
+        let lines = line_table(size);
+        let mut intersection_lines = vec![Vec::new(); size * size];
+        for (line, intersections) in lines.iter().enumerate() {
+            for (position, &index) in intersections.iter().enumerate() {
+                intersection_lines[index].push((line, position));
+            }
+        }
+        return Ok(GomokuSimulator { size, rule, swap2, lines, intersection_lines });
+    }
+
+    /// Freestyle gomoku on a 15x15 board without an opening rule.
+    pub fn freestyle() -> Self {
+        GomokuSimulator::new(15, GomokuRule::Freestyle, false).unwrap()
+    }
+
+    /// Standard gomoku on a 15x15 board with the swap2 opening.
+    pub fn standard() -> Self {
+        GomokuSimulator::new(15, GomokuRule::Standard, true).unwrap()
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn rule(&self) -> GomokuRule {
+        self.rule
+    }
+
+    pub fn swap2(&self) -> bool {
+        self.swap2
+    }
+
+    /// The color that has won, which can only be the
+    /// color of the last stone placed.
+    fn winner(&self, state: &GomokuState) -> Option<Stone> {
+        let index = state.last_move?;
+        let stone = state.board[index]?;
+        let is_stone = |&&index: &&usize| state.board[index] == Some(stone);
+        let wins = self.intersection_lines[index].iter().any(|&(line, position)| {
+            let intersections = &self.lines[line];
+            let after = intersections[position + 1..].iter().take_while(is_stone).count();
+            let before = intersections[..position].iter().rev().take_while(is_stone).count();
+            match self.rule {
+                GomokuRule::Freestyle => before + 1 + after >= LINE_LENGTH,
+                GomokuRule::Standard => before + 1 + after == LINE_LENGTH,
+            }
+        });
+        return if wins { Some(stone) } else { None };
+    }
+
+    fn is_over(&self, state: &GomokuState) -> bool {
+        return self.winner(state).is_some() || state.board.iter().all(Option::is_some);
+    }
+}
+
+/// Whether the player to act may take an action in a game that is not over.
+fn is_legal(state: &GomokuState, action: &GomokuAction) -> bool {
+    return match *action {
+        GomokuAction::Place { column, row } => {
+            (column as usize) < state.size as usize && (row as usize) < state.size as usize &&
+                state.board[state.index(column as usize, row as usize)].is_none()
+        },
+        GomokuAction::TakeBlack => matches!(state.phase, GomokuPhase::Choice | GomokuPhase::FinalChoice),
+        GomokuAction::Defer => state.phase == GomokuPhase::Choice,
+    };
+}
+
+/// Every row, column, diagonal and anti-diagonal of a board
+/// that has room for a line of five.
+fn line_table(size: usize) -> Vec<Vec<usize>> {
+    let mut lines = Vec::new();
+    for (column_step, row_step) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+        for column in 0..size as i32 {
+            for row in 0..size as i32 {
+                // Start lines only from intersections without a predecessor.
+                let previous = (column - column_step, row - row_step);
+                if (0..size as i32).contains(&previous.0) && (0..size as i32).contains(&previous.1) {
+                    continue;
+                }
+                let mut line = Vec::new();
+                let (mut c, mut r) = (column, row);
+                while (0..size as i32).contains(&c) && (0..size as i32).contains(&r) {
+                    line.push(r as usize * size + c as usize);
+                    c += column_step;
+                    r += row_step;
+                }
+                if line.len() >= LINE_LENGTH {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+    return lines;
+}
+
+impl Simulator<GomokuState, GomokuAction> for GomokuSimulator {
+    fn generate_initial_state(&mut self) -> GomokuState {
+        GomokuState::new(self.size, if self.swap2 { GomokuPhase::Opening } else { GomokuPhase::Play })
+    }
+
+    fn calculate_rewards(&mut self, state: &GomokuState) -> Vec<Reward> {
+        return match self.winner(state).map(|stone| state.player(stone)) {
+            Some(0) => ADVERSARIAL_P1_WIN.to_vec(),
+            Some(_) => ADVERSARIAL_P1_LOSS.to_vec(),
+            None => ADVERSARIAL_DRAW.to_vec(),
+        };
+    }
+
+    fn calculate_legal_actions(&mut self, state: &GomokuState) -> Vec<LegalActions<GomokuAction>> {
+        let mut legal_actions = vec![LegalActions::<GomokuAction>::new(); N_PLAYERS];
+        if self.is_over(state) {
+            return legal_actions;
+        }
+        let player_id = state.player_to_act();
+        [GomokuAction::TakeBlack, GomokuAction::Defer].into_iter()
+            .chain((0..state.board.len()).map(|index| state.place_action(index)))
+            .filter(|action| is_legal(state, action))
+            .for_each(|action| legal_actions[player_id].insert(action));
+        return legal_actions;
+    }
+
+    /// Checks the actions directly rather than through every legal
+    /// action, of which there are hundreds on large boards.
+    fn check_valid_state_transition(&mut self, state: &GomokuState, actions: &Vec<Option<GomokuAction>>) -> Result<(), String> {
+        if actions.len() != N_PLAYERS {
+            return Err(format!("actions length is {}, while number of players is {}", actions.len(), N_PLAYERS));
+        }
+        let acting_player = if self.is_over(state) { None } else { Some(state.player_to_act()) };
+        for (player_id, action) in actions.iter().enumerate() {
+            let is_legal = match action {
+                Some(action) => acting_player == Some(player_id) && is_legal(state, action),
+                None => acting_player != Some(player_id),
+            };
+            if !is_legal {
+                return Err(format!("player {} has illegal action", player_id));
+            }
+        }
+        return Ok(());
+    }
+
+    fn state_transition(&mut self, state: &GomokuState, actions: &Vec<Option<GomokuAction>>) -> GomokuState {
+        self.check_valid_state_transition(state, actions).unwrap();
+        let player_id = state.player_to_act();
+        let mut next = state.clone();
+        match actions[player_id].unwrap() {
+            GomokuAction::Place { column, row } => {
+                let index = state.index(column as usize, row as usize);
+                next.board[index] = Some(state.stone_to_move());
+                next.last_move = Some(index);
+                let stones = next.board.iter().filter(|intersection| intersection.is_some()).count();
+                next.phase = match state.phase {
+                    GomokuPhase::Opening if stones < 3 => GomokuPhase::Opening,
+                    GomokuPhase::Opening => GomokuPhase::Choice,
+                    GomokuPhase::Deferred if stones < 5 => GomokuPhase::Deferred,
+                    GomokuPhase::Deferred => GomokuPhase::FinalChoice,
+                    _ => GomokuPhase::Play,
+                };
+                if matches!(state.phase, GomokuPhase::Choice | GomokuPhase::FinalChoice) {
+                    next.black_player = 1 - player_id;
+                }
+            },
+            GomokuAction::TakeBlack => {
+                next.black_player = player_id;
+                next.phase = GomokuPhase::Play;
+            },
+            GomokuAction::Defer => next.phase = GomokuPhase::Deferred,
+        }
+        return next;
+    }
+
+    fn number_of_players(&mut self) -> usize {
+        2
+    }
+}
+
+impl ImperfectInfoSimulator<GomokuState, GomokuAction, GomokuState> for GomokuSimulator {
+    fn observe(&mut self, state: &GomokuState, _player_id: usize) -> GomokuState {
+        state.clone()
+    }
+
+    fn sample_determinization(&mut self, observation: &GomokuState, _player_id: usize, _rng: &mut ChaCha8Rng) -> GomokuState {
+        observation.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::simulator_conformance_tests!(freestyle_conformance, |_rng| GomokuSimulator::new(9, GomokuRule::Freestyle, false).unwrap());
+    crate::simulator_conformance_tests!(swap2_conformance, |_rng| GomokuSimulator::new(9, GomokuRule::Standard, true).unwrap());
+
+    fn play(simulator: &mut GomokuSimulator, moves: &str) -> GomokuState {
+        let mut state = simulator.generate_initial_state();
+        for action in moves.split_whitespace() {
+            let mut actions = vec![None; N_PLAYERS];
+            actions[state.player_to_act()] = Some(action.parse().unwrap());
+            state = simulator.state_transition(&state, &actions);
+        }
+        return state;
+    }
+
+    #[test]
+    fn new_checks_board_size() {
+        assert!(GomokuSimulator::new(4, GomokuRule::Freestyle, false).is_err());
+        assert!(GomokuSimulator::new(5, GomokuRule::Freestyle, false).is_ok());
+        assert!(GomokuSimulator::new(19, GomokuRule::Standard, true).is_ok());
+        assert!(GomokuSimulator::new(20, GomokuRule::Standard, false).is_err());
+    }
+
+    #[test]
+    fn line_table_counts_lines_of_five() {
+        // 15 rows, 15 columns and 11 diagonals of each direction of length 5 or more.
+        assert_eq!(line_table(15).len(), 15 + 15 + 2 * (2 * 11 - 1));
+        assert_eq!(line_table(5).len(), 5 + 5 + 2);
+    }
+
+    #[test]
+    fn five_in_a_row_wins() {
+        let mut simulator = GomokuSimulator::freestyle();
+        let state = play(&mut simulator, "h8 a1 i9 a2 j10 a3 k11 a4");
+        assert!(!simulator.is_terminal_state(&state));
+        let state = play(&mut simulator, "h8 a1 i9 a2 j10 a3 k11 a4 l12");
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_P1_WIN.to_vec());
+        assert!(simulator.is_terminal_state(&state));
+        let state = play(&mut simulator, "h8 a1 i9 a2 j10 a3 k11 a4 o1 a5");
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_P1_LOSS.to_vec());
+    }
+
+    #[test]
+    fn overlines_win_only_in_freestyle() {
+        let moves = "a1 a15 b1 b15 c1 c15 e1 e15 f1 f15 d1";
+        let mut simulator = GomokuSimulator::freestyle();
+        let state = play(&mut simulator, moves);
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_P1_WIN.to_vec());
+        let mut simulator = GomokuSimulator::new(15, GomokuRule::Standard, false).unwrap();
+        let state = play(&mut simulator, moves);
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_DRAW.to_vec());
+        assert!(!simulator.is_terminal_state(&state));
+    }
+
+    #[test]
+    fn swap2_second_player_takes_black() {
+        let mut simulator = GomokuSimulator::standard();
+        let state = play(&mut simulator, "h8 h9 i8");
+        assert_eq!((state.phase, state.player_to_act()), (GomokuPhase::Choice, 1));
+        assert_eq!((state.stone_count(Stone::Black), state.stone_count(Stone::White)), (2, 1));
+        assert_eq!(simulator.calculate_legal_actions(&state)[1].0.len(), 15 * 15 - 3 + 2);
+        let state = play(&mut simulator, "h8 h9 i8 black");
+        assert_eq!((state.phase, state.black_player, state.player_to_act()), (GomokuPhase::Play, 1, 0));
+        assert_eq!(state.stone_to_move(), Stone::White);
+    }
+
+    #[test]
+    fn swap2_second_player_places_white() {
+        let mut simulator = GomokuSimulator::standard();
+        let state = play(&mut simulator, "h8 h9 i8 j8");
+        assert_eq!((state.phase, state.black_player, state.player_to_act()), (GomokuPhase::Play, 0, 0));
+        assert_eq!(state.stone_count(Stone::White), 2);
+    }
+
+    #[test]
+    fn swap2_deferred_choice() {
+        let mut simulator = GomokuSimulator::standard();
+        let state = play(&mut simulator, "h8 h9 i8 defer j8 j9");
+        assert_eq!((state.phase, state.player_to_act()), (GomokuPhase::FinalChoice, 0));
+        assert_eq!((state.stone_count(Stone::Black), state.stone_count(Stone::White)), (3, 2));
+        assert!(!simulator.calculate_legal_actions(&state)[0].0.contains(&GomokuAction::Defer));
+        let state = play(&mut simulator, "h8 h9 i8 defer j8 j9 black");
+        assert_eq!((state.black_player, state.player_to_act()), (0, 1));
+        let state = play(&mut simulator, "h8 h9 i8 defer j8 j9 k8");
+        assert_eq!((state.black_player, state.player_to_act()), (1, 1));
+        assert_eq!(state.stone_to_move(), Stone::Black);
+    }
+}
@@ -0,0 +1,236 @@
+use std::fmt::{Display, self};
+use std::str::FromStr;
+
+use crate::core::imperfect_info::Observation;
+use crate::core::simulator::State;
+
+use super::gomoku_action::GomokuAction;
+
+/// The largest board that can be written with one letter per column.
+pub const MAX_NOTATION_SIZE: usize = 26;
+
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+pub enum Stone {
+    Black,
+    White,
+}
+
+/// The part of the game being played, where every phase but `Play`
+/// belongs to the swap2 opening.
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+pub enum GomokuPhase {
+    /// The players alternate placing stones, black first.
+    Play,
+    /// The first player places two black stones and one white stone.
+    Opening,
+    /// The second player takes black, places a white stone as white or defers.
+    Choice,
+    /// The second player places a black stone and a white stone.
+    Deferred,
+    /// The first player takes black or places a white stone as white.
+    FinalChoice,
+}
+
+impl GomokuPhase {
+    const ALL: [GomokuPhase; 5] = [
+        GomokuPhase::Play,
+        GomokuPhase::Opening,
+        GomokuPhase::Choice,
+        GomokuPhase::Deferred,
+        GomokuPhase::FinalChoice,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            GomokuPhase::Play => "play",
+            GomokuPhase::Opening => "opening",
+            GomokuPhase::Choice => "choice",
+            GomokuPhase::Deferred => "deferred",
+            GomokuPhase::FinalChoice => "final-choice",
+        }
+    }
+}
+
+/// Gomoku board state for a square board of any size.
+///
+/// Intersections are stored row by row, bottom row first.
+#[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+pub struct GomokuState {
+    pub size: u8,
+    pub board: Vec<Option<Stone>>,
+    /// The ID of the player with the black stones, which is
+    /// the first player until it is chosen in the swap2 opening.
+    pub black_player: usize,
+    pub phase: GomokuPhase,
+    /// The index of the last stone placed.
+    pub last_move: Option<usize>,
+}
+
+impl GomokuState {
+    /// Creates an empty board.
+    ///
+    /// Will panic if the board is larger than `MAX_NOTATION_SIZE`.
+    pub fn new(size: usize, phase: GomokuPhase) -> Self {
+        assert!(size <= MAX_NOTATION_SIZE, "a {}x{} board is too large", size, size);
+        GomokuState { size: size as u8, board: vec![None; size * size], black_player: 0, phase, last_move: None }
+    }
+
+    /// The index of an intersection, where rows are numbered from the bottom.
+    pub fn index(&self, column: usize, row: usize) -> usize {
+        return row * self.size as usize + column;
+    }
+
+    /// The action that places a stone on the intersection of an index.
+    pub fn place_action(&self, index: usize) -> GomokuAction {
+        let size = self.size as usize;
+        return GomokuAction::Place { column: (index % size) as u8, row: (index / size) as u8 };
+    }
+
+    pub fn stone_count(&self, stone: Stone) -> usize {
+        return self.board.iter().filter(|&&intersection| intersection == Some(stone)).count();
+    }
+
+    /// Black moves whenever both colors have placed the same number of stones.
+    pub fn stone_to_move(&self) -> Stone {
+        return if self.stone_count(Stone::Black) <= self.stone_count(Stone::White) { Stone::Black } else { Stone::White };
+    }
+
+    /// The player who places the next stone or makes the next choice.
+    pub fn player_to_act(&self) -> usize {
+        return match self.phase {
+            GomokuPhase::Opening | GomokuPhase::FinalChoice => 0,
+            GomokuPhase::Choice | GomokuPhase::Deferred => 1,
+            GomokuPhase::Play => self.player(self.stone_to_move()),
+        };
+    }
+
+    /// The ID of the player with a color.
+    pub fn player(&self, stone: Stone) -> usize {
+        return if stone == Stone::Black { self.black_player } else { 1 - self.black_player };
+    }
+}
+
+impl State for GomokuState {}
+
+/// The board is fully visible to every player.
+impl Observation for GomokuState {}
+
+/// Writes the board with black stones as `X` and white stones as `O`,
+/// followed by the phase, the player with black and the last move.
+impl Display for GomokuState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = self.size as usize;
+        for row in (0..size).rev() {
+            for column in 0..size {
+                match self.board[self.index(column, row)] {
+                    Some(Stone::Black) => write!(f, "X")?,
+                    Some(Stone::White) => write!(f, "O")?,
+                    None => write!(f, "-")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        let last_move = self.last_move.map_or("-".to_string(), |index| self.place_action(index).to_string());
+        write!(f, "phase: {}, black: {}, last: {}", self.phase.name(), self.black_player, last_move)
+    }
+}
+
+impl FromStr for GomokuState {
+    type Err = String;
+
+    /// Parses a state in the format written by `Display`,
+    /// taking the board size from the number of rows.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().collect::<Vec<&str>>();
+        let status = lines.pop().ok_or("missing status")?;
+        let size = lines.len();
+        if size == 0 || size > MAX_NOTATION_SIZE {
+            return Err(format!("invalid board size: {}", size));
+        }
+        let mut state = GomokuState::new(size, GomokuPhase::Play);
+        for (i, line) in lines.iter().enumerate() {
+            let intersections = line.chars().collect::<Vec<char>>();
+            if intersections.len() != size {
+                return Err(format!("expected {} columns, found {}", size, intersections.len()));
+            }
+            let row = size - 1 - i;
+            for (column, intersection) in intersections.iter().enumerate() {
+                let index = state.index(column, row);
+                state.board[index] = match intersection {
+                    'X' => Some(Stone::Black),
+                    'O' => Some(Stone::White),
+                    '-' => None,
+                    _ => return Err(format!("invalid intersection: {}", intersection)),
+                };
+            }
+        }
+
+        let invalid_status = || format!("invalid status: {}", status);
+        let fields = status.split(", ")
+            .map(|field| field.split_once(": ").ok_or_else(invalid_status))
+            .collect::<Result<Vec<(&str, &str)>, String>>()?;
+        let [("phase", phase), ("black", black_player), ("last", last_move)] = fields[..] else {
+            return Err(invalid_status());
+        };
+        state.phase = GomokuPhase::ALL.into_iter().find(|x| x.name() == phase).ok_or_else(invalid_status)?;
+        state.black_player = match black_player {
+            "0" => 0,
+            "1" => 1,
+            _ => return Err(invalid_status()),
+        };
+        state.last_move = match last_move {
+            "-" => None,
+            _ => match last_move.parse::<GomokuAction>()? {
+                GomokuAction::Place { column, row } if (column as usize) < size && (row as usize) < size => {
+                    Some(state.index(column as usize, row as usize))
+                },
+                _ => return Err(invalid_status()),
+            },
+        };
+        return Ok(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gomoku_state_to_string_and_from_str() {
+        let mut state = GomokuState::new(5, GomokuPhase::Choice);
+        for (column, row, stone) in [(2, 2, Stone::Black), (3, 2, Stone::White), (0, 0, Stone::Black)] {
+            let index = state.index(column, row);
+            state.board[index] = Some(stone);
+        }
+        state.last_move = Some(state.index(0, 0));
+        let expected = "-----\n\
+                        -----\n\
+                        --XO-\n\
+                        -----\n\
+                        X----\n\
+                        phase: choice, black: 0, last: a1";
+        assert_eq!(state.to_string(), expected);
+        assert_eq!(expected.parse::<GomokuState>(), Ok(state));
+    }
+
+    #[test]
+    fn gomoku_state_from_str_invalid() {
+        assert!("---\n---\n---".parse::<GomokuState>().is_err());
+        assert!("---\n---\n---\nphase: play, black: 2, last: -".parse::<GomokuState>().is_err());
+        assert!("---\n---\n---\nphase: play, black: 0, last: d1".parse::<GomokuState>().is_err());
+        assert!("---\n--\n---\nphase: play, black: 0, last: -".parse::<GomokuState>().is_err());
+        assert!("---\n---\n---\nphase: play, black: 0, last: -".parse::<GomokuState>().is_ok());
+    }
+
+    #[test]
+    fn player_to_act_follows_colors() {
+        let mut state = GomokuState::new(15, GomokuPhase::Play);
+        assert_eq!((state.stone_to_move(), state.player_to_act()), (Stone::Black, 0));
+        state.board[0] = Some(Stone::Black);
+        assert_eq!((state.stone_to_move(), state.player_to_act()), (Stone::White, 1));
+        state.black_player = 1;
+        assert_eq!(state.player_to_act(), 0);
+        state.phase = GomokuPhase::Deferred;
+        assert_eq!(state.player_to_act(), 1);
+    }
+}
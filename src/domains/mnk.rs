@@ -0,0 +1,3 @@
+pub mod mnk_action;
+pub mod mnk_simulator;
+pub mod mnk_state;
@@ -0,0 +1,60 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::core::simulator::Action;
+
+/// Places a piece on an empty square, with columns and
+/// rows numbered from the bottom left corner.
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+pub struct MnkAction {
+    pub column: u8,
+    pub row: u8,
+}
+
+impl Action for MnkAction {}
+
+/// Squares are written as their column letter and row number, as in `b2`.
+impl fmt::Display for MnkAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.column) as char, self.row + 1)
+    }
+}
+
+impl FromStr for MnkAction {
+    type Err = String;
+
+    /// Parses a square in the format written by `Display`. Whether
+    /// the square is on the board is checked by the simulator.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let mut chars = s.chars();
+        let column = chars.next().filter(char::is_ascii_lowercase).map(|letter| letter as u8 - b'a');
+        let row = chars.as_str().parse::<u8>().ok().filter(|&row| row >= 1);
+        return match (column, row) {
+            (Some(column), Some(row)) if chars.as_str().starts_with(|c: char| c.is_ascii_digit()) => {
+                Ok(MnkAction { column, row: row - 1 })
+            },
+            _ => Err(format!("invalid m,n,k action: {}", s)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mnk_action_to_string() {
+        assert_eq!(MnkAction { column: 1, row: 1 }.to_string(), "b2");
+        assert_eq!(MnkAction { column: 0, row: 10 }.to_string(), "a11");
+    }
+
+    #[test]
+    fn mnk_action_from_str() {
+        assert_eq!("c1".parse::<MnkAction>(), Ok(MnkAction { column: 2, row: 0 }));
+        assert!("c0".parse::<MnkAction>().is_err());
+        assert!("C1".parse::<MnkAction>().is_err());
+        assert!("c+1".parse::<MnkAction>().is_err());
+        assert!("3".parse::<MnkAction>().is_err());
+    }
+}
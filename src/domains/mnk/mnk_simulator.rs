@@ -0,0 +1,249 @@
+use crate::core::reward::{Reward, ADVERSARIAL_DRAW, ADVERSARIAL_P1_LOSS, ADVERSARIAL_P1_WIN};
+use crate::core::imperfect_info::ImperfectInfoSimulator;
+use crate::core::simulator::{Simulator, LegalActions};
+use crate::domains::connectn::connectn_state::has_line;
+
+use super::mnk_action::MnkAction;
+use super::mnk_state::{fits, MnkState};
+
+use rand_chacha::ChaCha8Rng;
+
+const N_PLAYERS: usize = 2;
+
+/// Players take turns placing pieces on any empty square of an
+/// `m` by `n` board, and the first player to get `k` pieces in a
+/// row horizontally, vertically or diagonally wins.
+///
+/// Small boards such as tic-tac-toe can be searched exhaustively,
+/// which makes them useful for testing agents against known results.
+#[derive(Clone, Debug)]
+pub struct MnkSimulator {
+    m: usize,
+    n: usize,
+    k: usize,
+}
+
+impl MnkSimulator {
+    /// Creates a simulator for a board size and line length.
+    ///
+    /// ### Arguments
+    ///
+    /// * `m` - The number of columns.
+    /// * `n` - The number of rows.
+    /// * `k` - The number of pieces in a row needed to win.
+    ///
+    /// ### Return Value
+    ///
+    /// The simulator, or an error if `k` is zero or the board does
+    /// not fit in a bit board.
+    pub fn new(m: usize, n: usize, k: usize) -> Result<Self, String> {
+        if !fits(m, n) {
+            return Err(format!("a {}x{} board does not fit in a bit board", m, n));
+        }
+        if k == 0 {
+            return Err("k must be positive".to_string());
+        }
+        return Ok(MnkSimulator { m, n, k });
+    }
+
+    /// Tic-tac-toe, the 3,3,3-game.
+    pub fn tic_tac_toe() -> Self {
+        MnkSimulator { m: 3, n: 3, k: 3 }
+    }
+
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Will panic if a state does not have the simulator's board size,
+    /// since its bits would be laid out differently.
+    fn check_board_size(&self, state: &MnkState) {
+        assert_eq!(
+            (state.width as usize, state.height as usize),
+            (self.m, self.n),
+            "state board size does not match the simulator",
+        );
+    }
+}
+
+impl Simulator<MnkState, MnkAction> for MnkSimulator {
+    fn generate_initial_state(&mut self) -> MnkState {
+        MnkState::new(self.m, self.n)
+    }
+
+    fn calculate_rewards(&mut self, state: &MnkState) -> Vec<Reward> {
+        self.check_board_size(state);
+        if has_line(state.bit_board[0], self.n, self.k) {
+            return ADVERSARIAL_P1_WIN.to_vec();
+        }
+        if has_line(state.bit_board[1], self.n, self.k) {
+            return ADVERSARIAL_P1_LOSS.to_vec();
+        }
+        return ADVERSARIAL_DRAW.to_vec();
+    }
+
+    fn calculate_legal_actions(&mut self, state: &MnkState) -> Vec<LegalActions<MnkAction>> {
+        self.check_board_size(state);
+        let mut legal_actions = vec![LegalActions::<MnkAction>::new(); N_PLAYERS];
+        if has_line(state.bit_board[0], self.n, self.k) || has_line(state.bit_board[1], self.n, self.k) {
+            return legal_actions;
+        }
+        let agent_turn = if state.player_1_turn() { 0 } else { 1 };
+        let occupied = state.bit_board[0] | state.bit_board[1];
+        for column in 0..self.m {
+            for row in (0..self.n).filter(|&row| occupied & state.mask(column, row) == 0) {
+                legal_actions[agent_turn].insert(MnkAction { column: column as u8, row: row as u8 });
+            }
+        }
+        return legal_actions;
+    }
+
+    fn state_transition(&mut self, state: &MnkState, actions: &Vec<Option<MnkAction>>) -> MnkState {
+        self.check_valid_state_transition(state, actions).unwrap();
+        let mut state = state.clone();
+        let agent_turn: usize = if state.player_1_turn() { 0 } else { 1 };
+        let action = actions[agent_turn].unwrap();
+        state.bit_board[agent_turn] |= state.mask(action.column as usize, action.row as usize);
+        return state;
+    }
+
+    fn number_of_players(&mut self) -> usize {
+        2
+    }
+}
+
+impl ImperfectInfoSimulator<MnkState, MnkAction, MnkState> for MnkSimulator {
+    fn observe(&mut self, state: &MnkState, _player_id: usize) -> MnkState {
+        state.clone()
+    }
+
+    fn sample_determinization(&mut self, observation: &MnkState, _player_id: usize, _rng: &mut ChaCha8Rng) -> MnkState {
+        observation.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::core::perft::perft;
+
+    use super::*;
+
+    crate::simulator_conformance_tests!(tic_tac_toe_conformance, |_rng| MnkSimulator::tic_tac_toe());
+    crate::simulator_conformance_tests!(mnk_conformance, |_rng| MnkSimulator::new(7, 5, 4).unwrap());
+
+    fn play(simulator: &mut MnkSimulator, moves: &str) -> MnkState {
+        let mut state = simulator.generate_initial_state();
+        for (i, action) in moves.split_whitespace().enumerate() {
+            let mut actions = vec![None; N_PLAYERS];
+            actions[i % N_PLAYERS] = Some(action.parse().unwrap());
+            state = simulator.state_transition(&state, &actions);
+        }
+        return state;
+    }
+
+    /// The value of a state for the first player with perfect play.
+    fn solve(simulator: &mut MnkSimulator, state: &MnkState, values: &mut HashMap<MnkState, f64>) -> f64 {
+        if let Some(&value) = values.get(state) {
+            return value;
+        }
+        let value = if simulator.is_terminal_state(state) {
+            simulator.calculate_rewards(state)[0].0
+        } else {
+            let player_1_turn = state.player_1_turn();
+            let legal_actions = simulator.calculate_legal_actions(state);
+            let agent_turn = if player_1_turn { 0 } else { 1 };
+            let mut best = if player_1_turn { f64::NEG_INFINITY } else { f64::INFINITY };
+            for action in legal_actions[agent_turn].iter() {
+                let mut actions = vec![None; N_PLAYERS];
+                actions[agent_turn] = Some(*action);
+                let next_state = simulator.state_transition(state, &actions);
+                let value = solve(simulator, &next_state, values);
+                best = if player_1_turn { best.max(value) } else { best.min(value) };
+            }
+            best
+        };
+        values.insert(state.clone(), value);
+        return value;
+    }
+
+    #[test]
+    fn new_rejects_invalid_games() {
+        assert!(MnkSimulator::new(15, 7, 5).is_ok());
+        assert!(MnkSimulator::new(15, 8, 5).is_err());
+        assert!(MnkSimulator::new(27, 1, 5).is_err());
+        assert!(MnkSimulator::new(0, 3, 3).is_err());
+        assert!(MnkSimulator::new(3, 3, 0).is_err());
+        assert!(MnkSimulator::new(1, 128, 3).is_err());
+    }
+
+    #[test]
+    fn tallest_board_does_not_overflow() {
+        let mut simulator = MnkSimulator::new(1, 127, 3).unwrap();
+        let state = play(&mut simulator, "a1 a127 a2 a126 a64");
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_DRAW.to_vec());
+        let column = play(&mut simulator, "a1 a127 a2 a126 a3");
+        assert_eq!(simulator.calculate_rewards(&column), ADVERSARIAL_P1_WIN.to_vec());
+    }
+
+    #[test]
+    #[should_panic(expected = "state board size does not match the simulator")]
+    fn state_of_another_board_size_panics() {
+        MnkSimulator::tic_tac_toe().calculate_rewards(&MnkState::new(4, 3));
+    }
+
+    #[test]
+    fn perft_tic_tac_toe() {
+        let mut simulator = MnkSimulator::tic_tac_toe();
+        let state = simulator.generate_initial_state();
+        let expected = [1, 9, 72, 504, 3024, 15120, 54720];
+        for (depth, expected) in expected.into_iter().enumerate() {
+            assert_eq!(perft(&mut simulator, &state, depth), expected, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn tic_tac_toe_wins() {
+        let mut simulator = MnkSimulator::tic_tac_toe();
+        let column = play(&mut simulator, "a1 b1 a2 b2 a3");
+        assert_eq!(simulator.calculate_rewards(&column), ADVERSARIAL_P1_WIN.to_vec());
+        assert!(simulator.is_terminal_state(&column));
+        let diagonal = play(&mut simulator, "a1 c1 a2 b2 b3 a3");
+        assert_eq!(simulator.calculate_rewards(&diagonal), ADVERSARIAL_P1_LOSS.to_vec());
+        let full = play(&mut simulator, "b2 a1 c1 a3 a2 c2 b1 b3 c3");
+        assert!(simulator.is_terminal_state(&full));
+        assert_eq!(simulator.calculate_rewards(&full), ADVERSARIAL_DRAW.to_vec());
+    }
+
+    #[test]
+    fn lines_do_not_wrap_between_columns() {
+        let mut simulator = MnkSimulator::new(3, 3, 3).unwrap();
+        // The top of column a and the bottom of column b are only
+        // separated by the empty row above the top row.
+        let state = "---\nX--\nXX-".parse::<MnkState>().unwrap();
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_DRAW.to_vec());
+    }
+
+    #[test]
+    fn tic_tac_toe_is_a_draw() {
+        let mut simulator = MnkSimulator::tic_tac_toe();
+        let state = simulator.generate_initial_state();
+        assert_eq!(solve(&mut simulator, &state, &mut HashMap::new()), ADVERSARIAL_DRAW[0].0);
+    }
+
+    #[test]
+    fn four_by_three_is_a_first_player_win() {
+        let mut simulator = MnkSimulator::new(4, 3, 3).unwrap();
+        let state = simulator.generate_initial_state();
+        assert_eq!(solve(&mut simulator, &state, &mut HashMap::new()), ADVERSARIAL_P1_WIN[0].0);
+    }
+}
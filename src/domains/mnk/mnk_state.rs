@@ -0,0 +1,43 @@
+use crate::domains::connectn::connectn_state::{self, ConnectNState};
+
+/// The largest width that can be written with one letter per column.
+pub const MAX_WIDTH: usize = 26;
+
+/// m,n,k-game board state, which is laid out like a Connect N board
+/// even though pieces can be placed on any empty square. For a 3x3 board:
+/// .  .  . Row above top row
+/// 2  6 10
+/// 1  5  9
+/// 0  4  8
+pub type MnkState = ConnectNState;
+
+/// Whether a board fits in a `ConnectNState` bit board
+/// and its columns can be written with one letter each.
+pub fn fits(width: usize, height: usize) -> bool {
+    return connectn_state::fits(width, height) && width <= MAX_WIDTH;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mnk_state_to_string_and_from_str() {
+        let mut state = MnkState::new(4, 3);
+        state.bit_board[0] |= state.mask(0, 0) | state.mask(3, 2);
+        state.bit_board[1] |= state.mask(1, 1);
+        let expected = "---X\n\
+                        -O--\n\
+                        X---";
+        assert_eq!(state.to_string(), expected);
+        assert_eq!(expected.parse::<MnkState>(), Ok(state));
+    }
+
+    #[test]
+    fn fits_limits_width_to_column_letters() {
+        assert!(fits(26, 3));
+        assert!(!fits(27, 3));
+        assert!(fits(1, 127));
+        assert!(!fits(1, 128));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod othello_action;
+pub mod othello_simulator;
+pub mod othello_state;
@@ -0,0 +1,86 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::core::simulator::Action;
+
+use super::othello_state::BOARD_SIZE;
+
+/// Places a disc or, when no disc can be placed, passes the turn.
+#[derive(Clone, Copy, fmt::Debug, Hash, PartialEq, Eq)]
+pub enum OthelloAction {
+    /// Places a disc on an empty square, with columns numbered
+    /// from the left and rows numbered from the top.
+    Place { column: u8, row: u8 },
+    Pass,
+}
+
+impl OthelloAction {
+    /// The bit of the square a disc is placed on, if any.
+    pub fn mask(&self) -> u64 {
+        return match self {
+            OthelloAction::Place { column, row } => 1 << (*row as usize * BOARD_SIZE + *column as usize),
+            OthelloAction::Pass => 0,
+        };
+    }
+}
+
+impl Action for OthelloAction {}
+
+/// Squares are written in standard notation as their column
+/// letter and row number, as in `d3`, and passes as `pass`.
+impl fmt::Display for OthelloAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OthelloAction::Place { column, row } => write!(f, "{}{}", (b'a' + column) as char, row + 1),
+            OthelloAction::Pass => write!(f, "pass"),
+        }
+    }
+}
+
+impl FromStr for OthelloAction {
+    type Err = String;
+
+    /// Parses an action in the format written by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "pass" {
+            return Ok(OthelloAction::Pass);
+        }
+        return match s.as_bytes() {
+            &[column @ b'a'..=b'h', row @ b'1'..=b'8'] => {
+                Ok(OthelloAction::Place { column: column - b'a', row: row - b'1' })
+            },
+            _ => Err(format!("invalid othello action: {}", s)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn othello_action_to_string() {
+        assert_eq!(OthelloAction::Place { column: 3, row: 2 }.to_string(), "d3");
+        assert_eq!(OthelloAction::Place { column: 7, row: 7 }.to_string(), "h8");
+        assert_eq!(OthelloAction::Pass.to_string(), "pass");
+    }
+
+    #[test]
+    fn othello_action_from_str() {
+        assert_eq!("a1".parse::<OthelloAction>(), Ok(OthelloAction::Place { column: 0, row: 0 }));
+        assert_eq!("f5".parse::<OthelloAction>(), Ok(OthelloAction::Place { column: 5, row: 4 }));
+        assert_eq!("pass".parse::<OthelloAction>(), Ok(OthelloAction::Pass));
+        assert!("i1".parse::<OthelloAction>().is_err());
+        assert!("a9".parse::<OthelloAction>().is_err());
+        assert!("a10".parse::<OthelloAction>().is_err());
+        assert!("D3".parse::<OthelloAction>().is_err());
+    }
+
+    #[test]
+    fn mask_numbers_squares_row_by_row() {
+        assert_eq!(OthelloAction::Place { column: 0, row: 0 }.mask(), 1);
+        assert_eq!(OthelloAction::Place { column: 3, row: 2 }.mask(), 1 << 19);
+        assert_eq!(OthelloAction::Pass.mask(), 0);
+    }
+}
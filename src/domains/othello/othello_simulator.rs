@@ -0,0 +1,289 @@
+use crate::core::reward::{Reward, ADVERSARIAL_DRAW, ADVERSARIAL_P1_LOSS, ADVERSARIAL_P1_WIN};
+use crate::core::imperfect_info::ImperfectInfoSimulator;
+use crate::core::simulator::{Simulator, LegalActions};
+
+use super::othello_action::OthelloAction;
+use super::othello_state::{BOARD_SIZE, OthelloState};
+
+use rand_chacha::ChaCha8Rng;
+
+const N_PLAYERS: usize = 2;
+
+/// Every square except those in column a.
+const NOT_COLUMN_A: u64 = 0xfefe_fefe_fefe_fefe;
+/// Every square except those in column h.
+const NOT_COLUMN_H: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+/// The 8 directions as shifts of a bit board, with the columns
+/// masked out so that lines never wrap from one row to the next.
+const DIRECTIONS: [fn(u64) -> u64; 8] = [
+    |x| (x << 1) & NOT_COLUMN_A,
+    |x| (x >> 1) & NOT_COLUMN_H,
+    |x| x << BOARD_SIZE,
+    |x| x >> BOARD_SIZE,
+    |x| (x << (BOARD_SIZE + 1)) & NOT_COLUMN_A,
+    |x| (x << (BOARD_SIZE - 1)) & NOT_COLUMN_H,
+    |x| (x >> (BOARD_SIZE - 1)) & NOT_COLUMN_A,
+    |x| (x >> (BOARD_SIZE + 1)) & NOT_COLUMN_H,
+];
+
+/// Players take turns placing discs of their color so that one or more
+/// straight lines of the opponent's discs are enclosed between the new
+/// disc and another disc of their own, which flips the enclosed discs.
+/// A player who cannot place a disc passes, and the game ends when
+/// neither player can, with the player owning more discs winning.
+#[derive(Clone, Debug)]
+pub struct OthelloSimulator;
+
+impl Default for OthelloSimulator {
+    fn default() -> Self {
+        OthelloSimulator::new()
+    }
+}
+
+impl OthelloSimulator {
+    pub fn new() -> Self {
+        OthelloSimulator
+    }
+}
+
+/// The empty squares where a player can place a disc.
+///
+/// ### Arguments
+///
+/// * `player` - The discs of the player placing a disc.
+/// * `opponent` - The discs of the other player.
+///
+/// ### Return Value
+///
+/// A bit board of the squares that enclose at least one opponent disc.
+pub fn moves(player: u64, opponent: u64) -> u64 {
+    let empty = !(player | opponent);
+    let mut moves = 0;
+    for shift in DIRECTIONS {
+        // A line holds at most 6 opponent discs between two of the player's.
+        let mut line = shift(player) & opponent;
+        for _ in 0..BOARD_SIZE - 3 {
+            line |= shift(line) & opponent;
+        }
+        moves |= shift(line) & empty;
+    }
+    return moves;
+}
+
+/// The opponent discs flipped by placing a disc.
+///
+/// ### Arguments
+///
+/// * `player` - The discs of the player placing a disc.
+/// * `opponent` - The discs of the other player.
+/// * `square` - The bit of the square the disc is placed on.
+///
+/// ### Return Value
+///
+/// A bit board of the opponent discs enclosed by the new disc.
+pub fn flips(player: u64, opponent: u64, square: u64) -> u64 {
+    let mut flips = 0;
+    for shift in DIRECTIONS {
+        let mut line = 0;
+        let mut next = shift(square);
+        while next & opponent != 0 {
+            line |= next;
+            next = shift(next);
+        }
+        if next & player != 0 {
+            flips |= line;
+        }
+    }
+    return flips;
+}
+
+impl Simulator<OthelloState, OthelloAction> for OthelloSimulator {
+    fn generate_initial_state(&mut self) -> OthelloState {
+        OthelloState::new()
+    }
+
+    /// Rewards are decided by counting the discs at the end of the game.
+    fn calculate_rewards(&mut self, state: &OthelloState) -> Vec<Reward> {
+        if !self.is_terminal_state(state) {
+            return ADVERSARIAL_DRAW.to_vec();
+        }
+        return match state.disc_count(0).cmp(&state.disc_count(1)) {
+            std::cmp::Ordering::Greater => ADVERSARIAL_P1_WIN.to_vec(),
+            std::cmp::Ordering::Less => ADVERSARIAL_P1_LOSS.to_vec(),
+            std::cmp::Ordering::Equal => ADVERSARIAL_DRAW.to_vec(),
+        };
+    }
+
+    /// The player to move can place a disc on any square that flips at
+    /// least one opponent disc. If there is none they must pass, unless
+    /// the opponent cannot place a disc either, which ends the game.
+    fn calculate_legal_actions(&mut self, state: &OthelloState) -> Vec<LegalActions<OthelloAction>> {
+        let mut legal_actions = vec![LegalActions::<OthelloAction>::new(); N_PLAYERS];
+        let player = state.bit_board[state.turn];
+        let opponent = state.bit_board[1 - state.turn];
+        let mut squares = moves(player, opponent);
+        if squares == 0 {
+            if moves(opponent, player) != 0 {
+                legal_actions[state.turn].insert(OthelloAction::Pass);
+            }
+            return legal_actions;
+        }
+        while squares != 0 {
+            let square = squares.trailing_zeros() as usize;
+            legal_actions[state.turn].insert(OthelloAction::Place {
+                column: (square % BOARD_SIZE) as u8,
+                row: (square / BOARD_SIZE) as u8,
+            });
+            squares &= squares - 1;
+        }
+        return legal_actions;
+    }
+
+    fn state_transition(&mut self, state: &OthelloState, actions: &Vec<Option<OthelloAction>>) -> OthelloState {
+        self.check_valid_state_transition(state, actions).unwrap();
+        let mut state = state.clone();
+        let player_id = state.turn;
+        let square = actions[player_id].unwrap().mask();
+        let flips = flips(state.bit_board[player_id], state.bit_board[1 - player_id], square);
+        state.bit_board[player_id] |= square | flips;
+        state.bit_board[1 - player_id] &= !flips;
+        state.turn = 1 - player_id;
+        return state;
+    }
+
+    fn number_of_players(&mut self) -> usize {
+        2
+    }
+}
+
+impl ImperfectInfoSimulator<OthelloState, OthelloAction, OthelloState> for OthelloSimulator {
+    fn observe(&mut self, state: &OthelloState, _player_id: usize) -> OthelloState {
+        state.clone()
+    }
+
+    fn sample_determinization(&mut self, observation: &OthelloState, _player_id: usize, _rng: &mut ChaCha8Rng) -> OthelloState {
+        observation.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::perft::perft;
+
+    use super::*;
+
+    crate::simulator_conformance_tests!(othello_conformance, |_rng| OthelloSimulator::new());
+
+    fn play(simulator: &mut OthelloSimulator, state: &OthelloState, moves: &str) -> OthelloState {
+        let mut state = state.clone();
+        for action in moves.split_whitespace() {
+            let mut actions = vec![None; N_PLAYERS];
+            actions[state.turn] = Some(action.parse().unwrap());
+            state = simulator.state_transition(&state, &actions);
+        }
+        return state;
+    }
+
+    fn actions(legal_actions: &LegalActions<OthelloAction>) -> Vec<String> {
+        let mut actions = legal_actions.iter().map(|action| action.to_string()).collect::<Vec<String>>();
+        actions.sort();
+        return actions;
+    }
+
+    #[test]
+    fn perft_initial_state() {
+        let mut simulator = OthelloSimulator::new();
+        let state = simulator.generate_initial_state();
+        for (depth, expected) in [1, 4, 12, 56, 244, 1396, 8200].into_iter().enumerate() {
+            assert_eq!(perft(&mut simulator, &state, depth), expected, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn opening_moves_flip_discs() {
+        let mut simulator = OthelloSimulator::new();
+        let initial = simulator.generate_initial_state();
+        assert_eq!(actions(&simulator.calculate_legal_actions(&initial)[0]), ["c4", "d3", "e6", "f5"]);
+        let state = play(&mut simulator, &initial, "d3 c3");
+        let expected = "--------\n\
+                        --------\n\
+                        --OX----\n\
+                        ---OX---\n\
+                        ---XO---\n\
+                        --------\n\
+                        --------\n\
+                        --------\n\
+                        X to move";
+        assert_eq!(state.to_string(), expected);
+    }
+
+    #[test]
+    fn flips_lines_in_every_direction() {
+        let state = "--------\n\
+                     -OOO----\n\
+                     -OXO----\n\
+                     -OOO----\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     X to move".parse::<OthelloState>().unwrap();
+        let player = state.bit_board[0];
+        let opponent = state.bit_board[1];
+        assert_eq!(moves(player, opponent).count_ones(), 8);
+        // Lines of opponent discs that reach the edge are not flipped.
+        let flipped = flips(player, opponent, state.mask(0, 0)) | flips(player, opponent, state.mask(2, 0));
+        assert_eq!(flipped, state.mask(1, 1) | state.mask(2, 1));
+        assert_eq!(flips(player, opponent, state.mask(7, 7)), 0);
+    }
+
+    #[test]
+    fn lines_do_not_wrap_between_rows() {
+        let state = "-------O\n\
+                     X-------\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     ----O--X\n\
+                     X to move".parse::<OthelloState>().unwrap();
+        assert_eq!(moves(state.bit_board[0], state.bit_board[1]), 0);
+    }
+
+    #[test]
+    fn player_without_moves_passes() {
+        let mut simulator = OthelloSimulator::new();
+        let state = "XO------\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     --------\n\
+                     O to move".parse::<OthelloState>().unwrap();
+        let legal_actions = simulator.calculate_legal_actions(&state);
+        assert_eq!(actions(&legal_actions[1]), ["pass"]);
+        assert!(legal_actions[0].0.is_empty());
+        let state = play(&mut simulator, &state, "pass");
+        assert_eq!(actions(&simulator.calculate_legal_actions(&state)[0]), ["c1"]);
+        let state = play(&mut simulator, &state, "c1");
+        assert!(simulator.is_terminal_state(&state));
+        assert_eq!(state.disc_count(0), 3);
+        assert_eq!(simulator.calculate_rewards(&state), ADVERSARIAL_P1_WIN.to_vec());
+    }
+
+    #[test]
+    fn rewards_count_discs() {
+        let mut simulator = OthelloSimulator::new();
+        let lost = format!("{}{}X to move", "XXXXXXXX\n".repeat(3), "OOOOOOOO\n".repeat(5)).parse::<OthelloState>().unwrap();
+        assert!(simulator.is_terminal_state(&lost));
+        assert_eq!(simulator.calculate_rewards(&lost), ADVERSARIAL_P1_LOSS.to_vec());
+        let drawn = format!("{}{}X to move", "XXXXXXXX\n".repeat(4), "OOOOOOOO\n".repeat(4)).parse::<OthelloState>().unwrap();
+        assert_eq!(simulator.calculate_rewards(&drawn), ADVERSARIAL_DRAW.to_vec());
+        let initial = simulator.generate_initial_state();
+        assert_eq!(simulator.calculate_rewards(&initial), ADVERSARIAL_DRAW.to_vec());
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt::{Display, self};
+use std::str::FromStr;
+
+use crate::core::imperfect_info::Observation;
+use crate::core::simulator::State;
+
+/// The number of rows and columns.
+pub const BOARD_SIZE: usize = 8;
+
+/// Othello board state, with the first player's black discs in
+/// `bit_board[0]` and the second player's white discs in `bit_board[1]`.
+///
+/// Squares are numbered row by row from the top left corner, so the
+/// bit of column `c` and row `r` is `r * 8 + c`:
+///  0  1  2  3  4  5  6  7 Row 1
+///  8  9 10 11 12 13 14 15 Row 2
+/// ...
+/// 56 57 58 59 60 61 62 63 Row 8
+///
+/// A player without a move passes, so the player to move
+/// cannot be counted from the discs and is stored instead.
+#[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
+pub struct OthelloState {
+    pub bit_board: [u64; 2],
+    /// The ID of the player to move.
+    pub turn: usize,
+}
+
+impl OthelloState {
+    /// The starting position, with two discs of each color
+    /// in the center and black to move.
+    pub fn new() -> Self {
+        let mut state = OthelloState { bit_board: [0, 0], turn: 0 };
+        state.bit_board[0] = state.mask(4, 3) | state.mask(3, 4);
+        state.bit_board[1] = state.mask(3, 3) | state.mask(4, 4);
+        return state;
+    }
+
+    /// The bit of a square, where rows are numbered from the top.
+    pub fn mask(&self, column: usize, row: usize) -> u64 {
+        return 1 << (row * BOARD_SIZE + column);
+    }
+
+    pub fn empty(&self) -> u64 {
+        return !(self.bit_board[0] | self.bit_board[1]);
+    }
+
+    pub fn disc_count(&self, player_id: usize) -> u32 {
+        return self.bit_board[player_id].count_ones();
+    }
+}
+
+impl Default for OthelloState {
+    fn default() -> Self {
+        OthelloState::new()
+    }
+}
+
+impl State for OthelloState {}
+
+/// The board is fully visible to every player.
+impl Observation for OthelloState {}
+
+/// Writes the board from row 1 down with black discs as `X`
+/// and white discs as `O`, followed by the player to move.
+impl Display for OthelloState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..BOARD_SIZE {
+            for column in 0..BOARD_SIZE {
+                let mask = self.mask(column, row);
+                if (self.bit_board[0] & mask) != 0 {
+                    write!(f, "X")?;
+                } else if (self.bit_board[1] & mask) != 0 {
+                    write!(f, "O")?;
+                } else {
+                    write!(f, "-")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        write!(f, "{} to move", if self.turn == 0 { 'X' } else { 'O' })
+    }
+}
+
+impl FromStr for OthelloState {
+    type Err = String;
+
+    /// Parses a state in the format written by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().collect::<Vec<&str>>();
+        if lines.len() != BOARD_SIZE + 1 {
+            return Err(format!("expected {} lines, found {}", BOARD_SIZE + 1, lines.len()));
+        }
+        let mut state = OthelloState { bit_board: [0, 0], turn: 0 };
+        for (row, line) in lines[..BOARD_SIZE].iter().enumerate() {
+            let squares = line.chars().collect::<Vec<char>>();
+            if squares.len() != BOARD_SIZE {
+                return Err(format!("expected {} columns, found {}", BOARD_SIZE, squares.len()));
+            }
+            for (column, square) in squares.iter().enumerate() {
+                let mask = state.mask(column, row);
+                match square {
+                    'X' => state.bit_board[0] |= mask,
+                    'O' => state.bit_board[1] |= mask,
+                    '-' => {},
+                    _ => return Err(format!("invalid square: {}", square)),
+                }
+            }
+        }
+        state.turn = match lines[BOARD_SIZE] {
+            "X to move" => 0,
+            "O to move" => 1,
+            status => return Err(format!("invalid status: {}", status)),
+        };
+        return Ok(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn othello_state_to_string_and_from_str() {
+        let state = OthelloState::new();
+        let expected = "--------\n\
+                        --------\n\
+                        --------\n\
+                        ---OX---\n\
+                        ---XO---\n\
+                        --------\n\
+                        --------\n\
+                        --------\n\
+                        X to move";
+        assert_eq!(state.to_string(), expected);
+        assert_eq!(expected.parse::<OthelloState>(), Ok(state));
+    }
+
+    #[test]
+    fn othello_state_from_str_invalid() {
+        let board = "--------\n".repeat(BOARD_SIZE);
+        assert!(format!("{}O to move", board).parse::<OthelloState>().is_ok());
+        assert!(format!("{}Y to move", board).parse::<OthelloState>().is_err());
+        assert!(format!("{}--------\nX to move", board).parse::<OthelloState>().is_err());
+        assert!(format!("{}X to move", board.replacen('-', "Y", 1)).parse::<OthelloState>().is_err());
+        assert!(format!("{}X to move", board.replacen('-', "", 1)).parse::<OthelloState>().is_err());
+    }
+
+    #[test]
+    fn disc_count_counts_each_color() {
+        let mut state = OthelloState::new();
+        state.bit_board[0] |= state.mask(0, 0);
+        assert_eq!((state.disc_count(0), state.disc_count(1)), (3, 2));
+        assert_eq!(state.empty().count_ones(), 59);
+    }
+}
@@ -1,2 +1,9 @@
 pub const N_DICE: usize = 5;
-pub const N_VALUES: usize = 6;
\ No newline at end of file
+pub const N_VALUES: usize = 6;
+pub const BONUS_THRESHOLD: u16 = 63;
+pub const BONUS_SCORE: u16 = 35;
+pub const FULL_HOUSE_SCORE: u16 = 25;
+pub const SMALL_STRAIGHT_SCORE: u16 = 30;
+pub const LARGE_STRAIGHT_SCORE: u16 = 40;
+pub const YAHTZEE_SCORE: u16 = 50;
+pub const YAHTZEE_BONUS: u16 = 100;
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::str::FromStr;
 
 use crate::core::simulator::Action;
 
@@ -29,6 +30,24 @@ impl fmt::Display for YahtzeeAction {
     }
 }
 
+impl FromStr for YahtzeeAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            Some(dice) => {
+                let values = dice.split_whitespace()
+                    .map(|x| x.parse::<u8>().map_err(|_| format!("invalid dice count: {}", x)))
+                    .collect::<Result<Vec<u8>, String>>()?;
+                let selected: [u8; N_VALUES] = values.try_into()
+                    .map_err(|_| format!("expected {} dice counts: {}", N_VALUES, s))?;
+                Ok(YahtzeeAction::SelectDice { selected })
+            },
+            None => Ok(YahtzeeAction::SelectCategory { score_category: s.parse()? }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -46,4 +65,23 @@ mod tests {
         };
         assert_eq!(action.to_string(), "Ones");
     }
+
+    #[test]
+    fn yahtzee_action_select_dice_from_str() {
+        let action = YahtzeeAction::SelectDice { selected: [1, 0, 2, 0, 0, 1] };
+        assert_eq!("[1 0 2 0 0 1]".parse::<YahtzeeAction>(), Ok(action));
+    }
+
+    #[test]
+    fn yahtzee_action_select_category_from_str() {
+        let action = YahtzeeAction::SelectCategory {
+            score_category: YahtzeeScoreCategory::Chance,
+        };
+        assert_eq!("Chance".parse::<YahtzeeAction>(), Ok(action));
+    }
+
+    #[test]
+    fn yahtzee_action_select_dice_from_str_wrong_length() {
+        assert!("[1 0 2]".parse::<YahtzeeAction>().is_err());
+    }
 }
@@ -1,5 +1,9 @@
+use std::str::FromStr;
+
 use enum_ordinalize::Ordinalize;
 
+use super::constants::{N_DICE, N_VALUES, FULL_HOUSE_SCORE, SMALL_STRAIGHT_SCORE, LARGE_STRAIGHT_SCORE, YAHTZEE_SCORE, YAHTZEE_BONUS};
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Ordinalize)]
 pub enum YahtzeeScoreCategory {
     Ones,
@@ -16,3 +20,63 @@ pub enum YahtzeeScoreCategory {
     Yahtzee,
     Chance,
 }
+
+impl YahtzeeScoreCategory {
+    /// The highest score that can be recorded in this category.
+    /// A Yahtzee category includes a bonus for every
+    /// additional Yahtzee scored in the other categories.
+    pub fn max_score(&self) -> u16 {
+        let max_dice_sum = (N_DICE * N_VALUES) as u16;
+        match self {
+            YahtzeeScoreCategory::Ones |
+            YahtzeeScoreCategory::Twos |
+            YahtzeeScoreCategory::Threes |
+            YahtzeeScoreCategory::Fours |
+            YahtzeeScoreCategory::Fives |
+            YahtzeeScoreCategory::Sixes => (N_DICE as u16) * (self.ordinal() as u16 + 1),
+            YahtzeeScoreCategory::ThreeOfKind |
+            YahtzeeScoreCategory::FourOfKind |
+            YahtzeeScoreCategory::Chance => max_dice_sum,
+            YahtzeeScoreCategory::FullHouse => FULL_HOUSE_SCORE,
+            YahtzeeScoreCategory::SmallStraight => SMALL_STRAIGHT_SCORE,
+            YahtzeeScoreCategory::LargeStraight => LARGE_STRAIGHT_SCORE,
+            YahtzeeScoreCategory::Yahtzee => YAHTZEE_SCORE + YAHTZEE_BONUS * (YahtzeeScoreCategory::variant_count() as u16 - 1),
+        }
+    }
+}
+
+impl FromStr for YahtzeeScoreCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        YahtzeeScoreCategory::variants().into_iter()
+            .find(|category| format!("{:?}", category) == s)
+            .ok_or(format!("invalid score category: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_score_upper_section() {
+        assert_eq!(YahtzeeScoreCategory::Ones.max_score(), 5);
+        assert_eq!(YahtzeeScoreCategory::Sixes.max_score(), 30);
+    }
+
+    #[test]
+    fn max_score_yahtzee_includes_bonuses() {
+        assert_eq!(YahtzeeScoreCategory::Yahtzee.max_score(), 1250);
+    }
+
+    #[test]
+    fn yahtzee_score_category_from_str() {
+        assert_eq!("FullHouse".parse::<YahtzeeScoreCategory>(), Ok(YahtzeeScoreCategory::FullHouse));
+    }
+
+    #[test]
+    fn yahtzee_score_category_from_str_invalid() {
+        assert!("Sevens".parse::<YahtzeeScoreCategory>().is_err());
+    }
+}
@@ -1,26 +1,19 @@
 use std::collections::HashSet;
 
-use rand::{SeedableRng, RngCore};
+use rand::{RngCore, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 
-use crate::core::{reward::Reward, simulator::{LegalActions, Simulator}};
+use crate::core::{imperfect_info::ImperfectInfoSimulator, reward::Reward, simulator::{LegalActions, Simulator}};
 
-use super::{yahtzee_state::YahtzeeState, yahtzee_action::YahtzeeAction, yahtzee_score_category::YahtzeeScoreCategory, constants::{N_VALUES, N_DICE}};
+use super::{yahtzee_state::YahtzeeState, yahtzee_action::YahtzeeAction, yahtzee_score_category::YahtzeeScoreCategory, constants::{N_VALUES, N_DICE, FULL_HOUSE_SCORE, SMALL_STRAIGHT_SCORE, LARGE_STRAIGHT_SCORE, YAHTZEE_SCORE, YAHTZEE_BONUS}};
 
-const BONUS_THRESHOLD: u16 = 63;
-const BONUS_SCORE: u16 = 35;
-const FULL_HOUSE_SCORE: u16 = 25;
-const SMALL_STRAIGHT_SCORE: u16 = 30;
-const LARGE_STRAIGHT_SCORE: u16 = 40;
-const YAHTZEE_SCORE: u16 = 50;
-const YAHTZEE_BONUS: u16 = 100;
-
-pub struct YahtzeeSimulator<'a> {
-    rng: &'a mut ChaCha8Rng,
+#[derive(Clone, Debug)]
+pub struct YahtzeeSimulator {
+    rng: ChaCha8Rng,
 }
 
-impl <'a> YahtzeeSimulator<'a> {
-    pub fn new(rng: &'a mut ChaCha8Rng) -> Self {
+impl YahtzeeSimulator {
+    pub fn new(rng: ChaCha8Rng) -> Self {
         YahtzeeSimulator { rng }
     }
 }
@@ -34,31 +27,25 @@ fn roll_dice(rng: &mut ChaCha8Rng) -> [u8; N_VALUES] {
     dice_values
 }
 
-impl <'a> Simulator<YahtzeeState, YahtzeeAction> for YahtzeeSimulator<'a> {
+impl Simulator<YahtzeeState, YahtzeeAction> for YahtzeeSimulator {
     
     fn generate_initial_state(&mut self) -> YahtzeeState {
         YahtzeeState {
-            dice_values: roll_dice(self.rng),
+            dice_values: roll_dice(&mut self.rng),
             roll_number: 1,
             scores: [None; YahtzeeScoreCategory::variant_count()],
         }
     }
 
     fn calculate_rewards(&mut self, state: &YahtzeeState) -> Vec<Reward> {
-        let mut score = 0u16;
-        if !state.has_categories_left() {
-            let scores = state.scores.iter().map(|&x| x.unwrap_or(0) as u16).collect::<Vec<u16>>();
-            for i in 0..N_VALUES {
-                score += scores[i];
-            }
-            if score >= BONUS_THRESHOLD {
-                score += BONUS_SCORE;
-            }
-            for i in N_VALUES..scores.len() {
-                score += scores[i];
-            }
-        }
-        return vec![Reward(score as isize)];
+        let score = if state.has_categories_left() { 0 } else { state.total_score() };
+        return vec![Reward(score as f64)];
+    }
+
+    /// The points scored by the transition, so that the step rewards
+    /// of a game add up to its final score.
+    fn step_rewards(&mut self, prev: &YahtzeeState, _actions: &[Option<YahtzeeAction>], next: &YahtzeeState) -> Vec<Reward> {
+        return vec![Reward(next.total_score() as f64 - prev.total_score() as f64)];
     }
 
     fn calculate_legal_actions(&mut self, state: &YahtzeeState) -> Vec<LegalActions<YahtzeeAction>> {
@@ -79,16 +66,18 @@ impl <'a> Simulator<YahtzeeState, YahtzeeAction> for YahtzeeSimulator<'a> {
                     }
                 }
             } else {
-                let yahtzee = state.check_yahtzee();
-                if yahtzee == None || state.scores[yahtzee.unwrap()] != None {
-                    (0..YahtzeeScoreCategory::variant_count())
-                        .filter(|&x| state.scores[x] == None)
-                        .for_each(|x| { legal_actions.0.insert(YahtzeeAction::SelectCategory { score_category: YahtzeeScoreCategory::from_ordinal(x as i8).unwrap() }); } );
-                } else {
-                    legal_actions.0.insert(YahtzeeAction::SelectCategory { score_category: YahtzeeScoreCategory::from_ordinal(yahtzee.unwrap() as i8).unwrap() });
-                    if state.scores[YahtzeeScoreCategory::Yahtzee.ordinal() as usize] == None {
-                        legal_actions.0.insert(YahtzeeAction::SelectCategory { score_category: YahtzeeScoreCategory::Yahtzee });
-                    }
+                match state.check_yahtzee() {
+                    Some(yahtzee) if state.scores[yahtzee].is_none() => {
+                        legal_actions.0.insert(YahtzeeAction::SelectCategory { score_category: YahtzeeScoreCategory::from_ordinal(yahtzee as i8).unwrap() });
+                        if state.scores[YahtzeeScoreCategory::Yahtzee.ordinal() as usize].is_none() {
+                            legal_actions.0.insert(YahtzeeAction::SelectCategory { score_category: YahtzeeScoreCategory::Yahtzee });
+                        }
+                    },
+                    _ => {
+                        (0..YahtzeeScoreCategory::variant_count())
+                            .filter(|&x| state.scores[x].is_none())
+                            .for_each(|x| { legal_actions.0.insert(YahtzeeAction::SelectCategory { score_category: YahtzeeScoreCategory::from_ordinal(x as i8).unwrap() }); } );
+                    },
                 }
             }
         }
@@ -97,30 +86,30 @@ impl <'a> Simulator<YahtzeeState, YahtzeeAction> for YahtzeeSimulator<'a> {
 
     fn state_transition(&mut self, state: &YahtzeeState, actions: &Vec<Option<YahtzeeAction>>) -> YahtzeeState {
         let action = &actions[0].as_ref().unwrap();
-        self.check_valid_state_transition(state, &actions).unwrap();
+        self.check_valid_state_transition(state, actions).unwrap();
 
         let mut dice_values = state.dice_values;
         let mut rolls = state.roll_number;
         let mut scores = state.scores;
-        let yahtzee = state.check_yahtzee();
-        if yahtzee != None {
-            let yahtzee_score = scores[YahtzeeScoreCategory::Yahtzee.ordinal() as usize].unwrap();
-            if yahtzee_score >= YAHTZEE_SCORE {
-                scores[YahtzeeScoreCategory::Yahtzee.ordinal() as usize] = Some(yahtzee_score + YAHTZEE_BONUS);
-            }
-        }
 
         match action {
             YahtzeeAction::SelectDice { selected } => {
                 dice_values = *selected;
                 let num_selected = dice_values.iter().sum::<u8>() as usize;
                 for _ in num_selected..N_DICE {
-                    let roll = self.rng.next_u32() as usize % N_DICE;
+                    let roll = self.rng.next_u32() as usize % N_VALUES;
                     dice_values[roll] += 1;
                 }
                 rolls += 1;
             },
             YahtzeeAction::SelectCategory { score_category } => {
+                if state.check_yahtzee().is_some() {
+                    if let Some(yahtzee_score) = scores[YahtzeeScoreCategory::Yahtzee.ordinal() as usize] {
+                        if yahtzee_score >= YAHTZEE_SCORE {
+                            scores[YahtzeeScoreCategory::Yahtzee.ordinal() as usize] = Some(yahtzee_score + YAHTZEE_BONUS);
+                        }
+                    }
+                }
                 let score = match score_category {
                     YahtzeeScoreCategory::Ones => score_ones(&dice_values),
                     YahtzeeScoreCategory::Twos => score_twos(&dice_values),
@@ -137,17 +126,32 @@ impl <'a> Simulator<YahtzeeState, YahtzeeAction> for YahtzeeSimulator<'a> {
                     YahtzeeScoreCategory::Chance => score_chance(&dice_values),
                 };
                 scores[score_category.ordinal() as usize] = Some(score);
-                let rng = &mut ChaCha8Rng::from_entropy();
-                dice_values = roll_dice(rng);
+                dice_values = roll_dice(&mut self.rng);
                 rolls = 1;
             }
         }
-        return YahtzeeState { dice_values: dice_values, roll_number: rolls, scores: scores };
+        return YahtzeeState { dice_values, roll_number: rolls, scores };
     }
     
     fn number_of_players(&mut self) -> usize {
         1
     }
+
+    /// Rolls the dice of the copy from a new seed, so that
+    /// searching does not change the rolls of the real game.
+    fn fork(&self, rng: &mut ChaCha8Rng) -> Self {
+        return YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(rng.next_u64()));
+    }
+}
+
+impl ImperfectInfoSimulator<YahtzeeState, YahtzeeAction, YahtzeeState> for YahtzeeSimulator {
+    fn observe(&mut self, state: &YahtzeeState, _player_id: usize) -> YahtzeeState {
+        state.clone()
+    }
+
+    fn sample_determinization(&mut self, observation: &YahtzeeState, _player_id: usize, _rng: &mut ChaCha8Rng) -> YahtzeeState {
+        observation.clone()
+    }
 }
 
 fn score_ones(dice_values: &[u8; N_VALUES]) -> u16 {
@@ -243,3 +247,201 @@ fn score_chance(dice_values: &[u8; N_VALUES]) -> u16 {
 fn sum_of_values(dice_values: &[u8; N_VALUES]) -> u16 {
     return dice_values.iter().sum::<u8>() as u16;
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rand::SeedableRng;
+
+    use crate::core::agent::Agent;
+    use crate::core::game::play_game;
+    use crate::core::ismcts::IsmctsAgent;
+    use crate::core::simulator::{Action, State};
+    use crate::core::testing::random_joint_action;
+
+    use super::super::constants::BONUS_SCORE;
+
+    use super::*;
+
+    crate::simulator_conformance_tests!(conformance, |rng| YahtzeeSimulator::new(rng.clone()));
+
+    fn arbitrary_action() -> impl Strategy<Value = YahtzeeAction> {
+        prop_oneof![
+            prop::array::uniform6(0..=N_DICE as u8)
+                .prop_map(|selected| YahtzeeAction::SelectDice { selected }),
+            (0..YahtzeeScoreCategory::variant_count() as i8)
+                .prop_map(|x| YahtzeeAction::SelectCategory { score_category: YahtzeeScoreCategory::from_ordinal(x).unwrap() }),
+        ]
+    }
+
+    /// Either an arbitrary action or the legal action at an arbitrary index,
+    /// so that games progress far enough to fill categories.
+    fn arbitrary_move() -> impl Strategy<Value = (Option<usize>, YahtzeeAction)> {
+        (prop::option::of(any::<usize>()), arbitrary_action())
+    }
+
+    fn check_state(state: &YahtzeeState) -> Result<(), TestCaseError> {
+        prop_assert_eq!(state.dice_values.iter().sum::<u8>(), N_DICE as u8, "dice count:\n{}", state);
+        prop_assert!((1..=3).contains(&state.roll_number), "roll number:\n{}", state);
+        for (category, score) in YahtzeeScoreCategory::variants().iter().zip(state.scores) {
+            if let Some(score) = score {
+                prop_assert!(score <= category.max_score(), "{:?} score out of range:\n{}", category, state);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn step_rewards_add_up_to_final_score() {
+        let mut playout_rng = ChaCha8Rng::seed_from_u64(1);
+        let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+        let mut state = simulator.generate_initial_state();
+        let mut total = 0.0;
+        while !simulator.is_terminal_state(&state) {
+            let legal_actions = simulator.calculate_legal_actions(&state);
+            let actions = random_joint_action(&legal_actions, &mut playout_rng);
+            let next_state = simulator.state_transition(&state, &actions);
+            total += simulator.step_rewards(&state, &actions, &next_state)[0].0;
+            state = next_state;
+        }
+        assert_eq!(simulator.calculate_rewards(&state), vec![Reward(total)]);
+    }
+
+    #[test]
+    fn step_rewards_include_bonuses() {
+        let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+        let mut scores = [None; YahtzeeScoreCategory::variant_count()];
+        scores[..N_VALUES].copy_from_slice(&[Some(3), Some(6), Some(9), Some(12), Some(15), None]);
+        scores[YahtzeeScoreCategory::Yahtzee.ordinal() as usize] = Some(YAHTZEE_SCORE);
+        let state = YahtzeeState { dice_values: [0, 0, 0, 0, 0, 5], roll_number: 3, scores };
+        let actions = vec![Some(YahtzeeAction::SelectCategory { score_category: YahtzeeScoreCategory::Sixes })];
+        let next_state = simulator.state_transition(&state, &actions);
+        let expected = 30 + BONUS_SCORE + YAHTZEE_BONUS;
+        assert_eq!(simulator.step_rewards(&state, &actions, &next_state), vec![Reward(expected as f64)]);
+    }
+
+    #[test]
+    fn yahtzee_bonus_added_only_when_scoring() {
+        let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+        let yahtzee = YahtzeeScoreCategory::Yahtzee.ordinal() as usize;
+        let mut scores = [None; YahtzeeScoreCategory::variant_count()];
+        scores[yahtzee] = Some(YAHTZEE_SCORE);
+        let state = YahtzeeState { dice_values: [0, 0, 0, 0, 0, 5], roll_number: 1, scores };
+        let keep = vec![Some(YahtzeeAction::SelectDice { selected: [0, 0, 0, 0, 0, 5] })];
+        assert_eq!(simulator.state_transition(&state, &keep).scores, scores);
+        let state = YahtzeeState { roll_number: 3, ..state };
+        let score = vec![Some(YahtzeeAction::SelectCategory { score_category: YahtzeeScoreCategory::Sixes })];
+        assert_eq!(simulator.state_transition(&state, &score).scores[yahtzee], Some(YAHTZEE_SCORE + YAHTZEE_BONUS));
+    }
+
+    #[test]
+    fn yahtzee_with_unscored_yahtzee_category() {
+        let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+        let scores = [None; YahtzeeScoreCategory::variant_count()];
+        let state = YahtzeeState { dice_values: [5, 0, 0, 0, 0, 0], roll_number: 1, scores };
+        let keep = vec![Some(YahtzeeAction::SelectDice { selected: [5, 0, 0, 0, 0, 0] })];
+        assert_eq!(simulator.state_transition(&state, &keep).scores, scores);
+    }
+
+    #[test]
+    fn rerolled_dice_show_every_value() {
+        let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+        let scores = [None; YahtzeeScoreCategory::variant_count()];
+        let state = YahtzeeState { dice_values: [1, 1, 1, 1, 1, 0], roll_number: 1, scores };
+        let reroll = vec![Some(YahtzeeAction::SelectDice { selected: [0; N_VALUES] })];
+        let mut rolled = [0; N_VALUES];
+        for _ in 0..100 {
+            let next_state = simulator.state_transition(&state, &reroll);
+            rolled.iter_mut().zip(next_state.dice_values).for_each(|(count, dice)| *count += dice);
+        }
+        assert!(rolled.iter().all(|&count| count > 0), "{:?}", rolled);
+    }
+
+    #[test]
+    fn scoring_rolls_dice_with_simulator_rng() {
+        let roll_after_scoring = || {
+            let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+            let mut state = YahtzeeState { dice_values: [1, 1, 1, 1, 1, 0], roll_number: 3, scores: [None; YahtzeeScoreCategory::variant_count()] };
+            let mut rolls = vec![];
+            for score_category in [YahtzeeScoreCategory::Ones, YahtzeeScoreCategory::Twos, YahtzeeScoreCategory::Threes] {
+                state = simulator.state_transition(&state, &vec![Some(YahtzeeAction::SelectCategory { score_category })]);
+                rolls.push(state.dice_values);
+                state.roll_number = 3;
+            }
+            rolls
+        };
+        assert_eq!(roll_after_scoring(), roll_after_scoring());
+    }
+
+    #[test]
+    fn searching_does_not_change_rolls() {
+        let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+        let mut searched = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+        let state = simulator.generate_initial_state();
+        assert_eq!(searched.generate_initial_state(), state);
+        let mut agent = IsmctsAgent::new(100, 100.0, ChaCha8Rng::seed_from_u64(1));
+        let _: YahtzeeAction = Agent::select_action(&mut agent, 0, &state, &mut searched);
+        assert_eq!(searched.generate_initial_state(), simulator.generate_initial_state());
+    }
+
+    /// Selects the first legal action by name, after rolling
+    /// the dice of its simulator if `rolls` is set.
+    struct FirstActionAgent {
+        rolls: bool,
+    }
+
+    impl <S : State, A : Action> Agent<S, A> for FirstActionAgent {
+        fn select_action<I>(&mut self, player_id: usize, state: &S, simulator: &mut I) -> A where
+            I: Simulator<S, A>,
+        {
+            if self.rolls {
+                simulator.generate_initial_state();
+            }
+            let legal_actions = simulator.calculate_legal_actions(state).swap_remove(player_id);
+            return legal_actions.0.into_iter().min_by_key(|action| action.to_string()).unwrap();
+        }
+    }
+
+    #[test]
+    fn agents_cannot_change_rolls() {
+        let play = |rolls: bool| {
+            let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+            let history = play_game(&mut simulator, &mut [FirstActionAgent { rolls }], &mut []);
+            return history.states().into_iter().cloned().collect::<Vec<YahtzeeState>>();
+        };
+        assert_eq!(play(true), play(false));
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_actions_preserve_scores(seed in any::<u64>(), moves in prop::collection::vec(arbitrary_move(), 0..200)) {
+            let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(seed));
+            let mut state = simulator.generate_initial_state();
+            let max_reward = YahtzeeScoreCategory::variants().iter().map(|x| x.max_score()).sum::<u16>() + BONUS_SCORE;
+            for (legal_index, action) in moves {
+                let legal_actions = simulator.calculate_legal_actions(&state).remove(0);
+                let action = match legal_index {
+                    Some(index) if !legal_actions.0.is_empty() => legal_actions.iter().nth(index % legal_actions.0.len()).unwrap().clone(),
+                    _ => action,
+                };
+                let is_legal = legal_actions.0.contains(&action);
+                let filled = state.scores.iter().filter(|x| x.is_some()).count();
+                match simulator.try_state_transition(&state, &vec![Some(action.clone())]) {
+                    Ok(next_state) => {
+                        prop_assert!(is_legal, "illegal action {} accepted:\n{}", action, state);
+                        let next_filled = next_state.scores.iter().filter(|x| x.is_some()).count();
+                        match action {
+                            YahtzeeAction::SelectDice { .. } => prop_assert_eq!(next_filled, filled),
+                            YahtzeeAction::SelectCategory { .. } => prop_assert_eq!(next_filled, filled + 1),
+                        }
+                        state = next_state;
+                    },
+                    Err(_) => prop_assert!(!is_legal, "legal action {} rejected:\n{}", action, state),
+                }
+                check_state(&state)?;
+                let reward = simulator.calculate_rewards(&state)[0];
+                prop_assert!((0.0..=max_reward as f64).contains(&reward.0));
+            }
+        }
+    }
+}
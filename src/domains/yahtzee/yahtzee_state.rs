@@ -1,8 +1,11 @@
 use std::fmt;
+use std::str::FromStr;
 
+use crate::core::imperfect_info::Observation;
 use crate::core::simulator::State;
+use crate::core::tt::{zobrist_key, ZobristHash};
 
-use super::{yahtzee_score_category::YahtzeeScoreCategory, constants::{N_VALUES, N_DICE}};
+use super::{yahtzee_score_category::YahtzeeScoreCategory, constants::{N_VALUES, N_DICE, BONUS_THRESHOLD, BONUS_SCORE}};
 
 #[derive(Clone, fmt::Debug, Hash, PartialEq, Eq)]
 pub struct YahtzeeState {
@@ -20,17 +23,82 @@ impl YahtzeeState {
     pub fn check_yahtzee(&self) -> Option<usize> {
         (0..N_VALUES).find(|&i| { self.dice_values[i] == N_DICE as u8 })
     }
+
+    /// The score of the filled categories, including the upper section
+    /// bonus once it has been reached and any Yahtzee bonuses.
+    pub fn total_score(&self) -> u16 {
+        let scores = self.scores.iter().map(|&x| x.unwrap_or(0)).collect::<Vec<u16>>();
+        let mut score = scores[..N_VALUES].iter().sum::<u16>();
+        if score >= BONUS_THRESHOLD {
+            score += BONUS_SCORE;
+        }
+        return score + scores[N_VALUES..].iter().sum::<u16>();
+    }
 }
 
 impl State for YahtzeeState {}
 
+/// The dice and scores are fully visible to the player.
+impl Observation for YahtzeeState {}
+
+/// Zobrist features are numbered by dice value and count, then by
+/// roll number and then by score category and score.
+impl ZobristHash for YahtzeeState {
+    fn zobrist_hash(&self) -> u64 {
+        let mut hash = zobrist_roll(self.roll_number);
+        for (value, &count) in self.dice_values.iter().enumerate() {
+            hash ^= zobrist_dice(value, count);
+        }
+        for (category, &score) in self.scores.iter().enumerate() {
+            hash ^= zobrist_score(category, score);
+        }
+        return hash;
+    }
+
+    fn updated_zobrist_hash(&self, mut hash: u64, next: &Self) -> u64 {
+        if self.roll_number != next.roll_number {
+            hash ^= zobrist_roll(self.roll_number) ^ zobrist_roll(next.roll_number);
+        }
+        for value in 0..N_VALUES {
+            if self.dice_values[value] != next.dice_values[value] {
+                hash ^= zobrist_dice(value, self.dice_values[value]) ^ zobrist_dice(value, next.dice_values[value]);
+            }
+        }
+        for category in 0..self.scores.len() {
+            if self.scores[category] != next.scores[category] {
+                hash ^= zobrist_score(category, self.scores[category]) ^ zobrist_score(category, next.scores[category]);
+            }
+        }
+        return hash;
+    }
+}
+
+const ZOBRIST_ROLL_FEATURES: u64 = (N_VALUES * (N_DICE + 1)) as u64;
+const ZOBRIST_SCORE_FEATURES: u64 = 1 << 32;
+
+fn zobrist_dice(value: usize, count: u8) -> u64 {
+    zobrist_key((value * (N_DICE + 1)) as u64 + count as u64)
+}
+
+fn zobrist_roll(roll_number: u8) -> u64 {
+    zobrist_key(ZOBRIST_ROLL_FEATURES + roll_number as u64)
+}
+
+/// Unfilled categories are not part of the hash.
+fn zobrist_score(category: usize, score: Option<u16>) -> u64 {
+    match score {
+        Some(score) => zobrist_key(ZOBRIST_SCORE_FEATURES + ((category as u64) << 16) + score as u64),
+        None => 0,
+    }
+}
+
 impl fmt::Display for YahtzeeState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} - [ ", self.roll_number)?;
         for dice_value in self.dice_values {
             write!(f, "{} ", dice_value)?;
         }
-        write!(f, "]\n")?;
+        writeln!(f, "]")?;
         for i in 0..self.scores.len() {
             let score_categories = YahtzeeScoreCategory::variants();
             let category_name = &score_categories[i];
@@ -40,15 +108,54 @@ impl fmt::Display for YahtzeeState {
             };
             write!(f, "{:?}: {}", category_name, score)?;
             if i != self.scores.len() - 1 {
-                write!(f, "\n")?;
+                writeln!(f)?;
             }
         }
         Ok(())
     }
 }
 
+impl FromStr for YahtzeeState {
+    type Err = String;
+
+    /// Parses a state in the format written by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let header = lines.next().ok_or("missing dice line")?;
+        let (roll_number, dice) = header.split_once(" - ").ok_or(format!("invalid dice line: {}", header))?;
+        let roll_number = roll_number.parse::<u8>().map_err(|_| format!("invalid roll number: {}", roll_number))?;
+        let dice_values = dice.strip_prefix('[').and_then(|x| x.strip_suffix(']'))
+            .ok_or(format!("invalid dice line: {}", header))?
+            .split_whitespace()
+            .map(|x| x.parse::<u8>().map_err(|_| format!("invalid dice count: {}", x)))
+            .collect::<Result<Vec<u8>, String>>()?
+            .try_into()
+            .map_err(|_| format!("expected {} dice counts: {}", N_VALUES, header))?;
+        let mut scores = [None; YahtzeeScoreCategory::variant_count()];
+        for (i, category) in YahtzeeScoreCategory::variants().iter().enumerate() {
+            let line = lines.next().ok_or(format!("missing score for {:?}", category))?;
+            let score = line.strip_prefix(&format!("{:?}: ", category))
+                .ok_or(format!("invalid score line: {}", line))?;
+            scores[i] = match score {
+                "-" => None,
+                _ => Some(score.parse::<u16>().map_err(|_| format!("invalid score: {}", score))?),
+            };
+        }
+        if let Some(line) = lines.next() {
+            return Err(format!("unexpected line: {}", line));
+        }
+        return Ok(YahtzeeState { dice_values, roll_number, scores });
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::core::testing::{random_playout, DEFAULT_MAX_STEPS};
+    use crate::domains::yahtzee::yahtzee_simulator::YahtzeeSimulator;
+
     use super::*;
 
     #[test]
@@ -71,6 +178,47 @@ mod tests {
         assert!(!state.has_categories_left());
     }
 
+    #[test]
+    fn total_score_includes_upper_bonus() {
+        let mut state = YahtzeeState {
+            dice_values: [0, 0, 5, 0, 0, 0],
+            roll_number: 1,
+            scores: [None; YahtzeeScoreCategory::variant_count()],
+        };
+        state.scores[..N_VALUES].copy_from_slice(&[Some(3), Some(6), Some(9), Some(12), Some(15), Some(18)]);
+        assert_eq!(state.total_score(), 63 + BONUS_SCORE);
+        state.scores[0] = Some(2);
+        state.scores[YahtzeeScoreCategory::Chance.ordinal() as usize] = Some(20);
+        assert_eq!(state.total_score(), 82);
+    }
+
+    #[test]
+    fn zobrist_hash_updates_incrementally() {
+        let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(0));
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let states = random_playout(&mut simulator, &mut rng, DEFAULT_MAX_STEPS).unwrap();
+        let mut hash = states[0].zobrist_hash();
+        for pair in states.windows(2) {
+            hash = pair[0].updated_zobrist_hash(hash, &pair[1]);
+            assert_eq!(hash, pair[1].zobrist_hash());
+        }
+    }
+
+    #[test]
+    fn zobrist_hash_depends_on_scores() {
+        let mut state = YahtzeeState {
+            dice_values: [1, 0, 1, 1, 2, 0],
+            roll_number: 1,
+            scores: [None; YahtzeeScoreCategory::variant_count()],
+        };
+        let empty_hash = state.zobrist_hash();
+        state.scores[0] = Some(0);
+        let zero_hash = state.zobrist_hash();
+        state.scores[0] = Some(1);
+        assert_ne!(empty_hash, zero_hash);
+        assert_ne!(zero_hash, state.zobrist_hash());
+    }
+
     #[test]
     fn check_yahtzee_none() {
         let state = YahtzeeState {
@@ -102,4 +250,23 @@ mod tests {
         
         assert_eq!(state.to_string(), expected);
     }
+
+    #[test]
+    fn yahtzee_state_from_str() {
+        let mut scores = [None; YahtzeeScoreCategory::variant_count()];
+        scores[YahtzeeScoreCategory::Twos.ordinal() as usize] = Some(6);
+        let state = YahtzeeState {
+            dice_values: [1, 0, 2, 0, 0, 2],
+            roll_number: 2,
+            scores,
+        };
+        let input = "2 - [ 1 0 2 0 0 2 ]\nOnes: -\nTwos: 6\nThrees: -\nFours: -\nFives: -\nSixes: -\nThreeOfKind: -\nFourOfKind: -\nFullHouse: -\nSmallStraight: -\nLargeStraight: -\nYahtzee: -\nChance: -";
+
+        assert_eq!(input.parse::<YahtzeeState>(), Ok(state));
+    }
+
+    #[test]
+    fn yahtzee_state_from_str_missing_category() {
+        assert!("1 - [ 0 0 0 0 0 0 ]\nOnes: -".parse::<YahtzeeState>().is_err());
+    }
 }
@@ -1,9 +1,8 @@
-use abstract_game_engine::core::agent::Agent;
 use abstract_game_engine::core::agent::DefaultAgents;
-use abstract_game_engine::core::simulator::Simulator;
-use abstract_game_engine::domains::connect4::connect4_action::Connect4Action;
+use abstract_game_engine::core::game::play_game;
+use abstract_game_engine::core::observer::GameObserver;
+use abstract_game_engine::core::simulator::{Action, Simulator, State};
 use abstract_game_engine::domains::connect4::connect4_simulator::Connect4Simulator;
-use abstract_game_engine::domains::yahtzee::yahtzee_action::YahtzeeAction;
 use abstract_game_engine::domains::yahtzee::yahtzee_simulator::YahtzeeSimulator;
 use rand::SeedableRng;
 use rand_chacha::ChaCha8Rng;
@@ -16,56 +15,37 @@ fn main() {
 
     println!("{domain}");
 
+    let mut console = ConsoleObserver;
     let rewards = match domain {
         Domain::Connect4 => {
             let mut simulator = Connect4Simulator::new();
-            let mut current_state = simulator.generate_initial_state();
             let mut agents: Vec<DefaultAgents> = select_agents(simulator.number_of_players());
-
-            while !simulator.is_terminal_state(&current_state) {
-                println!("{}", current_state);
-                let mut selected_actions: Vec<Option<Connect4Action>> = Vec::new();
-                let player_legal_actions = simulator.calculate_legal_actions(&current_state);
-                for player_id in 0..simulator.number_of_players() {
-                    if player_legal_actions[player_id].0.is_empty() {
-                        selected_actions.push(None);
-                    } else {
-                        let action = agents[player_id].select_action(player_id, &current_state, &mut simulator);
-                        selected_actions.push(Some(action));
-                    }
-                }
-                current_state = simulator.state_transition(&current_state, &selected_actions);
-            }
-            simulator.calculate_rewards(&current_state)
+            play_game(&mut simulator, &mut agents, &mut [&mut console]).outcome().unwrap().to_vec()
         }
         Domain::Yahtzee => {
             let seed = select_seed();
-            let mut rng = ChaCha8Rng::seed_from_u64(seed);
-            let mut simulator = YahtzeeSimulator::new(&mut rng);
-            let mut current_state = simulator.generate_initial_state();
+            let mut simulator = YahtzeeSimulator::new(ChaCha8Rng::seed_from_u64(seed));
             let mut agents: Vec<DefaultAgents> = select_agents(simulator.number_of_players());
-            
-            while !simulator.is_terminal_state(&current_state) {
-                println!("{}", current_state);
-                let mut selected_actions: Vec<Option<YahtzeeAction>> = Vec::new();
-                let player_legal_actions = simulator.calculate_legal_actions(&current_state);
-                for player_id in 0..simulator.number_of_players() {
-                    if player_legal_actions[player_id].0.is_empty() {
-                        selected_actions.push(None);
-                    } else {
-                        let action = agents[player_id].select_action(player_id, &current_state, &mut simulator);
-                        selected_actions.push(Some(action));
-                    }
-                }
-                current_state = simulator.state_transition(&current_state, &selected_actions);
-            }
-            simulator.calculate_rewards(&current_state)
+            play_game(&mut simulator, &mut agents, &mut [&mut console]).outcome().unwrap().to_vec()
         }
     };
     
     print!("Game Over - {:?}", rewards);
 }
 
+/// Prints each state of the game as it is reached.
+struct ConsoleObserver;
+
+impl <S : State, A : Action> GameObserver<S, A> for ConsoleObserver {
+    fn on_game_start(&mut self, state: &S) {
+        println!("{}", state);
+    }
+
+    fn on_state_transition(&mut self, _state: &S, _actions: &[Option<A>], next_state: &S) {
+        println!("{}", next_state);
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Domain {
     Connect4,
@@ -82,7 +62,7 @@ impl fmt::Display for Domain {
 }
 
 fn select_domain() -> Domain {
-    let domains = vec![Domain::Connect4, Domain::Yahtzee];
+    let domains = [Domain::Connect4, Domain::Yahtzee];
     let mut input = String::new();
 
     println!("Select domain:");
@@ -100,7 +80,7 @@ fn select_domain() -> Domain {
             Err(_) => None,
         };
         match choice {
-            Some(domain) => break domain.clone(),
+            Some(domain) => break *domain,
             None => {
                 println!("Invalid input: {}", input);
                 input.clear();
@@ -112,16 +92,14 @@ fn select_domain() -> Domain {
 fn select_seed() -> u64 {
     let mut input = String::new();
     println!("Select a seed (u64 value, or press enter to select random seed):");
-    loop {
-        io::stdin().read_line(&mut input).unwrap();
-        match input.trim().parse::<u64>() {
-            Ok(seed) => break seed,
-            Err(_) => {
-                let seed = rand::random::<u64>();
-                println!("Selecting a random seed = {seed}");
-                break seed;
-            },
-        }
+    io::stdin().read_line(&mut input).unwrap();
+    match input.trim().parse::<u64>() {
+        Ok(seed) => seed,
+        Err(_) => {
+            let seed = rand::random::<u64>();
+            println!("Selecting a random seed = {seed}");
+            seed
+        },
     }
 }
 
@@ -143,11 +121,7 @@ fn select_agent() -> DefaultAgents {
     loop {
         io::stdin().read_line(&mut input).unwrap();
         let value = input.trim().parse::<usize>();
-        let choice = match value {
-            Ok(value) => Some(value),
-            Err(_) => None,
-        };
-        match choice {
+        match value.ok() {
             Some(1) => break DefaultAgents::Random(ChaCha8Rng::from_entropy()),
             Some(2) => break DefaultAgents::Io,
             Some(_) | None => {